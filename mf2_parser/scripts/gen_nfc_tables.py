@@ -0,0 +1,55 @@
+"""Generate mf2_parser/src/normalization/tables.rs from the Unicode data bundled with Python.
+
+Usage: python3 scripts/gen_nfc_tables.py > src/normalization/tables.rs
+"""
+import unicodedata, sys
+ud = unicodedata
+ccc = []
+decomp = []
+comp = []
+for cp in range(0x110000):
+    if 0xD800 <= cp <= 0xDFFF: continue
+    c = chr(cp)
+    k = ud.combining(c)
+    if k:
+        ccc.append((cp, k))
+    d = ud.decomposition(c)
+    if d and not d.startswith('<'):
+        if 0xAC00 <= cp <= 0xD7A3: continue
+        parts = [int(x, 16) for x in d.split()]
+        decomp.append((cp, parts))
+        if len(parts) == 2 and ud.normalize('NFC', chr(parts[0]) + chr(parts[1])) == c:
+            comp.append((parts[0], parts[1], cp))
+# compress ccc into ranges
+ranges = []
+for cp, k in ccc:
+    if ranges and ranges[-1][1] == cp - 1 and ranges[-1][2] == k:
+        ranges[-1][1] = cp
+    else:
+        ranges.append([cp, cp, k])
+comp.sort()
+out = []
+out.append("// Generated from the Unicode Character Database %s by `scripts/gen_nfc_tables.py`." % ud.unidata_version)
+out.append("// Do not edit by hand.")
+out.append("")
+out.append("/// Canonical combining class ranges as `(first, last, class)`, sorted by code point.")
+out.append("pub(super) static CANONICAL_COMBINING_CLASS: &[(u32, u32, u8)] = &[")
+for a, b, k in ranges:
+    out.append("    (0x%X, 0x%X, %d)," % (a, b, k))
+out.append("];")
+out.append("")
+out.append("/// Single-level canonical decomposition mappings, sorted by code point.")
+out.append("/// Hangul syllables are decomposed algorithmically and are not listed.")
+out.append("pub(super) static CANONICAL_DECOMPOSITION: &[(u32, u32, u32)] = &[")
+for cp, parts in decomp:
+    second = parts[1] if len(parts) == 2 else 0
+    out.append("    (0x%X, 0x%X, 0x%X)," % (cp, parts[0], second))
+out.append("];")
+out.append("")
+out.append("/// Primary composites as `(first, second, composite)`, sorted by `(first, second)`.")
+out.append("/// Composition exclusions and singleton decompositions are already filtered out.")
+out.append("pub(super) static CANONICAL_COMPOSITION: &[(u32, u32, u32)] = &[")
+for a, b, c in comp:
+    out.append("    (0x%X, 0x%X, 0x%X)," % (a, b, c))
+out.append("];")
+print("\n".join(out))
@@ -23,7 +23,16 @@ impl Error {
         Self {
             start,
             end: end.unwrap_or(start + 1),
-            expected: expected.unwrap_or_else(|| String::new()),
+            expected: expected.unwrap_or_default(),
         }
     }
+    pub fn start(&self) -> usize {
+        self.start
+    }
+    pub fn end(&self) -> usize {
+        self.end
+    }
+    pub fn expected(&self) -> &str {
+        &self.expected
+    }
 }
@@ -1,4 +1,5 @@
 pub mod error;
 pub mod model;
+pub mod normalization;
 pub mod parser;
 mod validators;
@@ -1,44 +1,106 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::normalization::nfc;
 
 // Reference: https://github.com/unicode-org/message-format-wg/tree/main/spec/data-model
+//
+// The data model borrows from the message source: names, literals and text are `Cow` slices
+// into the source string and only own their contents when unescaping or normalization changes them.
+// Use `into_owned` to detach a message from its source, e.g. to store it as `Message<'static>`.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub enum Message {
-    Pattern(PatternMessage),
-    Select(SelectMessage),
+pub enum Message<'a> {
+    Pattern(PatternMessage<'a>),
+    Select(SelectMessage<'a>),
+}
+
+impl Message<'_> {
+    pub fn into_owned(self) -> Message<'static> {
+        match self {
+            Message::Pattern(pattern) => Message::Pattern(pattern.into_owned()),
+            Message::Select(select) => Message::Select(select.into_owned()),
+        }
+    }
+}
+
+fn owned(s: Cow<'_, str>) -> Cow<'static, str> {
+    Cow::Owned(s.into_owned())
+}
+
+fn owned_map<V, W>(
+    map: HashMap<Cow<'_, str>, V>,
+    into_owned: impl Fn(V) -> W,
+) -> HashMap<Cow<'static, str>, W> {
+    map.into_iter()
+        .map(|(k, v)| (owned(k), into_owned(v)))
+        .collect()
+}
+
+fn owned_items(items: Vec<PatternItem<'_>>) -> Vec<PatternItem<'static>> {
+    items.into_iter().map(PatternItem::into_owned).collect()
+}
+
+fn owned_declarations(declarations: Vec<Declaration<'_>>) -> Vec<Declaration<'static>> {
+    declarations
+        .into_iter()
+        .map(Declaration::into_owned)
+        .collect()
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct PatternMessage {
-    declarations: Vec<Declaration>,
-    pub pattern: Vec<PatternItem>,
+pub struct PatternMessage<'a> {
+    declarations: Vec<Declaration<'a>>,
+    pub pattern: Vec<PatternItem<'a>>,
+}
+
+impl Default for PatternMessage<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl PatternMessage {
+impl<'a> PatternMessage<'a> {
     pub fn new() -> Self {
         Self {
             declarations: Vec::new(),
             pattern: Vec::new(),
         }
     }
-    pub fn set_declarations(&mut self, declarations: Vec<Declaration>) {
+    pub fn set_declarations(&mut self, declarations: Vec<Declaration<'a>>) {
         self.declarations = declarations;
     }
-    pub fn set_pattern(&mut self, pattern: Vec<PatternItem>) {
+    pub fn set_pattern(&mut self, pattern: Vec<PatternItem<'a>>) {
         self.pattern = pattern;
     }
-    pub fn pattern(&self) -> &Vec<PatternItem> {
+    pub fn pattern(&self) -> &Vec<PatternItem<'a>> {
         &self.pattern
     }
+    pub fn declarations(&self) -> &Vec<Declaration<'a>> {
+        &self.declarations
+    }
+    pub fn into_owned(self) -> PatternMessage<'static> {
+        PatternMessage {
+            declarations: owned_declarations(self.declarations),
+            pattern: owned_items(self.pattern),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct SelectMessage {
-    declarations: Vec<Declaration>,
-    selectors: Vec<VariableRef>,
-    variants: Vec<Variant>,
+pub struct SelectMessage<'a> {
+    declarations: Vec<Declaration<'a>>,
+    selectors: Vec<VariableRef<'a>>,
+    variants: Vec<Variant<'a>>,
 }
 
-impl SelectMessage {
+impl Default for SelectMessage<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> SelectMessage<'a> {
     pub fn new() -> Self {
         Self {
             declarations: Vec::new(),
@@ -46,36 +108,74 @@ impl SelectMessage {
             variants: Vec::new(),
         }
     }
-    pub fn set_declarations(&mut self, declarations: Vec<Declaration>) {
+    pub fn set_declarations(&mut self, declarations: Vec<Declaration<'a>>) {
         self.declarations = declarations;
     }
-    pub fn set_selectors(&mut self, selectors: Vec<VariableRef>) {
+    pub fn set_selectors(&mut self, selectors: Vec<VariableRef<'a>>) {
         self.selectors = selectors;
     }
-    pub fn set_variants(&mut self, variants: Vec<Variant>) {
+    pub fn set_variants(&mut self, variants: Vec<Variant<'a>>) {
         self.variants = variants;
     }
+    pub fn selectors(&self) -> &Vec<VariableRef<'a>> {
+        &self.selectors
+    }
+    pub fn variants(&self) -> &Vec<Variant<'a>> {
+        &self.variants
+    }
+    pub fn declarations(&self) -> &Vec<Declaration<'a>> {
+        &self.declarations
+    }
+    pub fn into_owned(self) -> SelectMessage<'static> {
+        SelectMessage {
+            declarations: owned_declarations(self.declarations),
+            selectors: self
+                .selectors
+                .into_iter()
+                .map(VariableRef::into_owned)
+                .collect(),
+            variants: self.variants.into_iter().map(Variant::into_owned).collect(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Variant {
-    keys: Vec<VariantKey>,
-    value: Vec<PatternItem>,
+pub struct Variant<'a> {
+    keys: Vec<VariantKey<'a>>,
+    value: Vec<PatternItem<'a>>,
+}
+
+impl Default for Variant<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl Variant {
+impl<'a> Variant<'a> {
     pub fn new() -> Self {
         Self {
             keys: Vec::new(),
             value: Vec::new(),
         }
     }
-    pub fn set_keys(&mut self, keys: Vec<VariantKey>) {
+    pub fn set_keys(&mut self, keys: Vec<VariantKey<'a>>) {
         self.keys = keys;
     }
-    pub fn set_value(&mut self, value: Vec<PatternItem>) {
+    pub fn set_value(&mut self, value: Vec<PatternItem<'a>>) {
         self.value = value;
     }
+    pub fn keys(&self) -> &Vec<VariantKey<'a>> {
+        &self.keys
+    }
+    pub fn value(&self) -> &Vec<PatternItem<'a>> {
+        &self.value
+    }
+    pub fn into_owned(self) -> Variant<'static> {
+        Variant {
+            keys: self.keys.into_iter().map(VariantKey::into_owned).collect(),
+            value: owned_items(self.value),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -83,6 +183,12 @@ pub struct CatchallKey {
     value: Option<String>,
 }
 
+impl Default for CatchallKey {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl CatchallKey {
     pub fn new() -> Self {
         Self { value: None }
@@ -90,216 +196,401 @@ impl CatchallKey {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct InputDeclaration {
-    name: String,
-    value: VariableExpression,
+pub struct InputDeclaration<'a> {
+    name: Cow<'a, str>,
+    value: VariableExpression<'a>,
 }
 
-impl InputDeclaration {
+impl Default for InputDeclaration<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> InputDeclaration<'a> {
     pub fn new() -> Self {
         Self {
-            name: String::new(),
+            name: Cow::Borrowed(""),
             value: VariableExpression::new(),
         }
     }
-    pub fn set_name(&mut self, name: String) {
+    pub fn set_name(&mut self, name: Cow<'a, str>) {
         self.name = name;
     }
-    pub fn set_value(&mut self, value: VariableExpression) {
+    pub fn set_value(&mut self, value: VariableExpression<'a>) {
         self.value = value;
     }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn value(&self) -> &VariableExpression<'a> {
+        &self.value
+    }
+    pub fn into_owned(self) -> InputDeclaration<'static> {
+        InputDeclaration {
+            name: owned(self.name),
+            value: self.value.into_owned(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct LocalDeclaration {
-    name: String,
-    value: Expression,
+pub struct LocalDeclaration<'a> {
+    name: Cow<'a, str>,
+    value: Expression<'a>,
 }
 
-impl LocalDeclaration {
-    pub fn new(value: Expression) -> Self {
+impl<'a> LocalDeclaration<'a> {
+    pub fn new(value: Expression<'a>) -> Self {
         Self {
-            name: String::new(),
+            name: Cow::Borrowed(""),
             value,
         }
     }
-    pub fn set_name(&mut self, name: String) {
+    pub fn set_name(&mut self, name: Cow<'a, str>) {
         self.name = name;
     }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn value(&self) -> &Expression<'a> {
+        &self.value
+    }
+    pub fn into_owned(self) -> LocalDeclaration<'static> {
+        LocalDeclaration {
+            name: owned(self.name),
+            value: self.value.into_owned(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Markup {
-    name: String,
+pub struct Markup<'a> {
+    name: Cow<'a, str>,
     pub kind: MarkupKind,
-    options: HashMap<String, OptionValue>,
-    attributes: HashMap<String, AttributeValue>,
+    options: HashMap<Cow<'a, str>, OptionValue<'a>>,
+    attributes: HashMap<Cow<'a, str>, AttributeValue<'a>>,
+}
+
+impl Default for Markup<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl Markup {
+impl<'a> Markup<'a> {
     pub fn new() -> Self {
         Self {
-            name: String::new(),
+            name: Cow::Borrowed(""),
             kind: MarkupKind::Open,
             options: HashMap::new(),
             attributes: HashMap::new(),
         }
     }
-    pub fn set_name(&mut self, name: String) {
+    pub fn set_name(&mut self, name: Cow<'a, str>) {
         self.name = name;
     }
     pub fn set_kind(&mut self, kind: MarkupKind) {
         self.kind = kind;
     }
-    pub fn set_options(&mut self, options: HashMap<String, OptionValue>) {
+    pub fn set_options(&mut self, options: HashMap<Cow<'a, str>, OptionValue<'a>>) {
         self.options = options;
     }
-    pub fn set_attributes(&mut self, attributes: HashMap<String, AttributeValue>) {
+    pub fn set_attributes(&mut self, attributes: HashMap<Cow<'a, str>, AttributeValue<'a>>) {
         self.attributes = attributes;
     }
+    pub fn into_owned(self) -> Markup<'static> {
+        Markup {
+            name: owned(self.name),
+            kind: self.kind,
+            options: owned_map(self.options, OptionValue::into_owned),
+            attributes: owned_map(self.attributes, AttributeValue::into_owned),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Literal {
-    pub value: String,
+pub struct Literal<'a> {
+    pub value: Cow<'a, str>,
 }
 
-impl Literal {
-    pub fn new(value: String) -> Self {
-        Self { value }
+impl<'a> Literal<'a> {
+    pub fn new(value: impl Into<Cow<'a, str>>) -> Self {
+        Self {
+            value: value.into(),
+        }
     }
 
-    pub fn normalize(&self) -> String {
-        self.value.clone()
-        // TODO: Implement normalization logic
+    /// Returns the value in Unicode Normalization Form C, as used for key matching.
+    pub fn normalize(&self) -> Cow<'a, str> {
+        match &self.value {
+            Cow::Borrowed(value) => nfc(value),
+            Cow::Owned(value) => Cow::Owned(nfc(value).into_owned()),
+        }
+    }
+
+    pub fn into_owned(self) -> Literal<'static> {
+        Literal {
+            value: owned(self.value),
+        }
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct VariableRef {
-    name: String,
+pub struct VariableRef<'a> {
+    name: Cow<'a, str>,
 }
 
-impl VariableRef {
+impl Default for VariableRef<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> VariableRef<'a> {
     pub fn new() -> Self {
         Self {
-            name: String::new(),
+            name: Cow::Borrowed(""),
         }
     }
-    pub fn set_name(&mut self, name: String) {
+    pub fn set_name(&mut self, name: Cow<'a, str>) {
         self.name = name;
     }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn into_owned(self) -> VariableRef<'static> {
+        VariableRef {
+            name: owned(self.name),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct LiteralExpression {
-    arg: Literal,
-    function: Option<FunctionRef>,
-    attributes: HashMap<String, AttributeValue>,
+pub struct LiteralExpression<'a> {
+    arg: Literal<'a>,
+    function: Option<FunctionRef<'a>>,
+    attributes: HashMap<Cow<'a, str>, AttributeValue<'a>>,
+    /// Byte range of the expression in the source, including the braces
+    span: Range<usize>,
+}
+
+impl Default for LiteralExpression<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl LiteralExpression {
+impl<'a> LiteralExpression<'a> {
     pub fn new() -> Self {
         Self {
-            arg: Literal::new(String::new()),
+            arg: Literal::new(""),
             function: None,
             attributes: HashMap::new(),
+            span: 0..0,
         }
     }
-    pub fn set_literal(&mut self, literal: Literal) {
+    pub fn set_literal(&mut self, literal: Literal<'a>) {
         self.arg = literal;
     }
-    pub fn set_function(&mut self, function: FunctionRef) {
+    pub fn literal(&self) -> &Literal<'a> {
+        &self.arg
+    }
+    pub fn function(&self) -> Option<&FunctionRef<'a>> {
+        self.function.as_ref()
+    }
+    pub fn set_function(&mut self, function: FunctionRef<'a>) {
         self.function = Some(function);
     }
-    pub fn set_attributes(&mut self, attributes: HashMap<String, AttributeValue>) {
+    pub fn set_attributes(&mut self, attributes: HashMap<Cow<'a, str>, AttributeValue<'a>>) {
         self.attributes = attributes;
     }
+    pub fn set_span(&mut self, span: Range<usize>) {
+        self.span = span;
+    }
+    pub fn span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+    pub fn into_owned(self) -> LiteralExpression<'static> {
+        LiteralExpression {
+            arg: self.arg.into_owned(),
+            function: self.function.map(FunctionRef::into_owned),
+            attributes: owned_map(self.attributes, AttributeValue::into_owned),
+            span: self.span,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct VariableExpression {
-    arg: VariableRef,
-    function: Option<FunctionRef>,
-    attributes: HashMap<String, AttributeValue>,
+pub struct VariableExpression<'a> {
+    arg: VariableRef<'a>,
+    function: Option<FunctionRef<'a>>,
+    attributes: HashMap<Cow<'a, str>, AttributeValue<'a>>,
+    /// Byte range of the expression in the source, including the braces
+    span: Range<usize>,
+}
+
+impl Default for VariableExpression<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl VariableExpression {
+impl<'a> VariableExpression<'a> {
     pub fn new() -> Self {
         Self {
             arg: VariableRef::new(),
             function: None,
             attributes: HashMap::new(),
+            span: 0..0,
         }
     }
-    pub fn set_variable_ref(&mut self, variable_ref: VariableRef) {
+    pub fn set_variable_ref(&mut self, variable_ref: VariableRef<'a>) {
         self.arg = variable_ref;
     }
-    pub fn set_function(&mut self, function: FunctionRef) {
+    pub fn set_function(&mut self, function: FunctionRef<'a>) {
         self.function = Some(function);
     }
-    pub fn set_attributes(&mut self, attributes: HashMap<String, AttributeValue>) {
+    pub fn set_attributes(&mut self, attributes: HashMap<Cow<'a, str>, AttributeValue<'a>>) {
         self.attributes = attributes;
     }
-    pub fn get_variable_name(&self) -> String {
-        self.arg.name.clone()
+    pub fn get_variable_name(&self) -> &str {
+        self.arg.name()
+    }
+    pub fn variable_ref(&self) -> &VariableRef<'a> {
+        &self.arg
+    }
+    pub fn function(&self) -> Option<&FunctionRef<'a>> {
+        self.function.as_ref()
+    }
+    pub fn set_span(&mut self, span: Range<usize>) {
+        self.span = span;
+    }
+    pub fn span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+    pub fn into_owned(self) -> VariableExpression<'static> {
+        VariableExpression {
+            arg: self.arg.into_owned(),
+            function: self.function.map(FunctionRef::into_owned),
+            attributes: owned_map(self.attributes, AttributeValue::into_owned),
+            span: self.span,
+        }
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct FunctionExpression {
-    function: FunctionRef,
-    attributes: HashMap<String, AttributeValue>,
+pub struct FunctionExpression<'a> {
+    function: FunctionRef<'a>,
+    attributes: HashMap<Cow<'a, str>, AttributeValue<'a>>,
+    /// Byte range of the expression in the source, including the braces
+    span: Range<usize>,
 }
 
-impl FunctionExpression {
+impl Default for FunctionExpression<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> FunctionExpression<'a> {
     pub fn new() -> Self {
         Self {
             function: FunctionRef::new(),
             attributes: HashMap::new(),
+            span: 0..0,
         }
     }
-    pub fn set_function(&mut self, function: FunctionRef) {
+    pub fn set_function(&mut self, function: FunctionRef<'a>) {
         self.function = function;
     }
-    pub fn set_attributes(&mut self, attributes: HashMap<String, AttributeValue>) {
+    pub fn function(&self) -> &FunctionRef<'a> {
+        &self.function
+    }
+    pub fn set_attributes(&mut self, attributes: HashMap<Cow<'a, str>, AttributeValue<'a>>) {
         self.attributes = attributes;
     }
+    pub fn set_span(&mut self, span: Range<usize>) {
+        self.span = span;
+    }
+    pub fn span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+    pub fn into_owned(self) -> FunctionExpression<'static> {
+        FunctionExpression {
+            function: self.function.into_owned(),
+            attributes: owned_map(self.attributes, AttributeValue::into_owned),
+            span: self.span,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct FunctionRef {
-    pub name: String,
-    options: HashMap<String, OptionValue>,
+pub struct FunctionRef<'a> {
+    pub name: Cow<'a, str>,
+    options: HashMap<Cow<'a, str>, OptionValue<'a>>,
+}
+
+impl Default for FunctionRef<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl FunctionRef {
+impl<'a> FunctionRef<'a> {
     pub fn new() -> Self {
         Self {
-            name: String::new(),
+            name: Cow::Borrowed(""),
             options: HashMap::new(),
         }
     }
-    pub fn set_name(&mut self, name: String) {
+    pub fn set_name(&mut self, name: Cow<'a, str>) {
         self.name = name;
     }
-    pub fn set_options(&mut self, options: HashMap<String, OptionValue>) {
+    pub fn set_options(&mut self, options: HashMap<Cow<'a, str>, OptionValue<'a>>) {
         self.options = options;
     }
+    pub fn options(&self) -> &HashMap<Cow<'a, str>, OptionValue<'a>> {
+        &self.options
+    }
+    pub fn into_owned(self) -> FunctionRef<'static> {
+        FunctionRef {
+            name: owned(self.name),
+            options: owned_map(self.options, OptionValue::into_owned),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub enum OptionValue {
-    Literal(Literal),
-    VariableRef(VariableRef),
+pub enum OptionValue<'a> {
+    Literal(Literal<'a>),
+    VariableRef(VariableRef<'a>),
+}
+
+impl OptionValue<'_> {
+    pub fn into_owned(self) -> OptionValue<'static> {
+        match self {
+            OptionValue::Literal(literal) => OptionValue::Literal(literal.into_owned()),
+            OptionValue::VariableRef(var_ref) => OptionValue::VariableRef(var_ref.into_owned()),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub enum VariantKey {
-    Literal(Literal),
+pub enum VariantKey<'a> {
+    Literal(Literal<'a>),
     CatchallKey(CatchallKey),
 }
 
+impl VariantKey<'_> {
+    pub fn into_owned(self) -> VariantKey<'static> {
+        match self {
+            VariantKey::Literal(literal) => VariantKey::Literal(literal.into_owned()),
+            VariantKey::CatchallKey(key) => VariantKey::CatchallKey(key),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum MarkupKind {
     Open,
@@ -308,27 +599,73 @@ pub enum MarkupKind {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub enum AttributeValue {
-    Literal(String),
+pub enum AttributeValue<'a> {
+    Literal(Cow<'a, str>),
     True(bool),
 }
 
+impl AttributeValue<'_> {
+    pub fn into_owned(self) -> AttributeValue<'static> {
+        match self {
+            AttributeValue::Literal(value) => AttributeValue::Literal(owned(value)),
+            AttributeValue::True(value) => AttributeValue::True(value),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub enum Declaration {
-    Input(InputDeclaration),
-    Local(LocalDeclaration),
+pub enum Declaration<'a> {
+    Input(InputDeclaration<'a>),
+    Local(LocalDeclaration<'a>),
+}
+
+impl Declaration<'_> {
+    pub fn into_owned(self) -> Declaration<'static> {
+        match self {
+            Declaration::Input(input) => Declaration::Input(input.into_owned()),
+            Declaration::Local(local) => Declaration::Local(local.into_owned()),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub enum PatternItem {
-    String(String),
-    Expression(Expression),
-    Markup(Markup),
+pub enum PatternItem<'a> {
+    String(Cow<'a, str>),
+    Expression(Expression<'a>),
+    Markup(Markup<'a>),
+}
+
+impl PatternItem<'_> {
+    pub fn into_owned(self) -> PatternItem<'static> {
+        match self {
+            PatternItem::String(text) => PatternItem::String(owned(text)),
+            PatternItem::Expression(expr) => PatternItem::Expression(expr.into_owned()),
+            PatternItem::Markup(markup) => PatternItem::Markup(markup.into_owned()),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub enum Expression {
-    Literal(LiteralExpression),
-    Variable(VariableExpression),
-    Function(FunctionExpression),
+pub enum Expression<'a> {
+    Literal(LiteralExpression<'a>),
+    Variable(VariableExpression<'a>),
+    Function(FunctionExpression<'a>),
+}
+
+impl Expression<'_> {
+    /// Byte range of the expression in the source, including the braces.
+    pub fn span(&self) -> Range<usize> {
+        match self {
+            Expression::Literal(expr) => expr.span(),
+            Expression::Variable(expr) => expr.span(),
+            Expression::Function(expr) => expr.span(),
+        }
+    }
+    pub fn into_owned(self) -> Expression<'static> {
+        match self {
+            Expression::Literal(expr) => Expression::Literal(expr.into_owned()),
+            Expression::Variable(expr) => Expression::Variable(expr.into_owned()),
+            Expression::Function(expr) => Expression::Function(expr.into_owned()),
+        }
+    }
 }
@@ -0,0 +1,192 @@
+// Unicode Normalization Form C (NFC)
+// Reference: https://www.unicode.org/reports/tr15/
+// MessageFormat 2 compares names and variant keys after NFC normalization:
+// https://github.com/unicode-org/message-format-wg/blob/main/spec/syntax.md#names-and-identifiers
+mod tables;
+
+use std::borrow::Cow;
+
+use tables::{CANONICAL_COMBINING_CLASS, CANONICAL_COMPOSITION, CANONICAL_DECOMPOSITION};
+
+// Hangul syllables are composed and decomposed algorithmically.
+// https://www.unicode.org/versions/latest/ch03.pdf (3.12 Conjoining Jamo Behavior)
+const S_BASE: u32 = 0xAC00;
+const L_BASE: u32 = 0x1100;
+const V_BASE: u32 = 0x1161;
+const T_BASE: u32 = 0x11A7;
+const L_COUNT: u32 = 19;
+const V_COUNT: u32 = 21;
+const T_COUNT: u32 = 28;
+const N_COUNT: u32 = V_COUNT * T_COUNT;
+const S_COUNT: u32 = L_COUNT * N_COUNT;
+
+/// Returns the NFC form of `s`, borrowing `s` if it is already normalized.
+pub fn nfc(s: &str) -> Cow<'_, str> {
+    // ASCII is never affected by normalization
+    if s.is_ascii() {
+        return Cow::Borrowed(s);
+    }
+    let mut chars: Vec<char> = Vec::with_capacity(s.len());
+    for c in s.chars() {
+        decompose(c, &mut chars);
+    }
+    reorder(&mut chars);
+    compose(&mut chars);
+    let normalized: String = chars.into_iter().collect();
+    if normalized == s {
+        Cow::Borrowed(s)
+    } else {
+        Cow::Owned(normalized)
+    }
+}
+
+/// Returns `true` if `s` is already in NFC.
+pub fn is_nfc(s: &str) -> bool {
+    matches!(nfc(s), Cow::Borrowed(_))
+}
+
+fn combining_class(c: char) -> u8 {
+    let cp = c as u32;
+    match CANONICAL_COMBINING_CLASS.binary_search_by(|&(first, last, _)| {
+        if last < cp {
+            core::cmp::Ordering::Less
+        } else if first > cp {
+            core::cmp::Ordering::Greater
+        } else {
+            core::cmp::Ordering::Equal
+        }
+    }) {
+        Ok(i) => CANONICAL_COMBINING_CLASS[i].2,
+        Err(_) => 0,
+    }
+}
+
+fn decompose(c: char, out: &mut Vec<char>) {
+    let cp = c as u32;
+    if (S_BASE..S_BASE + S_COUNT).contains(&cp) {
+        let s_index = cp - S_BASE;
+        let l = L_BASE + s_index / N_COUNT;
+        let v = V_BASE + (s_index % N_COUNT) / T_COUNT;
+        let t = T_BASE + s_index % T_COUNT;
+        out.push(char::from_u32(l).unwrap());
+        out.push(char::from_u32(v).unwrap());
+        if t != T_BASE {
+            out.push(char::from_u32(t).unwrap());
+        }
+        return;
+    }
+    match CANONICAL_DECOMPOSITION.binary_search_by_key(&cp, |&(composite, _, _)| composite) {
+        Ok(i) => {
+            let (_, first, second) = CANONICAL_DECOMPOSITION[i];
+            decompose(char::from_u32(first).unwrap(), out);
+            if second != 0 {
+                decompose(char::from_u32(second).unwrap(), out);
+            }
+        }
+        Err(_) => out.push(c),
+    }
+}
+
+/// Canonical Ordering Algorithm: stable sort each run of non-starters by combining class.
+fn reorder(chars: &mut [char]) {
+    let mut i = 0;
+    while i < chars.len() {
+        if combining_class(chars[i]) == 0 {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < chars.len() && combining_class(chars[i]) != 0 {
+            i += 1;
+        }
+        chars[start..i].sort_by_key(|&c| combining_class(c));
+    }
+}
+
+fn compose_pair(first: char, second: char) -> Option<char> {
+    let (a, b) = (first as u32, second as u32);
+    // L + V -> LV
+    if (L_BASE..L_BASE + L_COUNT).contains(&a) && (V_BASE..V_BASE + V_COUNT).contains(&b) {
+        let lv = S_BASE + ((a - L_BASE) * V_COUNT + (b - V_BASE)) * T_COUNT;
+        return char::from_u32(lv);
+    }
+    // LV + T -> LVT
+    if (S_BASE..S_BASE + S_COUNT).contains(&a)
+        && (a - S_BASE).is_multiple_of(T_COUNT)
+        && (T_BASE + 1..T_BASE + T_COUNT).contains(&b)
+    {
+        return char::from_u32(a + (b - T_BASE));
+    }
+    CANONICAL_COMPOSITION
+        .binary_search_by_key(&(a, b), |&(first, second, _)| (first, second))
+        .ok()
+        .and_then(|i| char::from_u32(CANONICAL_COMPOSITION[i].2))
+}
+
+/// Canonical Composition Algorithm over a decomposed, canonically ordered buffer.
+fn compose(chars: &mut Vec<char>) {
+    let mut starter: Option<usize> = None;
+    let mut last_class: i16 = -1;
+    let mut result: Vec<char> = Vec::with_capacity(chars.len());
+    for &c in chars.iter() {
+        let class = combining_class(c) as i16;
+        if let Some(s) = starter {
+            // a character is blocked if a preceding non-starter has the same or higher class
+            let blocked = last_class != -1 && (last_class == 0 || last_class >= class);
+            if !blocked {
+                if let Some(composite) = compose_pair(result[s], c) {
+                    result[s] = composite;
+                    continue;
+                }
+            }
+        }
+        if class == 0 {
+            starter = Some(result.len());
+            last_class = -1;
+        } else {
+            last_class = class;
+        }
+        result.push(c);
+    }
+    *chars = result;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nfc_composes_decomposed_sequences() {
+        assert_eq!(nfc("e\u{301}"), "\u{E9}");
+        assert_eq!(nfc("cafe\u{301}"), "caf\u{E9}");
+        // Å (A + ring above) composes, Angstrom sign is a singleton and maps to it too
+        assert_eq!(nfc("A\u{30A}"), "\u{C5}");
+        assert_eq!(nfc("\u{212B}"), "\u{C5}");
+    }
+
+    #[test]
+    fn test_nfc_reorders_combining_marks() {
+        // dot below (ccc 220) sorts before dot above (ccc 230) and both compose with s
+        assert_eq!(nfc("s\u{307}\u{323}"), "\u{1E69}");
+        assert_eq!(nfc("s\u{323}\u{307}"), "\u{1E69}");
+    }
+
+    #[test]
+    fn test_nfc_hangul() {
+        assert_eq!(nfc("\u{1112}\u{1161}\u{11AB}"), "\u{D55C}");
+        assert_eq!(nfc("\u{D55C}"), "\u{D55C}");
+    }
+
+    #[test]
+    fn test_nfc_respects_composition_exclusions() {
+        // U+0958 DEVANAGARI LETTER QA is excluded from composition
+        assert_eq!(nfc("\u{958}"), "\u{915}\u{93C}");
+    }
+
+    #[test]
+    fn test_is_nfc() {
+        assert!(is_nfc("hello"));
+        assert!(is_nfc("caf\u{E9}"));
+        assert!(!is_nfc("cafe\u{301}"));
+    }
+}
@@ -1,58 +1,62 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
 
 use crate::error::Error;
 use crate::error::SyntaxError;
 use crate::model;
+use crate::normalization::nfc;
 use crate::validators::is_bidi_char;
 use crate::validators::is_name_char;
 use crate::validators::is_valid_name_string;
 use crate::validators::is_ws_char;
 use crate::validators::trim_tail_ws_and_bidi;
 
+/// Parses a message into a data model that borrows from `src`.
+/// Positions (`pos` and error offsets) are byte offsets into `src`.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Mf2Parser {
-    src: Vec<char>,
+pub struct Mf2Parser<'src> {
+    src: &'src str,
     pos: usize,
 }
 
-impl Mf2Parser {
-    pub fn new(src: &str) -> Self {
-        Self {
-            src: src.chars().collect(),
-            pos: 0,
-        }
+impl<'src> Mf2Parser<'src> {
+    pub fn new(src: &'src str) -> Self {
+        Self { src, pos: 0 }
     }
 
     // Utility methods
-    fn read_whitespaces(&mut self) -> (String, bool) {
-        let mut pos = self.pos;
-        let mut result = String::new();
+    fn rest(&self) -> &'src str {
+        &self.src[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn read_whitespaces(&self) -> (&'src str, bool) {
+        let rest = self.rest();
         let mut is_only_bidi_or_empty = true;
-        loop {
-            if pos == self.src.len() {
-                break;
-            }
-            let c = self.src[pos];
+        let mut len = 0;
+        for c in rest.chars() {
             if !is_ws_char(c) & !is_bidi_char(c) {
                 break;
             }
             if is_ws_char(c) {
                 is_only_bidi_or_empty = false;
             }
-            result.push(c);
-            pos += 1;
+            len += c.len_utf8();
         }
-        (result, is_only_bidi_or_empty)
+        (&rest[..len], is_only_bidi_or_empty)
     }
 
-    fn read_bidis(&mut self) -> String {
-        let mut pos = self.pos;
-        let mut result = String::new();
-        while pos < self.src.len() && is_bidi_char(self.src[pos]) {
-            result.push(self.src[pos]);
-            pos += 1;
-        }
-        result
+    fn read_bidis(&self) -> &'src str {
+        let rest = self.rest();
+        let len = rest
+            .chars()
+            .take_while(|c| is_bidi_char(*c))
+            .map(char::len_utf8)
+            .sum();
+        &rest[..len]
     }
 
     fn skip_whitespaces(&mut self) {
@@ -79,13 +83,13 @@ impl Mf2Parser {
 
     fn skip_whitespaces_required_if_not_followed_by(
         &mut self,
-        expected: Vec<char>,
+        expected: &[char],
     ) -> Result<(), SyntaxError> {
         let (whitespaces, is_only_bidi_or_empty) = self.read_whitespaces();
 
-        let next_char = self.src[self.pos + whitespaces.len()];
+        let next_char = self.src[self.pos + whitespaces.len()..].chars().next();
 
-        if expected.contains(&next_char) {
+        if next_char.is_none_or(|c| expected.contains(&c)) {
             self.pos += whitespaces.len();
             return Ok(());
         }
@@ -99,30 +103,54 @@ impl Mf2Parser {
         Ok(())
     }
 
-    fn read_n_chars(&mut self, n: usize) -> String {
-        let s = &self.src[self.pos..self.pos + n];
-        s.iter().collect()
-    }
-
     fn expect_string(&mut self, expected: &str, consume: bool) -> Result<(), SyntaxError> {
-        expected.chars().enumerate().try_for_each(|(i, c)| {
-            if self.pos + i >= self.src.len() || self.src[self.pos + i] != c {
-                return Err(SyntaxError::MissingSyntax(Error::new(
-                    self.pos + i,
-                    None,
-                    Some(expected.to_string()),
-                )));
-            }
-            Ok(())
-        })?;
+        if !self.rest().starts_with(expected) {
+            return Err(SyntaxError::MissingSyntax(Error::new(
+                self.pos,
+                None,
+                Some(expected.to_string()),
+            )));
+        }
         if consume {
             self.pos += expected.len();
         }
         Ok(())
     }
 
+    /// Returns the text between `start` and the current position, appended to `unescaped`
+    /// if an escape sequence has already been seen. Text without escapes stays borrowed.
+    fn take_text(&self, start: usize, unescaped: Option<String>) -> Cow<'src, str> {
+        let text = &self.src[start..self.pos];
+        match unescaped {
+            Some(mut unescaped) => {
+                unescaped.push_str(text);
+                Cow::Owned(unescaped)
+            }
+            None => Cow::Borrowed(text),
+        }
+    }
+
+    /// Consumes an escape sequence (`\\`, `\{`, `\|` or `\}`) and returns the escaped char.
+    /// Text and literals hold the unescaped value, e.g. `\{` becomes `{`, as the spec defines an
+    /// escape sequence to represent the character after the backslash.
+    /// Reference: https://github.com/unicode-org/message-format-wg/blob/main/spec/syntax.md#escape-sequences
+    fn parse_escaped_char(&mut self) -> Result<char, SyntaxError> {
+        let escaped_char = self.src[self.pos + 1..].chars().next();
+        match escaped_char {
+            Some(c @ ('{' | '}' | '|' | '\u{5C}')) => {
+                self.pos += 2; // consume the escape char and escaped char
+                Ok(c)
+            }
+            _ => Err(SyntaxError::BadEscape(Error::new(
+                self.pos,
+                Some(self.pos + 2),
+                None,
+            ))),
+        }
+    }
+
     // parser methods
-    pub fn parse(&mut self) -> Result<model::Message, SyntaxError> {
+    pub fn parse(&mut self) -> Result<model::Message<'src>, SyntaxError> {
         self.pos = 0; // reset position
         let (declarations, is_match) = self.parse_declarations()?;
         if is_match {
@@ -132,8 +160,18 @@ impl Mf2Parser {
         }
 
         self.skip_whitespaces();
-        let quoted = declarations.len() > 0 && self.read_n_chars(2) == "{{";
-        if !quoted && self.pos > 0 {
+        let quoted = self.rest().starts_with("{{");
+        if !quoted {
+            if !declarations.is_empty() {
+                // A complex message is `*(declaration) complex-body`, so declarations must be
+                // followed by a quoted pattern or a matcher; a simple pattern after them is an error.
+                // Reference: https://github.com/unicode-org/message-format-wg/blob/main/spec/syntax.md#complex-messages
+                return Err(SyntaxError::MissingSyntax(Error::new(
+                    self.pos,
+                    None,
+                    Some("{{".to_string()),
+                )));
+            }
             self.pos = 0
         }
 
@@ -152,39 +190,34 @@ impl Mf2Parser {
         Ok(model::Message::Pattern(pattern_message))
     }
 
-    fn parse_declarations(&mut self) -> Result<(Vec<model::Declaration>, bool), SyntaxError> {
+    fn parse_declarations(&mut self) -> Result<(Vec<model::Declaration<'src>>, bool), SyntaxError> {
         let mut declarations: Vec<model::Declaration> = Vec::new();
         let mut is_match = false;
         self.skip_whitespaces();
-        loop {
-            if self.src[self.pos] == '.' {
-                match self.read_n_chars(6).as_str() {
-                    ".input" => {
-                        self.pos += 6; // consume ".input"
-                        let input_decl = self.parse_input_declaration()?;
-                        declarations.push(model::Declaration::Input(input_decl));
-                    }
-                    ".local" => {
-                        self.pos += 6; // consume ".local"
-                        let local_decl = self.parse_local_declaration()?;
-                        declarations.push(model::Declaration::Local(local_decl));
-                    }
-                    ".match" => {
-                        self.pos += 6; // consume ".match"
-                        is_match = true;
-                        break;
-                    }
-                    _ => return Err(SyntaxError::ParseError(Error::new(self.pos, None, None))), // if message starts with a dot, it must be a declaration or match
-                }
-                self.skip_whitespaces();
-            } else {
+        while self.peek() == Some('.') {
+            let rest = self.rest();
+            if rest.starts_with(".input") {
+                self.pos += 6; // consume ".input"
+                let input_decl = self.parse_input_declaration()?;
+                declarations.push(model::Declaration::Input(input_decl));
+            } else if rest.starts_with(".local") {
+                self.pos += 6; // consume ".local"
+                let local_decl = self.parse_local_declaration()?;
+                declarations.push(model::Declaration::Local(local_decl));
+            } else if rest.starts_with(".match") {
+                self.pos += 6; // consume ".match"
+                is_match = true;
                 break;
+            } else {
+                // if message starts with a dot, it must be a declaration or match
+                return Err(SyntaxError::ParseError(Error::new(self.pos, None, None)));
             }
+            self.skip_whitespaces();
         }
         Ok((declarations, is_match))
     }
 
-    fn parse_input_declaration(&mut self) -> Result<model::InputDeclaration, SyntaxError> {
+    fn parse_input_declaration(&mut self) -> Result<model::InputDeclaration<'src>, SyntaxError> {
         self.skip_whitespaces();
         self.expect_string("{", false)?;
         let value_start = self.pos;
@@ -192,7 +225,7 @@ impl Mf2Parser {
         match value {
             ParsedExpressionOrMarkup::Expression(model::Expression::Variable(var_expr)) => {
                 let mut input_decl = model::InputDeclaration::new();
-                input_decl.set_name(var_expr.get_variable_name());
+                input_decl.set_name(Cow::Owned(var_expr.get_variable_name().to_string()));
                 input_decl.set_value(var_expr);
                 Ok(input_decl)
             }
@@ -203,7 +236,8 @@ impl Mf2Parser {
             ))),
         }
     }
-    fn parse_local_declaration(&mut self) -> Result<model::LocalDeclaration, SyntaxError> {
+
+    fn parse_local_declaration(&mut self) -> Result<model::LocalDeclaration<'src>, SyntaxError> {
         self.skip_whitespaces_required()?;
         self.expect_string("$", true)?;
         let name = self.parse_name()?;
@@ -226,16 +260,16 @@ impl Mf2Parser {
 
     fn parse_select_message(
         &mut self,
-        declarations: Vec<model::Declaration>,
-    ) -> Result<model::SelectMessage, SyntaxError> {
+        declarations: Vec<model::Declaration<'src>>,
+    ) -> Result<model::SelectMessage<'src>, SyntaxError> {
         self.skip_whitespaces_required()?;
         let mut selectors: Vec<model::VariableRef> = Vec::new();
-        while self.src[self.pos] == '$' {
+        while self.peek() == Some('$') {
             selectors.push(self.parse_variable()?);
             self.skip_whitespaces_required()?;
         }
 
-        if selectors.len() == 0 {
+        if selectors.is_empty() {
             return Err(SyntaxError::EmptyToken(Error::new(self.pos, None, None)));
         }
 
@@ -256,7 +290,7 @@ impl Mf2Parser {
     fn parse_expression_or_markup(
         &mut self,
         allow_markup: bool,
-    ) -> Result<ParsedExpressionOrMarkup, SyntaxError> {
+    ) -> Result<ParsedExpressionOrMarkup<'src>, SyntaxError> {
         let start_pos = self.pos;
         let mut is_markup = false;
         self.pos += 1; // consume the opening '{'
@@ -264,15 +298,15 @@ impl Mf2Parser {
 
         let arg = self.parse_value(false)?;
         if arg != ParsedValue::None {
-            self.skip_whitespaces_required_if_not_followed_by(vec!['}'])?;
+            self.skip_whitespaces_required_if_not_followed_by(&['}'])?;
         }
 
         let mut function_ref = model::FunctionRef::new();
         let mut markup = model::Markup::new();
 
-        match self.src[self.pos] {
-            '@' | '}' => {}
-            ':' => {
+        match self.peek() {
+            Some('@' | '}') => {}
+            Some(':') => {
                 // parse function
                 self.pos += 1; // consume the ':'
                 let func_name = self.parse_identifier()?;
@@ -281,7 +315,7 @@ impl Mf2Parser {
                     function_ref.set_options(options);
                 }
             }
-            '#' | '/' => {
+            Some(sigil @ ('#' | '/')) => {
                 // parse markup
                 if !allow_markup || arg != ParsedValue::None {
                     return Err(SyntaxError::ParseError(Error::new(start_pos, None, None)));
@@ -289,7 +323,7 @@ impl Mf2Parser {
 
                 is_markup = true;
                 self.pos += 1; // consume the '#' or '/'
-                let markup_kind = if self.src[self.pos - 1] == '#' {
+                let markup_kind = if sigil == '#' {
                     model::MarkupKind::Open
                 } else {
                     model::MarkupKind::Close
@@ -308,7 +342,7 @@ impl Mf2Parser {
 
         let attributes = self.parse_attributes()?;
 
-        if (is_markup && markup.kind == model::MarkupKind::Open) && self.src[self.pos] == '/' {
+        if (is_markup && markup.kind == model::MarkupKind::Open) && self.peek() == Some('/') {
             markup.set_kind(model::MarkupKind::StandAlone);
             self.pos += 1; // consume the '/'
         }
@@ -324,6 +358,7 @@ impl Mf2Parser {
             ParsedValue::Literal(literal) => {
                 let mut literal_expr = model::LiteralExpression::new();
                 literal_expr.set_literal(literal);
+                literal_expr.set_span(start_pos..self.pos);
                 // FIXME:
                 if !function_ref.name.is_empty() {
                     literal_expr.set_function(function_ref);
@@ -338,6 +373,7 @@ impl Mf2Parser {
             ParsedValue::Variable(var_ref) => {
                 let mut var_expr = model::VariableExpression::new();
                 var_expr.set_variable_ref(var_ref);
+                var_expr.set_span(start_pos..self.pos);
                 // FIXME:
                 if !function_ref.name.is_empty() {
                     var_expr.set_function(function_ref);
@@ -358,6 +394,7 @@ impl Mf2Parser {
                 //
                 let mut func_expr = model::FunctionExpression::new();
                 func_expr.set_function(function_ref);
+                func_expr.set_span(start_pos..self.pos);
                 if let Some(attributes_) = attributes {
                     func_expr.set_attributes(attributes_);
                 }
@@ -369,28 +406,24 @@ impl Mf2Parser {
         }
     }
 
-    pub fn parse_pattern(&mut self, quoted: bool) -> Result<Vec<model::PatternItem>, SyntaxError> {
+    pub fn parse_pattern(
+        &mut self,
+        quoted: bool,
+    ) -> Result<Vec<model::PatternItem<'src>>, SyntaxError> {
         if quoted {
-            if self.read_n_chars(2) == "{{" {
-                self.pos += 2; // consume the opening '{{'
-            } else {
-                return Err(SyntaxError::MissingSyntax(Error::new(
-                    self.pos,
-                    None,
-                    Some("{{".to_string()),
-                )));
-            }
+            self.expect_string("{{", true)?; // consume the opening '{{'
         }
 
         //
         let mut patterns: Vec<model::PatternItem> = Vec::new();
-        let mut text = String::new();
-        while self.pos < self.src.len() {
-            match self.src[self.pos] {
+        let mut text_start = self.pos;
+        let mut unescaped: Option<String> = None;
+        while let Some(c) = self.peek() {
+            match c {
                 '{' => {
+                    let text = self.take_text(text_start, unescaped.take());
                     if !text.is_empty() {
                         patterns.push(model::PatternItem::String(text));
-                        text = String::new();
                     }
                     let expression_or_markup = self.parse_expression_or_markup(true)?;
                     match expression_or_markup {
@@ -401,58 +434,52 @@ impl Mf2Parser {
                             patterns.push(model::PatternItem::Markup(markup));
                         }
                     }
+                    text_start = self.pos;
                 }
                 '}' => {
                     if !quoted {
                         return Err(SyntaxError::ParseError(Error::new(self.pos, None, None)));
                     }
-                    if !text.is_empty() {
-                        patterns.push(model::PatternItem::String(text));
-                        text = String::new();
-                    }
                     break; // end of pattern
                 }
+                '\u{5C}' => {
+                    let mut text = self.take_text(text_start, unescaped.take()).into_owned();
+                    text.push(self.parse_escaped_char()?);
+                    unescaped = Some(text);
+                    text_start = self.pos;
+                }
                 _ => {
-                    text.push(self.src[self.pos]);
-                    self.pos += 1; // consume the char
+                    self.pos += c.len_utf8(); // consume the char
                 }
             }
         }
 
+        let text = self.take_text(text_start, unescaped);
         if !text.is_empty() {
             patterns.push(model::PatternItem::String(text));
         }
 
         //
         if quoted {
-            if self.read_n_chars(2) == "}}" {
-                self.pos += 2; // consume the opening '{{'
-            } else {
-                return Err(SyntaxError::MissingSyntax(Error::new(
-                    self.pos,
-                    None,
-                    Some("}}".to_string()),
-                )));
-            }
+            self.expect_string("}}", true)?; // consume the closing '}}'
         }
 
         Ok(patterns)
     }
 
-    pub fn parse_variant(&mut self) -> Result<model::Variant, SyntaxError> {
+    pub fn parse_variant(&mut self) -> Result<model::Variant<'src>, SyntaxError> {
         let mut keys: Vec<model::VariantKey> = Vec::new();
         while self.pos < self.src.len() {
-            if keys.len() > 0 {
-                self.skip_whitespaces_required()?;
+            if !keys.is_empty() {
+                self.skip_whitespaces_required_if_not_followed_by(&['{'])?;
             } else {
                 self.skip_whitespaces();
             }
-            let next_char = self.src[self.pos];
-            match next_char {
-                '{' => {
+            match self.peek() {
+                None | Some('{') => {
                     break;
                 }
-                '*' => {
+                Some('*') => {
                     self.pos += 1; // consume the '*'
                     keys.push(model::VariantKey::CatchallKey(model::CatchallKey::new()));
                 }
@@ -460,8 +487,9 @@ impl Mf2Parser {
                     let key = self.parse_literal(true)?;
                     match key {
                         Some(literal) => {
-                            literal.normalize();
-                            keys.push(model::VariantKey::Literal(literal));
+                            // keys are matched in NFC, so store them normalized
+                            let normalized = model::Literal::new(literal.normalize());
+                            keys.push(model::VariantKey::Literal(normalized));
                         }
                         None => {
                             return Err(SyntaxError::EmptyToken(Error::new(self.pos, None, None)));
@@ -470,15 +498,19 @@ impl Mf2Parser {
                 }
             }
         }
+        // a variant needs at least one key before its quoted pattern
+        if keys.is_empty() {
+            return Err(SyntaxError::EmptyToken(Error::new(self.pos, None, None)));
+        }
         let mut variant = model::Variant::new();
         variant.set_keys(keys);
         variant.set_value(self.parse_pattern(true)?);
-        return Ok(variant);
+        Ok(variant)
     }
 
-    fn parse_value(&mut self, required: bool) -> Result<ParsedValue, SyntaxError> {
-        match self.src[self.pos] {
-            '$' => Ok(ParsedValue::Variable(self.parse_variable()?)),
+    fn parse_value(&mut self, required: bool) -> Result<ParsedValue<'src>, SyntaxError> {
+        match self.peek() {
+            Some('$') => Ok(ParsedValue::Variable(self.parse_variable()?)),
             _ => {
                 let start_pos = self.pos;
                 let literal = self.parse_literal(required)?;
@@ -496,7 +528,7 @@ impl Mf2Parser {
         }
     }
 
-    fn parse_variable(&mut self) -> Result<model::VariableRef, SyntaxError> {
+    fn parse_variable(&mut self) -> Result<model::VariableRef<'src>, SyntaxError> {
         self.pos += 1; // consume the '$'
         let mut var_ref = model::VariableRef::new();
         let name = self.parse_name()?;
@@ -504,9 +536,12 @@ impl Mf2Parser {
         Ok(var_ref)
     }
 
-    fn parse_literal(&mut self, required: bool) -> Result<Option<model::Literal>, SyntaxError> {
-        if self.src[self.pos] == '|' {
-            return Ok(self.parse_quoted_literal()?);
+    fn parse_literal(
+        &mut self,
+        required: bool,
+    ) -> Result<Option<model::Literal<'src>>, SyntaxError> {
+        if self.peek() == Some('|') {
+            return self.parse_quoted_literal();
         }
 
         let value = self.parse_unquoted_literal();
@@ -522,35 +557,25 @@ impl Mf2Parser {
         Ok(Some(model::Literal::new(value)))
     }
 
-    fn parse_quoted_literal(&mut self) -> Result<Option<model::Literal>, SyntaxError> {
+    fn parse_quoted_literal(&mut self) -> Result<Option<model::Literal<'src>>, SyntaxError> {
         self.pos += 1; // consume the '|'
-        let mut value = String::new();
-        while self.pos < self.src.len() {
-            match self.src[self.pos] {
+        let mut start = self.pos;
+        let mut unescaped: Option<String> = None;
+        while let Some(c) = self.peek() {
+            match c {
                 '\u{5C}' => {
-                    let escaped_char = self.src[self.pos + 1];
-                    match escaped_char {
-                        '{' | '}' | '|' | '\u{5C}' => {
-                            value.push('\u{5C}'); // escape char
-                            value.push(escaped_char); //escaped char
-                            self.pos += 2; // consume the escape char and escaped char
-                        }
-                        _ => {
-                            return Err(SyntaxError::BadEscape(Error::new(
-                                self.pos,
-                                Some(self.pos + 2),
-                                None,
-                            )));
-                        }
-                    }
+                    let mut value = self.take_text(start, unescaped.take()).into_owned();
+                    value.push(self.parse_escaped_char()?);
+                    unescaped = Some(value);
+                    start = self.pos;
                 }
                 '|' => {
+                    let value = self.take_text(start, unescaped);
                     self.pos += 1; // consume the '|'
                     return Ok(Some(model::Literal::new(value)));
                 }
                 _ => {
-                    value.push(self.src[self.pos]);
-                    self.pos += 1; // consume the char
+                    self.pos += c.len_utf8(); // consume the char
                 }
             }
         }
@@ -562,23 +587,23 @@ impl Mf2Parser {
         )))
     }
 
-    fn parse_name(&mut self) -> Result<String, SyntaxError> {
+    fn parse_name(&mut self) -> Result<Cow<'src, str>, SyntaxError> {
         match self.parse_name_value() {
-            Some(name) => Ok(name),
+            // names are compared in NFC, e.g. for duplicate option detection
+            Some(name) => Ok(nfc(name)),
             _ => Err(SyntaxError::EmptyToken(Error::new(self.pos, None, None))),
         }
     }
 
     fn parse_options(
         &mut self,
-    ) -> Result<Option<HashMap<String, model::OptionValue>>, SyntaxError> {
-        let mut options: HashMap<String, model::OptionValue> = HashMap::new();
+    ) -> Result<Option<HashMap<Cow<'src, str>, model::OptionValue<'src>>>, SyntaxError> {
+        let mut options: HashMap<Cow<'src, str>, model::OptionValue> = HashMap::new();
 
-        self.skip_whitespaces_required_if_not_followed_by(vec!['/', '}'])?;
+        self.skip_whitespaces_required_if_not_followed_by(&['/', '}'])?;
 
-        while self.pos < self.src.len() {
-            let char = self.src[self.pos];
-            if char == '/' || char == '}' || char == '@' {
+        while let Some(c) = self.peek() {
+            if c == '/' || c == '}' || c == '@' {
                 break; // end of options
             }
             let start_pos = self.pos;
@@ -605,7 +630,7 @@ impl Mf2Parser {
                     return Err(SyntaxError::EmptyToken(Error::new(self.pos, None, None)));
                 }
             }
-            self.skip_whitespaces_required_if_not_followed_by(vec!['/', '}'])?;
+            self.skip_whitespaces_required_if_not_followed_by(&['/', '}'])?;
         }
         if options.is_empty() {
             return Ok(None);
@@ -615,10 +640,10 @@ impl Mf2Parser {
 
     fn parse_attributes(
         &mut self,
-    ) -> Result<Option<HashMap<String, model::AttributeValue>>, SyntaxError> {
-        let mut attributes: HashMap<String, model::AttributeValue> = HashMap::new();
+    ) -> Result<Option<HashMap<Cow<'src, str>, model::AttributeValue<'src>>>, SyntaxError> {
+        let mut attributes: HashMap<Cow<'src, str>, model::AttributeValue> = HashMap::new();
 
-        while self.src[self.pos] == '@' {
+        while self.peek() == Some('@') {
             let start_pos = self.pos;
             self.pos += 1; // consume the '@'
             let name = self.parse_identifier()?;
@@ -629,8 +654,8 @@ impl Mf2Parser {
                     None,
                 )));
             }
-            self.skip_whitespaces_required_if_not_followed_by(vec!['=', '/', '}'])?;
-            if self.src[self.pos] == '=' {
+            self.skip_whitespaces_required_if_not_followed_by(&['=', '/', '}'])?;
+            if self.peek() == Some('=') {
                 self.pos += 1; // consume the '='
                 self.skip_whitespaces();
                 let value = self.parse_literal(true)?;
@@ -639,6 +664,8 @@ impl Mf2Parser {
                 } else {
                     return Err(SyntaxError::EmptyToken(Error::new(self.pos, None, None)));
                 }
+                // attributes are separated by whitespace, e.g. `@a=1 @b`
+                self.skip_whitespaces_required_if_not_followed_by(&['/', '}'])?;
             } else {
                 // If no value is provided, treat it as a boolean attribute
                 attributes.insert(name, model::AttributeValue::True(true));
@@ -650,56 +677,159 @@ impl Mf2Parser {
         Ok(Some(attributes))
     }
 
-    fn parse_name_value(&mut self) -> Option<String> {
+    fn parse_name_value(&mut self) -> Option<&'src str> {
         self.skip_bidis();
-        let mut value = String::new();
+        let start = self.pos;
 
-        while !is_ws_char(self.src[self.pos])
-            && is_name_char(self.src[self.pos])
-            && self.pos < self.src.len()
-        {
-            value.push(self.src[self.pos]);
-            self.pos += 1; // consume char
+        while let Some(c) = self.peek() {
+            if is_ws_char(c) || !is_name_char(c) {
+                break;
+            }
+            self.pos += c.len_utf8(); // consume char
         }
-        value = trim_tail_ws_and_bidi(&value);
+        let value = trim_tail_ws_and_bidi(&self.src[start..self.pos]);
 
-        if is_valid_name_string(&value) {
-            return Some(value);
+        if !value.is_empty() && is_valid_name_string(value) {
+            Some(value)
         } else {
-            return None;
+            None
         }
     }
 
-    fn parse_identifier(&mut self) -> Result<String, SyntaxError> {
-        let mut name = self.parse_name()?;
-        if self.src[self.pos] == ':' {
+    fn parse_identifier(&mut self) -> Result<Cow<'src, str>, SyntaxError> {
+        let name = self.parse_name()?;
+        if self.peek() == Some(':') {
             self.pos += 1; // consume the ':'
-            name.push(':'); // add ':' to the name
-            name.push_str(&self.parse_name()?); // parse the namespace
+            let namespaced = format!("{}:{}", name, self.parse_name()?); // parse the namespace
+            return Ok(Cow::Owned(namespaced));
         }
-        return Ok(name);
+        Ok(name)
     }
 
-    fn parse_unquoted_literal(&mut self) -> String {
-        let mut value = String::new();
-        while self.pos < self.src.len() && is_name_char(self.src[self.pos]) {
-            value.push(self.src[self.pos]);
-
-            self.pos += 1; // consume char
+    fn parse_unquoted_literal(&mut self) -> &'src str {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if !is_name_char(c) {
+                break;
+            }
+            self.pos += c.len_utf8(); // consume char
         }
-        return value;
+        &self.src[start..self.pos]
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-enum ParsedValue {
-    Literal(model::Literal),
-    Variable(model::VariableRef),
+enum ParsedValue<'src> {
+    Literal(model::Literal<'src>),
+    Variable(model::VariableRef<'src>),
     None,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-enum ParsedExpressionOrMarkup {
-    Expression(model::Expression),
-    Markup(model::Markup),
+enum ParsedExpressionOrMarkup<'src> {
+    Expression(model::Expression<'src>),
+    Markup(model::Markup<'src>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_variant_keys_are_normalized() {
+        let message = Mf2Parser::new(".input {$x :string} .match $x cafe\u{301} {{a}} * {{b}}")
+            .parse()
+            .unwrap();
+        let model::Message::Select(select) = message else {
+            panic!("expected a select message");
+        };
+        assert_eq!(
+            select.variants()[0].keys()[0],
+            model::VariantKey::Literal(model::Literal::new("caf\u{E9}"))
+        );
+    }
+
+    #[test]
+    fn test_option_names_are_compared_in_nfc() {
+        let result = Mf2Parser::new("{$x :number caf\u{E9}=1 cafe\u{301}=2}").parse();
+        assert!(matches!(result, Err(SyntaxError::DuplicateOptionName(_))));
+    }
+
+    #[test]
+    fn test_expression_spans() {
+        let src = "Hi {$name}, {|x| :string}{:f}";
+        let model::Message::Pattern(pattern) = Mf2Parser::new(src).parse().unwrap() else {
+            panic!("expected a pattern message");
+        };
+        let spans: Vec<&str> = pattern
+            .pattern()
+            .iter()
+            .filter_map(|item| match item {
+                model::PatternItem::Expression(expr) => Some(&src[expr.span()]),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(spans, vec!["{$name}", "{|x| :string}", "{:f}"]);
+    }
+
+    #[test]
+    fn test_text_borrows_from_source() {
+        let src = "Hello {|world| :string} and {$name}!";
+        let model::Message::Pattern(pattern) = Mf2Parser::new(src).parse().unwrap() else {
+            panic!("expected a pattern message");
+        };
+        assert!(matches!(
+            pattern.pattern()[0],
+            model::PatternItem::String(Cow::Borrowed("Hello "))
+        ));
+        let model::PatternItem::Expression(model::Expression::Variable(var_expr)) =
+            &pattern.pattern()[3]
+        else {
+            panic!("expected a variable expression");
+        };
+        assert_eq!(var_expr.get_variable_name(), "name");
+    }
+
+    #[test]
+    fn test_escapes_are_unescaped() {
+        let model::Message::Pattern(pattern) = Mf2Parser::new("a\\{b\\}c").parse().unwrap() else {
+            panic!("expected a pattern message");
+        };
+        assert_eq!(
+            pattern.pattern()[0],
+            model::PatternItem::String(Cow::Owned("a{b}c".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_into_owned_detaches_from_source() {
+        let message = {
+            let src = String::from(".local $x = {|a| :string} {{{$x} b}}");
+            Mf2Parser::new(&src).parse().unwrap().into_owned()
+        };
+        assert!(matches!(message, model::Message::Pattern(_)));
+    }
+
+    #[test]
+    fn test_errors_report_byte_offsets() {
+        let result = Mf2Parser::new("\u{E9}\u{E9} {$}").parse();
+        assert_eq!(
+            result,
+            Err(SyntaxError::EmptyToken(Error::new(7, None, None)))
+        );
+    }
+
+    #[test]
+    fn test_attributes_need_whitespace_between_them() {
+        assert!(Mf2Parser::new("{:func @bar=42 @baz}").parse().is_ok());
+        assert!(Mf2Parser::new("{:func @bar=42@baz}").parse().is_err());
+        assert!(Mf2Parser::new("{:func @bar@baz}").parse().is_err());
+    }
+
+    #[test]
+    fn test_variants_need_a_key() {
+        assert!(Mf2Parser::new(".input {$x :string} .match $x {{}}")
+            .parse()
+            .is_err());
+    }
 }
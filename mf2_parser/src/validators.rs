@@ -90,18 +90,14 @@ pub fn is_name_char(c: char) -> bool {
 }
 
 pub fn is_ws_char(c: char) -> bool {
-    match c {
-        '\u{20}' | '\u{09}' | '\u{0D}' | '\u{0A}' | '\u{3000}' => true,
-        _ => false,
-    }
+    matches!(c, '\u{20}' | '\u{09}' | '\u{0D}' | '\u{0A}' | '\u{3000}')
 }
 
 pub fn is_bidi_char(c: char) -> bool {
-    match c {
-        '\u{061c}' | '\u{200E}' | '\u{200F}' => true,
-        '\u{2066}'..='\u{2069}' => true,
-        _ => false,
-    }
+    matches!(
+        c,
+        '\u{061c}' | '\u{200E}' | '\u{200F}' | '\u{2066}'..='\u{2069}'
+    )
 }
 
 // TODO use this validation in parser
@@ -202,7 +198,7 @@ pub fn is_valid_name_string(s: &str) -> bool {
             }
         }
     }
-    return true;
+    true
 }
 
 // TODO use this validation in parser
@@ -215,17 +211,6 @@ pub fn is_valid_name_string(s: &str) -> bool {
 //     return true;
 // }
 
-pub fn trim_tail_ws_and_bidi(s: &str) -> String {
-    let mut i = s.chars().count();
-    loop {
-        if i == 0 {
-            break;
-        }
-        let c = s.chars().nth(i - 1).unwrap();
-        if !is_ws_char(c) & !is_bidi_char(c) {
-            break;
-        }
-        i -= 1;
-    }
-    s[..i].to_string()
+pub fn trim_tail_ws_and_bidi(s: &str) -> &str {
+    s.trim_end_matches(|c| is_ws_char(c) || is_bidi_char(c))
 }
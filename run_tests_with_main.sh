@@ -21,5 +21,14 @@ for test_file in test/*.js; do
     fi
 done
 
+echo ""
+echo "-----------------------------------------"
+echo "Running: test/catalog/test_catalog.js"
+echo "-----------------------------------------"
+./target/release/js-minimal-runtime-for-mf2 \
+    --catalog test/catalog/en.mf2 \
+    --catalog test/catalog/ja.json \
+    test/catalog/test_catalog.js
+
 echo ""
 echo "========================================="
\ No newline at end of file
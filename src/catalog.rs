@@ -0,0 +1,413 @@
+// ロケールごとのメッセージをまとめて読み込むメッセージカタログ
+//
+// リソースファイルは次のどちらかの形式で書く
+// - `key = message` 形式。インデントされた行は直前のメッセージの続きになる。`#` で始まる行はコメント
+// - JSON オブジェクト形式。ネストしたオブジェクトのキーは `.` で連結する
+use crate::compiled::{BidiIsolation, CompiledMessage, FormatError, FormatOptions, FormatValue};
+use crate::functions::FunctionRegistry;
+use crate::json::{JsonParser, JsonValue};
+use crate::locale::Locale;
+use core::fmt::Display;
+use core::fmt::Formatter;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// エントリ単位の読み込みエラー
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatalogError {
+    pub file: String,
+    /// 1始まりの行番号
+    pub line: usize,
+    pub id: Option<String>,
+    pub message: String,
+}
+
+impl Display for CatalogError {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        write!(f, "{}:{}: ", self.file, self.line)?;
+        if let Some(id) = &self.id {
+            write!(f, "{}: ", id)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+/// メッセージIDとソース、そのソースが始まる行
+struct Entry {
+    id: String,
+    src: String,
+    line: usize,
+}
+
+pub struct Catalog {
+    /// フォールバックの最後に使うロケール
+    default_locale: String,
+    messages: HashMap<String, HashMap<String, CompiledMessage>>,
+}
+
+impl Catalog {
+    pub fn new(default_locale: &str) -> Self {
+        Self {
+            default_locale: default_locale.to_string(),
+            messages: HashMap::new(),
+        }
+    }
+
+    /// リソースファイルを読み込む。ロケールはファイル名（拡張子を除く）から決める
+    /// 構文エラーのあるエントリは読み飛ばし、エラーとして返す
+    pub fn load_file(
+        &mut self,
+        path: &Path,
+        registry: &FunctionRegistry,
+    ) -> io::Result<Vec<CatalogError>> {
+        let src = fs::read_to_string(path)?;
+        let locale = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let json = path.extension().is_some_and(|ext| ext == "json");
+        Ok(self.load_str(&locale, &path.display().to_string(), &src, json, registry))
+    }
+
+    pub fn load_str(
+        &mut self,
+        locale: &str,
+        file: &str,
+        src: &str,
+        json: bool,
+        registry: &FunctionRegistry,
+    ) -> Vec<CatalogError> {
+        let mut errors = Vec::new();
+        let entries = if json {
+            parse_json(file, src, &mut errors)
+        } else {
+            parse_key_value(file, src, &mut errors)
+        };
+
+        // ファイル名の大文字小文字などの揺れを吸収するため、正規化したタグをキーにする
+        let locale = Locale::parse(locale)
+            .map(|locale| locale.to_string())
+            .unwrap_or_else(|_| locale.to_string());
+        let messages = self.messages.entry(locale).or_default();
+        for entry in entries {
+            if messages.contains_key(&entry.id) {
+                errors.push(CatalogError {
+                    file: file.to_string(),
+                    line: entry.line,
+                    id: Some(entry.id),
+                    message: "duplicate message id".to_string(),
+                });
+                continue;
+            }
+            match CompiledMessage::compile(&entry.src, registry) {
+                Ok(compiled) => {
+                    messages.insert(entry.id, compiled);
+                }
+                Err(error) => {
+                    // 構文エラーの位置をファイル中の行に変換する
+                    // JSON形式ではエスケープで位置がずれるため、キーの行を使う
+                    let line = match error.position() {
+                        Some(pos) if !json => {
+                            entry.line + entry.src[..pos.min(entry.src.len())].matches('\n').count()
+                        }
+                        _ => entry.line,
+                    };
+                    errors.push(CatalogError {
+                        file: file.to_string(),
+                        line,
+                        id: Some(entry.id),
+                        message: error.message(&entry.src),
+                    });
+                }
+            }
+        }
+        errors
+    }
+
+    /// ロケールのフォールバックチェーン。例: ja-JP → ja → en
+    /// 拡張付きのタグは、拡張を除いたタグより先に探す
+    pub fn fallback_chain(&self, locale: &str) -> Vec<String> {
+        let mut chain = Vec::new();
+        if let Ok(locale) = Locale::parse(locale) {
+            let mut base_name = locale.base_name();
+            if locale.to_string() != base_name {
+                chain.push(locale.to_string());
+            }
+            while !base_name.is_empty() {
+                chain.push(base_name.clone());
+                base_name.truncate(base_name.rfind('-').unwrap_or(0));
+            }
+        }
+        if !chain.contains(&self.default_locale) {
+            chain.push(self.default_locale.clone());
+        }
+        chain
+    }
+
+    /// フォールバックチェーンの中で最初に見つかったメッセージで、そのロケールとしてフォーマットする
+    pub fn format<T: FormatValue>(
+        &self,
+        id: &str,
+        locale: &str,
+        variables: &HashMap<String, T>,
+    ) -> Option<(String, Vec<FormatError>)> {
+        self.fallback_chain(locale).iter().find_map(|tag| {
+            let message = self.messages.get(tag)?.get(id)?;
+            let locale = Locale::parse(tag).ok()?;
+            // カタログの出力はログなどにも使うので、プレースホルダーを分離しない
+            let options = FormatOptions {
+                bidi_isolation: BidiIsolation::None,
+                ..FormatOptions::new(locale)
+            };
+            Some(message.format(&options, variables))
+        })
+    }
+}
+
+fn parse_key_value(file: &str, src: &str, errors: &mut Vec<CatalogError>) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = Vec::new();
+    // 直前の行がエントリの一部かどうか。コメントや空行、エラー行で途切れる
+    let mut continuing = false;
+    for (index, line) in src.lines().enumerate() {
+        let line_number = index + 1;
+        if line.trim().is_empty() || line.starts_with('#') {
+            continuing = false;
+            continue;
+        }
+        if line.starts_with([' ', '\t']) {
+            match entries.last_mut() {
+                Some(entry) if continuing => {
+                    entry.src.push('\n');
+                    entry.src.push_str(line.trim_start());
+                }
+                _ => errors.push(CatalogError {
+                    file: file.to_string(),
+                    line: line_number,
+                    id: None,
+                    message: "continuation line without a message".to_string(),
+                }),
+            }
+            continue;
+        }
+        match line.split_once('=') {
+            Some((id, message)) if is_valid_id(id.trim()) => {
+                entries.push(Entry {
+                    id: id.trim().to_string(),
+                    src: message.trim_start().to_string(),
+                    line: line_number,
+                });
+                continuing = true;
+            }
+            _ => {
+                errors.push(CatalogError {
+                    file: file.to_string(),
+                    line: line_number,
+                    id: None,
+                    message: "expected `key = message`".to_string(),
+                });
+                continuing = false;
+            }
+        }
+    }
+    entries
+}
+
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+fn parse_json(file: &str, src: &str, errors: &mut Vec<CatalogError>) -> Vec<Entry> {
+    let mut parser = JsonParser::new(src);
+    let root = match parser.parse() {
+        Ok(root) => root,
+        Err(error) => {
+            errors.push(CatalogError {
+                file: file.to_string(),
+                line: error.line,
+                id: None,
+                message: error.message,
+            });
+            return Vec::new();
+        }
+    };
+    if !matches!(root, JsonValue::Object(_)) {
+        errors.push(CatalogError {
+            file: file.to_string(),
+            line: 1,
+            id: None,
+            message: "expected a JSON object".to_string(),
+        });
+        return Vec::new();
+    }
+
+    let mut entries = Vec::new();
+    let mut lines = parser.key_lines().into_iter();
+    flatten_json(file, "", &root, &mut lines, &mut entries, errors);
+    entries
+}
+
+/// キーの行番号はパース時の出現順に並んでいるので、同じ順序で走査して対応させる
+fn flatten_json(
+    file: &str,
+    prefix: &str,
+    value: &JsonValue,
+    lines: &mut impl Iterator<Item = usize>,
+    entries: &mut Vec<Entry>,
+    errors: &mut Vec<CatalogError>,
+) {
+    let JsonValue::Object(members) = value else {
+        return;
+    };
+    for (key, value) in members {
+        let line = lines.next().unwrap_or(1);
+        let id = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        match value {
+            JsonValue::String(src) => entries.push(Entry {
+                id,
+                src: src.clone(),
+                line,
+            }),
+            JsonValue::Object(_) => flatten_json(file, &id, value, lines, entries, errors),
+            _ => {
+                // 配列の中のオブジェクトのキーも行番号の対応から読み飛ばす
+                for _ in 0..count_keys(value) {
+                    lines.next();
+                }
+                errors.push(CatalogError {
+                    file: file.to_string(),
+                    line,
+                    id: Some(id),
+                    message: "expected a message string".to_string(),
+                });
+            }
+        }
+    }
+}
+
+fn count_keys(value: &JsonValue) -> usize {
+    match value {
+        JsonValue::Object(members) => members.iter().map(|(_, v)| 1 + count_keys(v)).sum(),
+        JsonValue::Array(items) => items.iter().map(count_keys).sum(),
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(
+        catalog: &Catalog,
+        id: &str,
+        locale: &str,
+        variables: &[(&str, &str)],
+    ) -> Option<String> {
+        let variables: HashMap<String, String> = variables
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        catalog
+            .format(id, locale, &variables)
+            .map(|(output, _)| output)
+    }
+
+    #[test]
+    fn test_key_value_with_multiline_messages() {
+        let src = "# greetings\nhello = Hello {$name}!\nitems = .input {$n :number}\n  .match $n\n  one {{one item}}\n  * {{{$n} items}}\n";
+        let mut catalog = Catalog::new("en");
+        let errors = catalog.load_str("en", "en.mf2", src, false, &FunctionRegistry::default());
+        assert_eq!(errors, vec![]);
+        assert_eq!(
+            format(&catalog, "hello", "en", &[("name", "Saji")]).unwrap(),
+            "Hello Saji!"
+        );
+        assert_eq!(
+            format(&catalog, "items", "en", &[("n", "1")]).unwrap(),
+            "one item"
+        );
+        assert_eq!(
+            format(&catalog, "items", "en", &[("n", "3")]).unwrap(),
+            "3 items"
+        );
+    }
+
+    #[test]
+    fn test_errors_report_file_and_line() {
+        let src = "ok = fine\nbroken = .input {$n :number}\n  .match $n\n  one {{one}\n  * {{other}}\nno separator\n";
+        let mut catalog = Catalog::new("en");
+        let errors = catalog.load_str("en", "en.mf2", src, false, &FunctionRegistry::default());
+        let lines: Vec<(usize, Option<&str>)> =
+            errors.iter().map(|e| (e.line, e.id.as_deref())).collect();
+        assert_eq!(lines, vec![(6, None), (4, Some("broken"))]);
+        assert!(
+            errors[1]
+                .to_string()
+                .starts_with("en.mf2:4: broken: syntax-error")
+        );
+        assert_eq!(format(&catalog, "ok", "en", &[]).unwrap(), "fine");
+    }
+
+    #[test]
+    fn test_json_form() {
+        let src = "{\n  \"hello\": \"Hello {$name}!\",\n  \"nav\": {\n    \"home\": \"Home\",\n    \"bad\": \"{oops\"\n  },\n  \"count\": 3\n}";
+        let mut catalog = Catalog::new("en");
+        let errors = catalog.load_str("en", "en.json", src, true, &FunctionRegistry::default());
+        let lines: Vec<(usize, Option<&str>)> =
+            errors.iter().map(|e| (e.line, e.id.as_deref())).collect();
+        assert_eq!(lines, vec![(7, Some("count")), (5, Some("nav.bad"))]);
+        assert_eq!(format(&catalog, "nav.home", "en", &[]).unwrap(), "Home");
+    }
+
+    #[test]
+    fn test_locale_fallback_chain() {
+        let registry = FunctionRegistry::default();
+        let mut catalog = Catalog::new("en");
+        catalog.load_str("en", "en.mf2", "hello = Hello\nbye = Bye", false, &registry);
+        catalog.load_str("ja", "ja.mf2", "hello = こんにちは", false, &registry);
+        assert_eq!(catalog.fallback_chain("ja-JP"), vec!["ja-JP", "ja", "en"]);
+        assert_eq!(
+            catalog.fallback_chain("JA-jp-u-nu-fullwide"),
+            vec!["ja-JP-u-nu-fullwide", "ja-JP", "ja", "en"]
+        );
+        assert_eq!(
+            format(&catalog, "hello", "ja-JP", &[]).unwrap(),
+            "こんにちは"
+        );
+        assert_eq!(format(&catalog, "bye", "ja-JP", &[]).unwrap(), "Bye");
+        assert_eq!(format(&catalog, "missing", "ja-JP", &[]), None);
+    }
+
+    /// test/catalog のファイルを --catalog と同じく load_file で読み込む
+    #[test]
+    fn test_catalog_fixtures() {
+        let registry = FunctionRegistry::default();
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("test/catalog");
+        let mut catalog = Catalog::new("en");
+        for file in ["en.mf2", "ja.json"] {
+            let errors = catalog.load_file(&dir.join(file), &registry).unwrap();
+            assert_eq!(errors, vec![], "{}", file);
+        }
+        assert_eq!(
+            format(&catalog, "greeting", "ja-JP", &[("name", "Saji")]).unwrap(),
+            "こんにちは、Sajiさん！"
+        );
+        // ja に無いメッセージは en にフォールバックする
+        assert_eq!(
+            format(&catalog, "items", "ja-JP", &[("count", "3")]).unwrap(),
+            "You have 3 items."
+        );
+        assert_eq!(
+            format(&catalog, "items", "en", &[("count", "1")]).unwrap(),
+            "You have one item."
+        );
+    }
+}
@@ -0,0 +1,842 @@
+// MessageFormat v2 のメッセージを一度だけ解析・検証し、フォーマット用の命令列に変換する
+// Reference: https://github.com/unicode-org/message-format-wg/blob/main/spec/formatting.md
+use crate::functions::{FunctionError, FunctionRegistry, MessageFunction, MessageValue};
+use crate::locale::Locale;
+use core::fmt::Display;
+use core::fmt::Formatter;
+use mf2_parser::error::{Error, SyntaxError};
+use mf2_parser::model::{Declaration, Expression, Message, OptionValue, PatternItem, VariantKey};
+use mf2_parser::parser::Mf2Parser;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+/// MessageFormat v2のフォーマット処理を行うトレイト
+pub trait FormatValue: ToString + Clone {
+    /// MF2の関数に渡す値に変換する
+    fn to_message_value(&self) -> MessageValue {
+        MessageValue::String(self.to_string())
+    }
+}
+
+/// メッセージ全体の基本方向。`Auto` は方向が不明として扱う
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Ltr,
+    Rtl,
+    Auto,
+}
+
+impl Direction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Direction::Ltr => "ltr",
+            Direction::Rtl => "rtl",
+            Direction::Auto => "auto",
+        }
+    }
+}
+
+/// プレースホルダーの双方向テキスト分離の戦略
+/// Reference: https://github.com/unicode-org/message-format-wg/blob/main/spec/formatting.md#handling-bidirectional-text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BidiIsolation {
+    Default,
+    None,
+}
+
+impl BidiIsolation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BidiIsolation::Default => "default",
+            BidiIsolation::None => "none",
+        }
+    }
+}
+
+/// フォーマット時の設定
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatOptions {
+    pub locale: Locale,
+    pub bidi_isolation: BidiIsolation,
+    pub dir: Direction,
+}
+
+impl FormatOptions {
+    /// Intl.MessageFormat の既定値。分離は "default"、方向はロケールから決める
+    pub fn new(locale: Locale) -> Self {
+        let dir = if locale.is_rtl() {
+            Direction::Rtl
+        } else {
+            Direction::Ltr
+        };
+        Self {
+            locale,
+            bidi_isolation: BidiIsolation::Default,
+            dir,
+        }
+    }
+}
+
+/// カスタム関数の呼び出し。関数名、被演算子、解決済みのオプションを受け取る
+pub type CustomFunctionCall<'a> = dyn FnMut(
+        &str,
+        Option<&MessageValue>,
+        &HashMap<String, MessageValue>,
+    ) -> Result<MessageValue, FunctionError>
+    + 'a;
+
+/// 構築時に検出されるエラー（構文エラーとデータモデルエラー）
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompileError {
+    Syntax(SyntaxError),
+    VariantKeyMismatch,
+    MissingFallbackVariant,
+    MissingSelectorAnnotation(String),
+    DuplicateDeclaration(String),
+    DuplicateVariant,
+}
+
+impl CompileError {
+    /// message-format-wg のエラー種別名
+    pub fn error_type(&self) -> &'static str {
+        match self {
+            CompileError::Syntax(SyntaxError::DuplicateOptionName(_)) => "duplicate-option-name",
+            CompileError::Syntax(_) => "syntax-error",
+            CompileError::VariantKeyMismatch => "variant-key-mismatch",
+            CompileError::MissingFallbackVariant => "missing-fallback-variant",
+            CompileError::MissingSelectorAnnotation(_) => "missing-selector-annotation",
+            CompileError::DuplicateDeclaration(_) => "duplicate-declaration",
+            CompileError::DuplicateVariant => "duplicate-variant",
+        }
+    }
+
+    /// 構文エラーの場合はメッセージ中のバイト位置を返す
+    pub fn position(&self) -> Option<usize> {
+        match self {
+            CompileError::Syntax(error) => Some(syntax_error_detail(error).start()),
+            _ => None,
+        }
+    }
+
+    /// 構文エラーに位置を付けたエラーメッセージ。位置は JS の文字列と同じ UTF-16 のオフセット
+    pub fn message(&self, source: &str) -> String {
+        let CompileError::Syntax(error) = self else {
+            return self.to_string();
+        };
+        let error = syntax_error_detail(error);
+        let start = source[..error.start().min(source.len())]
+            .encode_utf16()
+            .count();
+        let mut message = format!("{} at {}", self.error_type(), start);
+        if !error.expected().is_empty() {
+            message.push_str(&format!(", expected {}", error.expected()));
+        }
+        message
+    }
+}
+
+fn syntax_error_detail(error: &SyntaxError) -> &Error {
+    match error {
+        SyntaxError::EmptyToken(e)
+        | SyntaxError::BadEscape(e)
+        | SyntaxError::BadInputExpression(e)
+        | SyntaxError::DuplicateAttribute(e)
+        | SyntaxError::DuplicateOptionName(e)
+        | SyntaxError::ExtraContent(e)
+        | SyntaxError::ParseError(e)
+        | SyntaxError::MissingSyntax(e)
+        | SyntaxError::InvalidCharacter(e) => e,
+    }
+}
+
+impl Display for CompileError {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        match self {
+            CompileError::Syntax(error) => {
+                let error = syntax_error_detail(error);
+                write!(f, "{}", self.error_type())?;
+                if !error.expected().is_empty() {
+                    write!(f, ", expected {}", error.expected())?;
+                }
+                Ok(())
+            }
+            CompileError::MissingSelectorAnnotation(name)
+            | CompileError::DuplicateDeclaration(name) => {
+                write!(f, "{}: ${}", self.error_type(), name)
+            }
+            _ => write!(f, "{}", self.error_type()),
+        }
+    }
+}
+
+/// フォーマット時のエラーの種類
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormatErrorKind {
+    UnresolvedVariable(String),
+    UnknownFunction(String),
+    BadOperand(String),
+    BadOption(String),
+    BadFunctionResult(String),
+    FunctionError(String),
+}
+
+/// フォーマット時に検出されるエラー。出力にはフォールバック値が使われる
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatError {
+    pub kind: FormatErrorKind,
+    /// エラーが起きた式のメッセージ中のバイト範囲（{} を含む）
+    pub span: Option<Range<usize>>,
+}
+
+impl FormatError {
+    /// 式の位置を付けたエラーメッセージ。位置は JS の文字列と同じ UTF-16 のオフセット
+    pub fn message(&self, source: &str) -> String {
+        match self.span_utf16(source) {
+            Some(span) => format!("{} at {}", self, span.start),
+            None => self.to_string(),
+        }
+    }
+
+    /// span を UTF-16 のオフセットに変換する
+    pub fn span_utf16(&self, source: &str) -> Option<Range<usize>> {
+        let offset = |index: usize| source[..index].encode_utf16().count();
+        let span = self.span.as_ref()?;
+        Some(offset(span.start)..offset(span.end))
+    }
+
+    pub fn error_type(&self) -> &'static str {
+        match self.kind {
+            FormatErrorKind::UnresolvedVariable(_) => "unresolved-variable",
+            FormatErrorKind::UnknownFunction(_) => "unknown-function",
+            FormatErrorKind::BadOperand(_) => "bad-operand",
+            FormatErrorKind::BadOption(_) => "bad-option",
+            FormatErrorKind::BadFunctionResult(_) => "bad-function-result",
+            FormatErrorKind::FunctionError(_) => "function-error",
+        }
+    }
+}
+
+impl Display for FormatError {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        match &self.kind {
+            FormatErrorKind::UnresolvedVariable(name) => {
+                write!(f, "{}: ${}", self.error_type(), name)?
+            }
+            FormatErrorKind::UnknownFunction(name)
+            | FormatErrorKind::BadOperand(name)
+            | FormatErrorKind::BadFunctionResult(name)
+            | FormatErrorKind::FunctionError(name) => {
+                write!(f, "{}: :{}", self.error_type(), name)?
+            }
+            FormatErrorKind::BadOption(name) => write!(f, "{}: {}", self.error_type(), name)?,
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    None,
+    Literal(String),
+    Variable(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct FunctionCall {
+    name: String,
+    /// レジストリに存在しない関数は None。エラーはフォーマット時に報告する
+    function: Option<MessageFunction>,
+    options: Vec<(String, Operand)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct CompiledExpression {
+    operand: Operand,
+    function: Option<FunctionCall>,
+    /// エラー報告用のソース中の位置
+    span: Range<usize>,
+}
+
+impl CompiledExpression {
+    fn new(expression: &Expression, registry: &FunctionRegistry) -> Self {
+        let (operand, function) = match expression {
+            Expression::Literal(expr) => (
+                Operand::Literal(expr.literal().value.to_string()),
+                expr.function(),
+            ),
+            Expression::Variable(expr) => (
+                Operand::Variable(expr.get_variable_name().to_string()),
+                expr.function(),
+            ),
+            Expression::Function(expr) => (Operand::None, Some(expr.function())),
+        };
+        let function = function.map(|function| {
+            let mut options: Vec<(String, Operand)> = function
+                .options()
+                .iter()
+                .map(|(name, value)| {
+                    let value = match value {
+                        OptionValue::Literal(literal) => {
+                            Operand::Literal(literal.value.to_string())
+                        }
+                        OptionValue::VariableRef(var) => Operand::Variable(var.name().to_string()),
+                    };
+                    (name.to_string(), value)
+                })
+                .collect();
+            options.sort_by(|(a, _), (b, _)| a.cmp(b));
+            FunctionCall {
+                name: function.name.to_string(),
+                function: registry.get(&function.name),
+                options,
+            }
+        });
+        Self {
+            operand,
+            function,
+            span: expression.span(),
+        }
+    }
+
+    /// 式が参照する変数名
+    fn variables(&self) -> impl Iterator<Item = &str> {
+        let operand = match &self.operand {
+            Operand::Variable(name) => Some(name.as_str()),
+            _ => None,
+        };
+        let options = self.function.iter().flat_map(|f| {
+            f.options.iter().filter_map(|(_, value)| match value {
+                Operand::Variable(name) => Some(name.as_str()),
+                _ => None,
+            })
+        });
+        operand.into_iter().chain(options)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Op {
+    /// 連続するテキストは1つにまとめてある
+    Text(String),
+    Expression(CompiledExpression),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct CompiledVariant {
+    /// None は catch-all キー (*)
+    keys: Vec<Option<String>>,
+    ops: Vec<Op>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Body {
+    Pattern(Vec<Op>),
+    Select {
+        selectors: Vec<String>,
+        variants: Vec<CompiledVariant>,
+    },
+}
+
+/// 変数の解決結果。失敗した場合は {} を除いたフォールバック表現を持つ
+#[derive(Debug, Clone)]
+enum Resolved {
+    Value(MessageValue),
+    Fallback(String),
+}
+
+/// 解析・検証済みのメッセージ
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompiledMessage {
+    declarations: Vec<(String, CompiledExpression)>,
+    body: Body,
+}
+
+impl CompiledMessage {
+    /// メッセージのソースを解析してコンパイルする
+    pub fn compile(src: &str, registry: &FunctionRegistry) -> Result<Self, CompileError> {
+        let message = Mf2Parser::new(src).parse().map_err(CompileError::Syntax)?;
+        Self::new(&message, registry)
+    }
+
+    /// データモデルを検証してコンパイルする
+    pub fn new(message: &Message, registry: &FunctionRegistry) -> Result<Self, CompileError> {
+        let declarations = match message {
+            Message::Pattern(pattern) => pattern.declarations(),
+            Message::Select(select) => select.declarations(),
+        };
+
+        // 宣言済みの変数と、それより前に参照された変数は再宣言できない
+        let mut declared = HashSet::new();
+        let mut referenced = HashSet::new();
+        let mut annotated = HashSet::new();
+        let mut compiled_declarations = Vec::new();
+        for declaration in declarations {
+            let (name, expression, is_input) = match declaration {
+                Declaration::Input(input) => (
+                    input.name(),
+                    CompiledExpression::new(&Expression::Variable(input.value().clone()), registry),
+                    true,
+                ),
+                Declaration::Local(local) => (
+                    local.name(),
+                    CompiledExpression::new(local.value(), registry),
+                    false,
+                ),
+            };
+            let mut variables = expression.variables();
+            if is_input {
+                // .input の被演算子は宣言する変数自身
+                variables.next();
+            }
+            let variables: Vec<&str> = variables.collect();
+            let annotates = expression.function.is_some()
+                || matches!(&expression.operand, Operand::Variable(var) if annotated.contains(var));
+            referenced.extend(variables.iter().map(|v| v.to_string()));
+            if declared.contains(name) || referenced.contains(name) {
+                return Err(CompileError::DuplicateDeclaration(name.to_string()));
+            }
+            declared.insert(name.to_string());
+            if annotates {
+                annotated.insert(name.to_string());
+            }
+            compiled_declarations.push((name.to_string(), expression));
+        }
+
+        let body = match message {
+            Message::Pattern(pattern) => {
+                Body::Pattern(compile_pattern(pattern.pattern(), registry))
+            }
+            Message::Select(select) => {
+                let selectors: Vec<String> = select
+                    .selectors()
+                    .iter()
+                    .map(|selector| selector.name().to_string())
+                    .collect();
+                if let Some(selector) = selectors.iter().find(|s| !annotated.contains(*s)) {
+                    return Err(CompileError::MissingSelectorAnnotation(selector.clone()));
+                }
+                let mut variants = Vec::new();
+                let mut seen = HashSet::new();
+                for variant in select.variants() {
+                    if variant.keys().len() != selectors.len() {
+                        return Err(CompileError::VariantKeyMismatch);
+                    }
+                    let keys: Vec<Option<String>> = variant
+                        .keys()
+                        .iter()
+                        .map(|key| match key {
+                            VariantKey::Literal(literal) => Some(literal.normalize().into_owned()),
+                            VariantKey::CatchallKey(_) => None,
+                        })
+                        .collect();
+                    if !seen.insert(keys.clone()) {
+                        return Err(CompileError::DuplicateVariant);
+                    }
+                    variants.push(CompiledVariant {
+                        keys,
+                        ops: compile_pattern(variant.value(), registry),
+                    });
+                }
+                if !variants.iter().any(|v| v.keys.iter().all(Option::is_none)) {
+                    return Err(CompileError::MissingFallbackVariant);
+                }
+                Body::Select {
+                    selectors,
+                    variants,
+                }
+            }
+        };
+
+        Ok(Self {
+            declarations: compiled_declarations,
+            body,
+        })
+    }
+
+    /// 変数を適用してフォーマットする。解決できなかった式はフォールバック値で出力し、エラーを返す
+    pub fn format<T: FormatValue>(
+        &self,
+        options: &FormatOptions,
+        variables: &HashMap<String, T>,
+    ) -> (String, Vec<FormatError>) {
+        // カスタム関数の実装が渡されないので、呼び出しは関数のエラーとしてフォールバックする
+        self.format_with(options, variables, &mut |_, _, _| {
+            Err(FunctionError::Failed)
+        })
+    }
+
+    /// カスタム関数 (MessageFunction::Custom) の呼び出しを custom に委ねてフォーマットする
+    pub fn format_with<T: FormatValue>(
+        &self,
+        options: &FormatOptions,
+        variables: &HashMap<String, T>,
+        custom: &mut CustomFunctionCall,
+    ) -> (String, Vec<FormatError>) {
+        let locale = &options.locale;
+        let mut context = Context {
+            locale,
+            variables,
+            custom,
+            scope: HashMap::new(),
+            errors: Vec::new(),
+        };
+        for (name, expression) in &self.declarations {
+            let value = context.resolve_expression(expression);
+            context.scope.insert(name.as_str(), value);
+        }
+
+        let ops = match &self.body {
+            Body::Pattern(ops) => ops,
+            Body::Select {
+                selectors,
+                variants,
+            } => context.select(selectors, variants),
+        };
+
+        let mut result = String::new();
+        for op in ops {
+            match op {
+                Op::Text(text) => result.push_str(text),
+                Op::Expression(expression) => {
+                    let (text, dir) = match context.resolve_expression(expression) {
+                        Resolved::Value(value) => (value.format(locale), value.direction(locale)),
+                        Resolved::Fallback(fallback) => {
+                            (format!("{{{}}}", fallback), Direction::Auto)
+                        }
+                    };
+                    push_placeholder(&mut result, &text, dir, options);
+                }
+            }
+        }
+        (result, context.errors)
+    }
+}
+
+/// 仕様の "default" 戦略でプレースホルダーを分離して出力する
+fn push_placeholder(result: &mut String, text: &str, dir: Direction, options: &FormatOptions) {
+    let isolate = match (options.bidi_isolation, dir) {
+        (BidiIsolation::None, _) => None,
+        (_, Direction::Ltr) if options.dir == Direction::Ltr => None,
+        // LRI
+        (_, Direction::Ltr) => Some('\u{2066}'),
+        // RLI
+        (_, Direction::Rtl) => Some('\u{2067}'),
+        // FSI
+        (_, Direction::Auto) => Some('\u{2068}'),
+    };
+    match isolate {
+        Some(start) => {
+            result.push(start);
+            result.push_str(text);
+            // PDI
+            result.push('\u{2069}');
+        }
+        None => result.push_str(text),
+    }
+}
+
+fn compile_pattern(items: &[PatternItem], registry: &FunctionRegistry) -> Vec<Op> {
+    let mut ops = Vec::new();
+    for item in items {
+        match item {
+            PatternItem::String(text) => match ops.last_mut() {
+                Some(Op::Text(last)) => last.push_str(text),
+                _ => ops.push(Op::Text(text.to_string())),
+            },
+            PatternItem::Expression(expression) => ops.push(Op::Expression(
+                CompiledExpression::new(expression, registry),
+            )),
+            // 文字列へのフォーマットではマークアップは何も出力しない
+            PatternItem::Markup(_) => {}
+        }
+    }
+    ops
+}
+
+struct Context<'a, 'c, T: FormatValue> {
+    locale: &'a Locale,
+    variables: &'a HashMap<String, T>,
+    custom: &'a mut CustomFunctionCall<'c>,
+    scope: HashMap<&'a str, Resolved>,
+    errors: Vec<FormatError>,
+}
+
+impl<'a, T: FormatValue> Context<'a, '_, T> {
+    fn error(&mut self, kind: FormatErrorKind, span: Option<&Range<usize>>) {
+        self.errors.push(FormatError {
+            kind,
+            span: span.cloned(),
+        });
+    }
+
+    fn resolve_variable(&mut self, name: &str, span: Option<&Range<usize>>) -> Resolved {
+        if let Some(value) = self.scope.get(name) {
+            return value.clone();
+        }
+        match self.variables.get(name) {
+            Some(value) => Resolved::Value(value.to_message_value()),
+            None => {
+                self.error(FormatErrorKind::UnresolvedVariable(name.to_string()), span);
+                Resolved::Fallback(format!("${}", name))
+            }
+        }
+    }
+
+    fn resolve_operand(&mut self, operand: &Operand, span: &Range<usize>) -> Option<Resolved> {
+        match operand {
+            Operand::None => None,
+            Operand::Literal(value) => Some(Resolved::Value(MessageValue::String(value.clone()))),
+            Operand::Variable(name) => Some(self.resolve_variable(name, Some(span))),
+        }
+    }
+
+    fn resolve_expression(&mut self, expression: &CompiledExpression) -> Resolved {
+        let span = &expression.span;
+        let operand = self.resolve_operand(&expression.operand, span);
+        let Some(call) = &expression.function else {
+            return operand.unwrap_or_else(|| Resolved::Fallback(String::new()));
+        };
+        let operand = match operand {
+            Some(Resolved::Fallback(fallback)) => return Resolved::Fallback(fallback),
+            Some(Resolved::Value(value)) => Some(value),
+            None => None,
+        };
+        let fallback = match &expression.operand {
+            Operand::None => format!(":{}", call.name),
+            Operand::Literal(value) => {
+                format!("|{}|", value.replace('\\', "\\\\").replace('|', "\\|"))
+            }
+            Operand::Variable(name) => format!("${}", name),
+        };
+        let Some(function) = call.function else {
+            self.error(
+                FormatErrorKind::UnknownFunction(call.name.clone()),
+                Some(span),
+            );
+            return Resolved::Fallback(fallback);
+        };
+
+        // 解決できなかったオプションは省略する
+        let mut options = HashMap::new();
+        for (name, value) in &call.options {
+            if let Some(Resolved::Value(value)) = self.resolve_operand(value, span) {
+                options.insert(name.clone(), value);
+            }
+        }
+        let result = match function {
+            MessageFunction::Custom => (self.custom)(&call.name, operand.as_ref(), &options),
+            _ => function.call(operand.as_ref(), &options),
+        };
+        match result {
+            Ok(value) => Resolved::Value(value),
+            Err(error) => {
+                let kind = match error {
+                    FunctionError::BadOperand => FormatErrorKind::BadOperand(call.name.clone()),
+                    FunctionError::BadOption(name) => FormatErrorKind::BadOption(name),
+                    FunctionError::BadResult => {
+                        FormatErrorKind::BadFunctionResult(call.name.clone())
+                    }
+                    FunctionError::Failed => FormatErrorKind::FunctionError(call.name.clone()),
+                };
+                self.error(kind, Some(span));
+                Resolved::Fallback(fallback)
+            }
+        }
+    }
+
+    /// 各セレクタのキーの優先度を先頭から比較し、最も優先されるバリアントを選ぶ
+    fn select<'v>(&mut self, selectors: &[String], variants: &'v [CompiledVariant]) -> &'v [Op] {
+        let values: Vec<Resolved> = selectors
+            .iter()
+            // セレクタは宣言済みなので、未解決のエラーは宣言の側で報告される
+            .map(|selector| self.resolve_variable(selector, None))
+            .collect();
+        let mut best: Option<(&CompiledVariant, Vec<usize>)> = None;
+        'variants: for variant in variants {
+            let mut ranks = Vec::new();
+            for (key, value) in variant.keys.iter().zip(values.iter()) {
+                let rank = match (key, value) {
+                    (None, _) => usize::MAX,
+                    (Some(key), Resolved::Value(value)) => {
+                        match value.match_key(key, self.locale) {
+                            Some(rank) => rank,
+                            None => continue 'variants,
+                        }
+                    }
+                    (Some(_), Resolved::Fallback(_)) => continue 'variants,
+                };
+                ranks.push(rank);
+            }
+            if best
+                .as_ref()
+                .is_none_or(|(_, best_ranks)| ranks < *best_ranks)
+            {
+                best = Some((variant, ranks));
+            }
+        }
+        // 検証により catch-all のみのバリアントが必ず存在する
+        best.map(|(variant, _)| variant.ops.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale::{LocaleMatcher, resolve_locale};
+
+    impl FormatValue for String {}
+
+    fn compile(src: &str) -> Result<CompiledMessage, CompileError> {
+        CompiledMessage::compile(src, &FunctionRegistry::default())
+    }
+
+    fn print(src: &str, variables: &[(&str, &str)]) -> String {
+        let variables: HashMap<String, String> = variables
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        compile(src)
+            .unwrap()
+            .format(&options("en-US"), &variables)
+            .0
+    }
+
+    fn options(tag: &str) -> FormatOptions {
+        FormatOptions {
+            bidi_isolation: BidiIsolation::None,
+            ..FormatOptions::new(Locale::parse(tag).unwrap())
+        }
+    }
+
+    #[test]
+    fn test_format_simple_pattern() {
+        assert_eq!(
+            print("Hello {$place}!", &[("place", "World")]),
+            "Hello World!"
+        );
+        assert_eq!(print("Hello {$place}!", &[]), "Hello {$place}!");
+        assert_eq!(print("{|literal|} {4.2 :number}", &[]), "literal 4.2");
+    }
+
+    #[test]
+    fn test_bidi_isolation() {
+        let variables = HashMap::from([("name".to_string(), "World".to_string())]);
+        let format = |src: &str, tag: &str| {
+            compile(src)
+                .unwrap()
+                .format(&FormatOptions::new(Locale::parse(tag).unwrap()), &variables)
+                .0
+        };
+        // 文字列の方向は不明なので FSI で囲む
+        assert_eq!(format("Hi {$name}", "en"), "Hi \u{2068}World\u{2069}");
+        // 数値はロケールの方向を持ち、メッセージと同じ LTR なら分離しない
+        assert_eq!(format("{1 :number}", "en"), "1");
+        assert_eq!(format("{1 :number}", "ar"), "\u{2067}1\u{2069}");
+    }
+
+    #[test]
+    fn test_default_direction_from_resolved_locale() {
+        let resolve = |tag: &str| {
+            let locale = resolve_locale(&[tag.to_string()], LocaleMatcher::Lookup).unwrap();
+            FormatOptions::new(locale).dir
+        };
+        assert_eq!(resolve("he-IL"), Direction::Rtl);
+        assert_eq!(resolve("en-US"), Direction::Ltr);
+    }
+
+    #[test]
+    fn test_select_matches_keys_in_nfc() {
+        // キーは合成済み (U+00E9)、値は分解済み (e + U+0301)
+        let src = ".input {$x :string} .match $x caf\u{e9} {{precomposed key}} * {{other}}";
+        assert_eq!(print(src, &[("x", "cafe\u{301}")]), "precomposed key");
+        // キーは分解済み、値は合成済み
+        let src = ".input {$x :string} .match $x cafe\u{301} {{decomposed key}} * {{other}}";
+        assert_eq!(print(src, &[("x", "caf\u{e9}")]), "decomposed key");
+        assert_eq!(print(src, &[("x", "cafe")]), "other");
+    }
+
+    #[test]
+    fn test_select_prefers_exact_match_over_catchall() {
+        let src = ".input {$a :string} .input {$b :string} .match $a $b * b {{catchall}} a b {{exact}} * * {{other}}";
+        assert_eq!(print(src, &[("a", "a"), ("b", "b")]), "exact");
+        assert_eq!(print(src, &[("a", "x"), ("b", "b")]), "catchall");
+        assert_eq!(print(src, &[("a", "x"), ("b", "y")]), "other");
+    }
+
+    #[test]
+    fn test_static_text_is_merged() {
+        let compiled = compile("Hello, {#b}world{/b}!").unwrap();
+        assert_eq!(
+            compiled.body,
+            Body::Pattern(vec![Op::Text("Hello, world!".to_string())])
+        );
+    }
+
+    #[test]
+    fn test_functions_are_resolved_at_compile_time() {
+        let compiled = compile("{$x :number} {|y| :unknown}").unwrap();
+        let Body::Pattern(ops) = &compiled.body else {
+            panic!("expected a pattern message");
+        };
+        let functions: Vec<Option<MessageFunction>> = ops
+            .iter()
+            .filter_map(|op| match op {
+                Op::Expression(expr) => expr.function.as_ref().map(|f| f.function),
+                Op::Text(_) => None,
+            })
+            .collect();
+        assert_eq!(functions, vec![Some(MessageFunction::Number), None]);
+
+        let variables = HashMap::from([("x".to_string(), "1234".to_string())]);
+        let (output, errors) = compiled.format(&options("en-US"), &variables);
+        assert_eq!(output, "1,234 {|y|}");
+        assert_eq!(
+            errors,
+            vec![FormatError {
+                kind: FormatErrorKind::UnknownFunction("unknown".to_string()),
+                span: Some(13..27),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_custom_function_without_implementation_falls_back() {
+        let mut registry = FunctionRegistry::default();
+        registry.register_custom("tag");
+        let compiled = CompiledMessage::compile("a {$x :tag}", &registry).unwrap();
+        let variables = HashMap::from([("x".to_string(), "1".to_string())]);
+        let (output, errors) = compiled.format(&options("en-US"), &variables);
+        assert_eq!(output, "a {$x}");
+        assert_eq!(
+            errors,
+            vec![FormatError {
+                kind: FormatErrorKind::FunctionError("tag".to_string()),
+                span: Some(2..11),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_data_model_errors() {
+        for (src, error_type) in [
+            ("{{", "syntax-error"),
+            (".match $x * {{x}}", "missing-selector-annotation"),
+            (
+                ".input {$x :string} .match $x a {{a}}",
+                "missing-fallback-variant",
+            ),
+            (
+                ".local $x = {1} .local $x = {2} {{x}}",
+                "duplicate-declaration",
+            ),
+        ] {
+            assert_eq!(
+                compile(src).unwrap_err().error_type(),
+                error_type,
+                "{}",
+                src
+            );
+        }
+    }
+}
@@ -0,0 +1,217 @@
+// message-format-wg のテストスイートを CompiledMessage で実行するテストランナー
+// Reference: https://github.com/unicode-org/message-format-wg/tree/main/test
+use crate::compiled::{BidiIsolation, CompiledMessage, FormatOptions, FormatValue};
+use crate::functions::{FunctionRegistry, MessageValue, NumberValue};
+use crate::json::{JsonParser, JsonValue};
+use crate::locale::Locale;
+use std::collections::HashMap;
+use std::fs;
+use std::panic;
+use std::path::{Path, PathBuf};
+
+const TEST_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/message-format-wg");
+
+/// 現在の実装で通過するケース数の下限。機能が増えたら引き上げる
+const MINIMUM_PASSING_CASES: usize = 178;
+
+struct TestCase {
+    src: String,
+    locale: String,
+    params: HashMap<String, Param>,
+    exp: Option<String>,
+    exp_errors: Vec<String>,
+}
+
+fn test_files() -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for dir in [
+        PathBuf::from(TEST_DIR),
+        Path::new(TEST_DIR).join("functions"),
+    ] {
+        let entries = fs::read_dir(&dir).unwrap_or_else(|e| panic!("{}: {}", dir.display(), e));
+        for entry in entries {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+fn error_types(value: Option<&JsonValue>) -> Option<Vec<String>> {
+    let errors = value?.as_array()?;
+    Some(
+        errors
+            .iter()
+            .filter_map(|e| {
+                e.get("type")
+                    .and_then(|t| t.as_str())
+                    .map(|t| t.to_string())
+            })
+            .collect(),
+    )
+}
+
+/// テストのパラメータ。数値は数値のまま関数に渡す
+#[derive(Debug, Clone)]
+enum Param {
+    String(String),
+    Number(f64),
+}
+
+impl std::fmt::Display for Param {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Param::String(s) => write!(f, "{}", s),
+            Param::Number(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+impl FormatValue for Param {
+    fn to_message_value(&self) -> MessageValue {
+        match self {
+            Param::String(s) => MessageValue::String(s.clone()),
+            Param::Number(n) => MessageValue::Number(NumberValue::new(*n)),
+        }
+    }
+}
+
+fn param_value(value: &JsonValue) -> Option<Param> {
+    match value {
+        JsonValue::String(s) => Some(Param::String(s.clone())),
+        JsonValue::Number(n) => Some(Param::Number(*n)),
+        JsonValue::Bool(b) => Some(Param::String(b.to_string())),
+        _ => None,
+    }
+}
+
+fn load_cases(path: &Path) -> Vec<TestCase> {
+    let src = fs::read_to_string(path).unwrap();
+    let json = JsonParser::new(&src)
+        .parse()
+        .unwrap_or_else(|e| panic!("{}:{}:{}: {}", path.display(), e.line, e.column, e.message));
+    let defaults = json.get("defaultTestProperties");
+    let default_errors = error_types(defaults.and_then(|d| d.get("expErrors")));
+    let default_locale = defaults
+        .and_then(|d| d.get("locale"))
+        .and_then(|l| l.as_str())
+        .unwrap_or("en-US");
+
+    let mut cases = Vec::new();
+    for test in json.get("tests").and_then(|t| t.as_array()).unwrap() {
+        let mut params = HashMap::new();
+        for param in test
+            .get("params")
+            .and_then(|p| p.as_array())
+            .unwrap_or(&Vec::new())
+        {
+            let name = param.get("name").and_then(|n| n.as_str()).unwrap();
+            if let Some(value) = param.get("value").and_then(param_value) {
+                params.insert(name.to_string(), value);
+            }
+        }
+        cases.push(TestCase {
+            src: test
+                .get("src")
+                .and_then(|s| s.as_str())
+                .unwrap()
+                .to_string(),
+            locale: test
+                .get("locale")
+                .and_then(|l| l.as_str())
+                .unwrap_or(default_locale)
+                .to_string(),
+            params,
+            exp: test
+                .get("exp")
+                .and_then(|e| e.as_str())
+                .map(|e| e.to_string()),
+            exp_errors: error_types(test.get("expErrors"))
+                .or(default_errors.clone())
+                .unwrap_or_default(),
+        });
+    }
+    cases
+}
+
+/// 1ケースを実行し、失敗した場合はその理由を返す
+fn run_case(case: &TestCase) -> Result<(), String> {
+    let result = panic::catch_unwind(|| {
+        match CompiledMessage::compile(&case.src, &FunctionRegistry::default()) {
+            Ok(compiled) => {
+                // テストケースの期待値は分離文字を含まない
+                let options = FormatOptions {
+                    bidi_isolation: BidiIsolation::None,
+                    ..FormatOptions::new(Locale::parse(&case.locale).unwrap())
+                };
+                let (output, errors) = compiled.format(&options, &case.params);
+                let errors = errors.iter().map(|e| e.error_type().to_string()).collect();
+                (Some(output), errors)
+            }
+            Err(error) => (None, vec![error.error_type().to_string()]),
+        }
+    });
+    let (output, errors) = match result {
+        Ok(result) => result,
+        Err(_) => return Err("panicked".to_string()),
+    };
+
+    if errors != case.exp_errors {
+        return Err(format!(
+            "expected errors {:?}, got {:?}",
+            case.exp_errors, errors
+        ));
+    }
+    if let Some(exp) = &case.exp
+        && output.as_ref() != Some(exp)
+    {
+        return Err(format!("expected {:?}, got {:?}", exp, output));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_conformance() {
+    // パーサーのpanicはケースの失敗として扱うので、メッセージは出力しない
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+
+    let mut passed = 0;
+    let mut total = 0;
+    for path in test_files() {
+        let name = path.strip_prefix(TEST_DIR).unwrap().display().to_string();
+        let cases = load_cases(&path);
+        let mut file_passed = 0;
+        println!("{}", name);
+        for case in &cases {
+            match run_case(case) {
+                Ok(()) => {
+                    file_passed += 1;
+                    println!("  pass {:?}", case.src);
+                }
+                Err(reason) => println!("  FAIL {:?}: {}", case.src, reason),
+            }
+        }
+        println!("  {}/{} passed", file_passed, cases.len());
+        passed += file_passed;
+        total += cases.len();
+    }
+
+    panic::set_hook(hook);
+
+    println!(
+        "conformance: {}/{} ({:.1}%)",
+        passed,
+        total,
+        passed as f64 * 100.0 / total as f64
+    );
+    assert!(
+        passed >= MINIMUM_PASSING_CASES,
+        "conformance regressed: {} cases passed, expected at least {}",
+        passed,
+        MINIMUM_PASSING_CASES
+    );
+}
@@ -0,0 +1,533 @@
+// console.log などの出力。値は Node.js の util.inspect に近い形で表示する
+// Reference: https://console.spec.whatwg.org/
+// Reference: https://nodejs.org/api/util.html#utilinspectobject-options
+use crate::numeric::{number_to_string, string_to_number};
+use crate::object::array_index;
+use crate::runtime::RuntimeValue;
+
+/// 1行に収める長さ。超える場合は要素ごとに改行する
+const BREAK_LENGTH: usize = 80;
+/// これより深いオブジェクトと配列は [Object] や [Array] と略す
+const MAX_DEPTH: usize = 2;
+
+/// console.log の引数を空白で区切って並べる。最初の引数が文字列なら %s などの書式を展開する
+/// Reference: https://console.spec.whatwg.org/#formatter
+pub fn format_args(args: &[RuntimeValue]) -> String {
+    let mut args = args.iter();
+    let mut parts = Vec::new();
+    if let Some(RuntimeValue::StringLiteral(format)) = args.as_slice().first() {
+        args.next();
+        parts.push(substitute(format, &mut args));
+    }
+    parts.extend(args.map(inspect));
+    parts.join(" ")
+}
+
+/// %s、%d、%i、%f、%o、%O を後ろの引数で置き換える。%i だけは小数部を切り捨てる。
+/// 引数が足りなければそのまま残す
+fn substitute<'a>(format: &str, args: &mut impl Iterator<Item = &'a RuntimeValue>) -> String {
+    let mut output = String::new();
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        let Some(&specifier) = chars.peek().filter(|_| c == '%') else {
+            output.push(c);
+            continue;
+        };
+        if specifier == '%' {
+            chars.next();
+            output.push('%');
+            continue;
+        }
+        if !"sdifoO".contains(specifier) {
+            output.push(c);
+            continue;
+        }
+        let Some(arg) = args.next() else {
+            output.push(c);
+            continue;
+        };
+        chars.next();
+        let replacement = match specifier {
+            's' => inspect(arg),
+            'd' | 'i' | 'f' => match arg {
+                RuntimeValue::BigInt(value) if specifier != 'f' => format!("{}n", value),
+                arg => {
+                    let value = to_number(arg);
+                    let value = if specifier == 'i' {
+                        value.trunc()
+                    } else {
+                        value
+                    };
+                    number_to_string(value)
+                }
+            },
+            _ => Inspector::new(MAX_DEPTH).inspect(arg, 0, 0),
+        };
+        output.push_str(&replacement);
+    }
+    output
+}
+
+fn to_number(value: &RuntimeValue) -> f64 {
+    match value {
+        RuntimeValue::Number(value) => *value,
+        RuntimeValue::BigInt(value) => value.to_f64(),
+        RuntimeValue::Boolean(value) => f64::from(u8::from(*value)),
+        RuntimeValue::StringLiteral(value) => string_to_number(value),
+        RuntimeValue::Null => 0.0,
+        _ => f64::NAN,
+    }
+}
+
+/// console.log の引数1つの表示。文字列はそのまま、エラーは "名前: メッセージ" で出力する
+pub fn inspect(value: &RuntimeValue) -> String {
+    match value {
+        RuntimeValue::StringLiteral(value) => value.clone(),
+        RuntimeValue::Error(_) => value.to_string(),
+        value => Inspector::new(MAX_DEPTH).inspect(value, 0, 0),
+    }
+}
+
+/// console.table の表。data がオブジェクトでも配列でもなければ None
+/// Reference: https://console.spec.whatwg.org/#table
+pub fn table(data: &RuntimeValue, properties: Option<&RuntimeValue>) -> Option<String> {
+    let rows = own_entries(data)?;
+
+    // 列は行のプロパティを現れた順に並べる。properties があればその列だけにする
+    let mut columns: Vec<String> = match properties {
+        Some(RuntimeValue::Array(names)) => names
+            .borrow()
+            .iter()
+            .flatten()
+            .map(|name| name.to_string())
+            .collect(),
+        _ => Vec::new(),
+    };
+    let fixed_columns = !columns.is_empty();
+    let mut has_values = false;
+    let mut cells = Vec::new();
+    for (_, row) in &rows {
+        let entries = match own_entries(row) {
+            Some(entries) => entries,
+            None => {
+                has_values = true;
+                Vec::new()
+            }
+        };
+        for (key, _) in &entries {
+            if !fixed_columns && !columns.contains(key) {
+                columns.push(key.clone());
+            }
+        }
+        cells.push(entries);
+    }
+
+    let mut header = vec!["(index)".to_string()];
+    header.extend(columns.iter().cloned());
+    if has_values {
+        header.push("Values".to_string());
+    }
+    let mut body = Vec::new();
+    for ((index, row), entries) in rows.iter().zip(cells) {
+        let mut line = vec![index.clone()];
+        for column in &columns {
+            let cell = entries.iter().find(|(key, _)| key == column);
+            line.push(
+                cell.map(|(_, value)| inspect_cell(value))
+                    .unwrap_or_default(),
+            );
+        }
+        if has_values {
+            let is_object = matches!(row, RuntimeValue::Object(_) | RuntimeValue::Array(_));
+            line.push(if is_object {
+                String::new()
+            } else {
+                inspect_cell(row)
+            });
+        }
+        body.push(line);
+    }
+    Some(render_table(&header, &body))
+}
+
+/// 自身のプロパティのキーと値。配列は添字をキーにし、穴は飛ばす。オブジェクトでも配列でもなければ None
+fn own_entries(value: &RuntimeValue) -> Option<Vec<(String, RuntimeValue)>> {
+    match value {
+        RuntimeValue::Array(items) => Some(
+            items
+                .borrow()
+                .iter()
+                .enumerate()
+                .filter_map(|(index, item)| Some((index.to_string(), item.clone()?)))
+                .collect(),
+        ),
+        RuntimeValue::Object(object) => {
+            let object = object.borrow();
+            Some(
+                object
+                    .own_keys()
+                    .into_iter()
+                    .filter_map(|key| Some((key.clone(), object.get_own(&key)?.clone())))
+                    .collect(),
+            )
+        }
+        _ => None,
+    }
+}
+
+/// 表のセルの中身。入れ子のオブジェクトは略し、改行しない
+fn inspect_cell(value: &RuntimeValue) -> String {
+    let mut inspector = Inspector::new(0);
+    inspector.break_length = usize::MAX;
+    inspector.inspect(value, 0, 0)
+}
+
+fn render_table(header: &[String], body: &[Vec<String>]) -> String {
+    let widths: Vec<usize> = (0..header.len())
+        .map(|column| {
+            body.iter()
+                .map(|line| string_width(&line[column]))
+                .chain([string_width(&header[column])])
+                .max()
+                .unwrap_or_default()
+                + 2
+        })
+        .collect();
+    let border = |left: &str, middle: &str, right: &str| {
+        let lines: Vec<String> = widths.iter().map(|width| "─".repeat(*width)).collect();
+        format!("{}{}{}", left, lines.join(middle), right)
+    };
+    let row = |cells: &[String]| {
+        let cells: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| {
+                let padding = width - 1 - string_width(cell);
+                format!(" {}{}", cell, " ".repeat(padding))
+            })
+            .collect();
+        format!("│{}│", cells.join("│"))
+    };
+    let mut lines = vec![border("┌", "┬", "┐"), row(header), border("├", "┼", "┤")];
+    lines.extend(body.iter().map(|cells| row(cells)));
+    lines.push(border("└", "┴", "┘"));
+    lines.join("\n")
+}
+
+/// 端末での表示幅。全角文字は2、書式文字は0として数える
+fn string_width(s: &str) -> usize {
+    s.chars()
+        .map(|c| match c as u32 {
+            0x200B..=0x200F | 0x2066..=0x2069 | 0xFE00..=0xFE0F => 0,
+            0x1100..=0x115F
+            | 0x2E80..=0xA4CF
+            | 0xAC00..=0xD7A3
+            | 0xF900..=0xFAFF
+            | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFF60
+            | 0xFFE0..=0xFFE6
+            | 0x1F300..=0x1F64F
+            | 0x1F900..=0x1F9FF
+            | 0x20000..=0x3FFFD => 2,
+            _ => 1,
+        })
+        .sum()
+}
+
+/// util.inspect の表示。循環する参照は [Circular *1] と表示する
+struct Inspector {
+    max_depth: usize,
+    break_length: usize,
+    /// 今たどっているオブジェクトと配列
+    seen: Vec<RuntimeValue>,
+    /// 循環して参照されたオブジェクト。添字 + 1 が <ref *n> の番号
+    circular: Vec<RuntimeValue>,
+}
+
+impl Inspector {
+    fn new(max_depth: usize) -> Self {
+        Self {
+            max_depth,
+            break_length: BREAK_LENGTH,
+            seen: Vec::new(),
+            circular: Vec::new(),
+        }
+    }
+
+    /// indentation は改行したときの字下げの幅
+    fn inspect(&mut self, value: &RuntimeValue, depth: usize, indentation: usize) -> String {
+        match value {
+            RuntimeValue::StringLiteral(value) => quote(value),
+            RuntimeValue::Number(value) if *value == 0.0 && value.is_sign_negative() => {
+                "-0".to_string()
+            }
+            RuntimeValue::BigInt(value) => format!("{}n", value),
+            RuntimeValue::Function(function) => match function.name() {
+                Some(name) => format!("[Function: {}]", name),
+                None => "[Function (anonymous)]".to_string(),
+            },
+            RuntimeValue::NativeFunction(function) => format!("[Function: {}]", function.name()),
+            RuntimeValue::Error(_) => format!("[{}]", value),
+            RuntimeValue::Locale(_) => "Locale [Intl.Locale] {}".to_string(),
+            RuntimeValue::MessageFormatInstance(instance) => {
+                let entries = vec![
+                    format!("locale: {}", quote(&instance.locale().to_string())),
+                    format!("source: {}", quote(instance.source())),
+                ];
+                self.reduce("MessageFormat {", entries, "}", indentation)
+            }
+            RuntimeValue::Object(_) | RuntimeValue::Array(_) => {
+                self.inspect_container(value, depth, indentation)
+            }
+            value => value.to_string(),
+        }
+    }
+
+    fn inspect_container(
+        &mut self,
+        value: &RuntimeValue,
+        depth: usize,
+        indentation: usize,
+    ) -> String {
+        if self.seen.contains(value) {
+            let index = match self.circular.iter().position(|seen| seen == value) {
+                Some(index) => index,
+                None => {
+                    self.circular.push(value.clone());
+                    self.circular.len() - 1
+                }
+            };
+            return format!("[Circular *{}]", index + 1);
+        }
+        let items = match value {
+            RuntimeValue::Array(items) => Some(items.borrow().clone()),
+            _ => None,
+        };
+        let Some(entries) = own_entries(value) else {
+            unreachable!("inspect_container takes objects and arrays");
+        };
+        let is_array = items.is_some();
+        if entries.is_empty() && items.as_ref().is_none_or(|items| items.is_empty()) {
+            return if is_array { "[]" } else { "{}" }.to_string();
+        }
+        if depth > self.max_depth {
+            return if is_array { "[Array]" } else { "[Object]" }.to_string();
+        }
+
+        self.seen.push(value.clone());
+        let entries = match items {
+            // 続く穴は <N empty items> にまとめる
+            Some(items) => items
+                .chunk_by(|a, b| a.is_none() && b.is_none())
+                .map(|chunk| match &chunk[0] {
+                    Some(item) => self.inspect(item, depth + 1, indentation + 2),
+                    None if chunk.len() == 1 => "<1 empty item>".to_string(),
+                    None => format!("<{} empty items>", chunk.len()),
+                })
+                .collect(),
+            None => entries
+                .iter()
+                .map(|(key, item)| {
+                    let item = self.inspect(item, depth + 1, indentation + 2);
+                    format!("{}: {}", property_key(key), item)
+                })
+                .collect(),
+        };
+        self.seen.pop();
+
+        let (open, close) = if is_array { ("[", "]") } else { ("{", "}") };
+        let output = self.reduce(open, entries, close, indentation);
+        match self.circular.iter().position(|seen| seen == value) {
+            Some(index) => format!("<ref *{}> {}", index + 1, output),
+            None => output,
+        }
+    }
+
+    /// 短ければ1行に、長ければ要素ごとに改行して並べる
+    fn reduce(&self, open: &str, entries: Vec<String>, close: &str, indentation: usize) -> String {
+        let length = entries
+            .iter()
+            .map(|entry| string_width(entry))
+            .sum::<usize>()
+            + entries.len() * 2
+            + indentation
+            + open.len()
+            + 10;
+        if length <= self.break_length && entries.iter().all(|entry| !entry.contains('\n')) {
+            return format!("{} {} {}", open, entries.join(", "), close);
+        }
+        let padding = " ".repeat(indentation + 2);
+        format!(
+            "{}\n{}{}\n{}{}",
+            open,
+            padding,
+            entries.join(&format!(",\n{}", padding)),
+            " ".repeat(indentation),
+            close
+        )
+    }
+}
+
+/// 文字列を引用符で囲む。' を含めば " を、両方を含めば ` を使う
+fn quote(s: &str) -> String {
+    let quote = if !s.contains('\'') {
+        '\''
+    } else if !s.contains('"') {
+        '"'
+    } else if !s.contains('`') && !s.contains("${") {
+        '`'
+    } else {
+        '\''
+    };
+    let mut output = String::from(quote);
+    for c in s.chars() {
+        match c {
+            '\n' => output.push_str("\\n"),
+            '\t' => output.push_str("\\t"),
+            '\r' => output.push_str("\\r"),
+            '\\' => output.push_str("\\\\"),
+            c if c == quote => {
+                output.push('\\');
+                output.push(c);
+            }
+            c if c.is_control() => output.push_str(&format!("\\x{:02X}", c as u32)),
+            c => output.push(c),
+        }
+    }
+    output.push(quote);
+    output
+}
+
+/// 識別子として書けるキーはそのまま、それ以外は引用符で囲む
+fn property_key(key: &str) -> String {
+    let mut chars = key.chars();
+    let is_identifier = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+    if is_identifier && array_index(key).is_none() {
+        key.to_string()
+    } else {
+        quote(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::{Object, Shared};
+
+    fn string(value: &str) -> RuntimeValue {
+        RuntimeValue::StringLiteral(value.to_string())
+    }
+
+    fn object(properties: Vec<(&str, RuntimeValue)>) -> RuntimeValue {
+        RuntimeValue::Object(Shared::new(Object::with_properties(
+            properties
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value)),
+        )))
+    }
+
+    fn array(items: Vec<RuntimeValue>) -> RuntimeValue {
+        RuntimeValue::Array(Shared::new(items.into_iter().map(Some).collect()))
+    }
+
+    #[test]
+    fn test_inspect() {
+        let value = object(vec![
+            ("name", string("Ada")),
+            ("it's", RuntimeValue::Number(-0.0)),
+            ("tags", array(vec![string("a"), RuntimeValue::Null])),
+            ("empty", object(vec![])),
+        ]);
+        assert_eq!(
+            inspect(&value),
+            "{ name: 'Ada', \"it's\": -0, tags: [ 'a', null ], empty: {} }"
+        );
+        assert_eq!(inspect(&string("raw")), "raw");
+
+        let deep = array(vec![array(vec![array(vec![array(vec![
+            RuntimeValue::Number(1.0),
+        ])])])]);
+        assert_eq!(inspect(&deep), "[ [ [ [Array] ] ] ]");
+    }
+
+    #[test]
+    fn test_inspect_breaks_long_objects() {
+        let long = "x".repeat(40);
+        let value = object(vec![
+            ("a", string(&long)),
+            ("b", object(vec![("c", string(&long))])),
+        ]);
+        let expected = format!("{{\n  a: '{long}',\n  b: {{ c: '{long}' }}\n}}");
+        assert_eq!(inspect(&value), expected);
+    }
+
+    #[test]
+    fn test_inspect_circular() {
+        let value = object(vec![("name", string("loop"))]);
+        if let RuntimeValue::Object(shared) = &value {
+            shared.borrow_mut().set("self".to_string(), value.clone());
+        }
+        assert_eq!(
+            inspect(&value),
+            "<ref *1> { name: 'loop', self: [Circular *1] }"
+        );
+    }
+
+    #[test]
+    fn test_inspect_holes() {
+        let value = RuntimeValue::Array(Shared::new(vec![
+            Some(RuntimeValue::Number(1.0)),
+            None,
+            Some(RuntimeValue::Undefined),
+            None,
+            None,
+        ]));
+        assert_eq!(
+            inspect(&value),
+            "[ 1, <1 empty item>, undefined, <2 empty items> ]"
+        );
+        assert_eq!(
+            inspect(&RuntimeValue::Array(Shared::new(vec![None]))),
+            "[ <1 empty item> ]"
+        );
+    }
+
+    #[test]
+    fn test_format_args() {
+        let args = [
+            string("%s has %i items (100%%) %o"),
+            string("cart"),
+            string("3.7"),
+            array(vec![RuntimeValue::Boolean(true)]),
+            RuntimeValue::Undefined,
+        ];
+        assert_eq!(
+            format_args(&args),
+            "cart has 3 items (100%) [ true ] undefined"
+        );
+        assert_eq!(format_args(&[string("%s")]), "%s");
+    }
+
+    #[test]
+    fn test_table() {
+        let data = array(vec![
+            object(vec![("a", RuntimeValue::Number(1.0)), ("b", string("x"))]),
+            object(vec![("a", RuntimeValue::Number(22.0))]),
+            string("日本"),
+        ]);
+        let expected = [
+            "┌─────────┬────┬─────┬────────┐",
+            "│ (index) │ a  │ b   │ Values │",
+            "├─────────┼────┼─────┼────────┤",
+            "│ 0       │ 1  │ 'x' │        │",
+            "│ 1       │ 22 │     │        │",
+            "│ 2       │    │     │ '日本' │",
+            "└─────────┴────┴─────┴────────┘",
+        ];
+        assert_eq!(table(&data, None).unwrap(), expected.join("\n"));
+        assert_eq!(table(&string("x"), None), None);
+    }
+}
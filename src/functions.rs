@@ -0,0 +1,577 @@
+// MessageFormat v2 の関数レジストリと組み込み関数 (:string, :number, :integer)
+// Reference: https://github.com/unicode-org/message-format-wg/blob/main/spec/registry.md
+use crate::compiled::Direction;
+use crate::locale::Locale;
+use mf2_parser::normalization::nfc;
+use std::collections::HashMap;
+
+/// 関数の入力・出力となる値
+#[derive(Debug, Clone, PartialEq)]
+pub enum MessageValue {
+    String(String),
+    Number(NumberValue),
+}
+
+impl MessageValue {
+    /// 書式化した文字列を返す
+    pub fn format(&self, locale: &Locale) -> String {
+        match self {
+            MessageValue::String(s) => s.clone(),
+            MessageValue::Number(number) => number.format(locale),
+        }
+    }
+
+    /// 書式化した文字列の方向。文字列は内容から判断しないので不明とする
+    pub fn direction(&self, locale: &Locale) -> Direction {
+        match self {
+            MessageValue::String(_) => Direction::Auto,
+            MessageValue::Number(_) if locale.is_rtl() => Direction::Rtl,
+            MessageValue::Number(_) => Direction::Ltr,
+        }
+    }
+
+    /// オプション値やキーとの比較に使う、書式化前の文字列表現
+    fn plain(&self) -> String {
+        match self {
+            MessageValue::String(s) => s.clone(),
+            MessageValue::Number(number) => number.value.to_string(),
+        }
+    }
+
+    /// キーに一致する場合は優先度（小さいほど優先）を返す
+    pub fn match_key(&self, key: &str, locale: &Locale) -> Option<usize> {
+        match self {
+            MessageValue::String(s) => (nfc(s) == key).then_some(0),
+            MessageValue::Number(number) => number.match_key(key, locale),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NumberSelect {
+    #[default]
+    Plural,
+    Ordinal,
+    Exact,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumberOptions {
+    pub minimum_fraction_digits: usize,
+    pub maximum_fraction_digits: usize,
+    pub use_grouping: bool,
+    pub select: NumberSelect,
+}
+
+impl Default for NumberOptions {
+    fn default() -> Self {
+        Self {
+            minimum_fraction_digits: 0,
+            maximum_fraction_digits: 3,
+            use_grouping: true,
+            select: NumberSelect::Plural,
+        }
+    }
+}
+
+/// :number / :integer が返す値。書式化オプションを保持し、参照先でも引き継がれる
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumberValue {
+    pub value: f64,
+    pub options: NumberOptions,
+}
+
+impl NumberValue {
+    pub fn new(value: f64) -> Self {
+        Self {
+            value,
+            options: NumberOptions::default(),
+        }
+    }
+
+    /// 丸めた整数部と小数部の数字列を返す
+    /// 2進の誤差を持ち込まないよう、最短の10進表現の数字列を四捨五入する
+    fn digits(&self) -> (String, String) {
+        if !self.value.is_finite() {
+            return ("0".to_string(), String::new());
+        }
+        let max = self
+            .options
+            .maximum_fraction_digits
+            .max(self.options.minimum_fraction_digits);
+        // {:e} は "d.ddde±n" の形で最短の桁を返す
+        let scientific = format!("{:e}", self.value.abs());
+        let (mantissa, exponent) = scientific.split_once('e').unwrap();
+        let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+        // 整数部の桁数
+        let point = exponent.parse::<i64>().unwrap() + 1;
+        let (integer, fraction) = if point <= 0 {
+            ("0".to_string(), "0".repeat(-point as usize) + &digits)
+        } else if point as usize >= digits.len() {
+            (
+                digits.clone() + &"0".repeat(point as usize - digits.len()),
+                String::new(),
+            )
+        } else {
+            let (integer, fraction) = digits.split_at(point as usize);
+            (integer.to_string(), fraction.to_string())
+        };
+
+        let mut integer = integer.into_bytes();
+        let mut fraction = fraction.into_bytes();
+        if fraction.len() > max {
+            let round_up = fraction[max] >= b'5';
+            fraction.truncate(max);
+            if round_up {
+                // 繰り上がりを小数部から整数部へ伝える
+                let mut carry = true;
+                for digit in fraction.iter_mut().rev().chain(integer.iter_mut().rev()) {
+                    if *digit == b'9' {
+                        *digit = b'0';
+                    } else {
+                        *digit += 1;
+                        carry = false;
+                        break;
+                    }
+                }
+                if carry {
+                    integer.insert(0, b'1');
+                }
+            }
+        }
+        fraction.resize(
+            fraction.len().max(self.options.minimum_fraction_digits),
+            b'0',
+        );
+        while fraction.len() > self.options.minimum_fraction_digits && fraction.ends_with(b"0") {
+            fraction.pop();
+        }
+        (
+            String::from_utf8(integer).unwrap(),
+            String::from_utf8(fraction).unwrap(),
+        )
+    }
+
+    pub fn format(&self, locale: &Locale) -> String {
+        if self.value.is_nan() {
+            return "NaN".to_string();
+        }
+        if self.value.is_infinite() {
+            return if self.value < 0.0 { "-∞" } else { "∞" }.to_string();
+        }
+        let (integer, fraction) = self.digits();
+        let (decimal, group) = number_symbols(locale);
+        let mut result = String::new();
+        // Intl.NumberFormat と同じく、-0 や 0 に丸められた負の数にも符号を付ける
+        if self.value.is_sign_negative() {
+            result.push('-');
+        }
+        for (i, c) in integer.chars().enumerate() {
+            if self.options.use_grouping && i > 0 && (integer.len() - i) % 3 == 0 {
+                result.push_str(group);
+            }
+            result.push(c);
+        }
+        if !fraction.is_empty() {
+            result.push_str(decimal);
+            result.push_str(&fraction);
+        }
+        match locale.unicode_keyword("nu").and_then(numbering_system_zero) {
+            Some(zero) => result
+                .chars()
+                .map(|c| match c.to_digit(10) {
+                    Some(d) => char::from_u32(zero as u32 + d).unwrap_or(c),
+                    None => c,
+                })
+                .collect(),
+            None => result,
+        }
+    }
+
+    /// 複数形カテゴリ
+    /// Reference: https://www.unicode.org/cldr/charts/latest/supplemental/language_plural_rules.html
+    fn category(&self, locale: &Locale) -> &'static str {
+        let (integer, fraction) = self.digits();
+        // 下6桁があれば規則の判定には十分
+        let i: u64 = integer[integer.len().saturating_sub(6)..]
+            .parse()
+            .unwrap_or(0);
+        let v = fraction.len();
+        // 末尾の0を除いた小数部 (CLDR の t)
+        let t = fraction.trim_end_matches('0');
+        let n_is_one = integer == "1" && t.is_empty();
+        // 0 以外の 100万の倍数 (指数表記をしないので CLDR の e は常に 0)
+        let millions = integer != "0" && i.is_multiple_of(1_000_000) && v == 0;
+        match (self.options.select, locale.language()) {
+            (NumberSelect::Exact, _) => "",
+            (NumberSelect::Plural, "ja" | "zh" | "ko" | "th" | "vi" | "id" | "ms") => "other",
+            (NumberSelect::Plural, "fr" | "pt") if integer == "0" || integer == "1" => "one",
+            (NumberSelect::Plural, "es") if n_is_one => "one",
+            (NumberSelect::Plural, "es" | "fr" | "it" | "pt") if millions => "many",
+            (NumberSelect::Plural, "tr" | "nb") if n_is_one => "one",
+            (NumberSelect::Plural, "da")
+                if n_is_one || (!t.is_empty() && (integer == "0" || integer == "1")) =>
+            {
+                "one"
+            }
+            (NumberSelect::Plural, "ru" | "uk") if v == 0 => match (i % 10, i % 100) {
+                (1, n) if n != 11 => "one",
+                (2..=4, n) if !(12..=14).contains(&n) => "few",
+                _ => "many",
+            },
+            (NumberSelect::Plural, "pl" | "cs") if integer == "1" && v == 0 => "one",
+            (NumberSelect::Plural, "pl") if v == 0 => match (i % 10, i % 100) {
+                (2..=4, n) if !(12..=14).contains(&n) => "few",
+                _ => "many",
+            },
+            (NumberSelect::Plural, "cs") if v != 0 => "many",
+            (NumberSelect::Plural, "cs") if matches!(integer.as_str(), "2" | "3" | "4") => "few",
+            (NumberSelect::Plural, "he") if v == 0 && integer == "2" => "two",
+            (NumberSelect::Plural, "he")
+                if (integer == "1" && v == 0) || (integer == "0" && v != 0) =>
+            {
+                "one"
+            }
+            (
+                NumberSelect::Plural,
+                "es" | "fr" | "pt" | "tr" | "nb" | "da" | "ru" | "uk" | "pl" | "he",
+            ) => "other",
+            // en, de, nl, sv, fi, it
+            (NumberSelect::Plural, _) if integer == "1" && v == 0 => "one",
+            (NumberSelect::Plural, _) => "other",
+            (NumberSelect::Ordinal, "en") => match (i % 10, i % 100) {
+                (1, n) if n != 11 => "one",
+                (2, n) if n != 12 => "two",
+                (3, n) if n != 13 => "few",
+                _ => "other",
+            },
+            (NumberSelect::Ordinal, "fr") if integer == "1" => "one",
+            (NumberSelect::Ordinal, _) => "other",
+        }
+    }
+
+    fn match_key(&self, key: &str, locale: &Locale) -> Option<usize> {
+        if parse_number_literal(key) == Some(self.value) {
+            return Some(0);
+        }
+        (self.category(locale) == key).then_some(1)
+    }
+}
+
+/// 小数点と桁区切りの記号
+/// Reference: https://cldr.unicode.org/translation/numbers-currency/numbers
+fn number_symbols(locale: &Locale) -> (&'static str, &'static str) {
+    match locale.language() {
+        "fr" => (",", "\u{202F}"),
+        "ru" | "uk" | "pl" | "cs" | "sv" | "fi" | "nb" => (",", "\u{A0}"),
+        "de" | "es" | "it" | "nl" | "pt" | "id" | "tr" | "da" => (",", "."),
+        _ => (".", ","),
+    }
+}
+
+/// `-u-nu-` で指定された記数法の0の文字
+fn numbering_system_zero(numbering_system: &str) -> Option<char> {
+    match numbering_system {
+        "arab" => Some('\u{660}'),
+        "arabext" => Some('\u{6F0}'),
+        "beng" => Some('\u{9E6}'),
+        "deva" => Some('\u{966}'),
+        "fullwide" => Some('\u{FF10}'),
+        "thai" => Some('\u{E50}'),
+        _ => None,
+    }
+}
+
+/// MF2の number-literal をパースする
+/// Reference: https://github.com/unicode-org/message-format-wg/blob/main/spec/message.abnf
+pub fn parse_number_literal(s: &str) -> Option<f64> {
+    let bytes = s.as_bytes();
+    let mut pos = 0;
+    let digits = |pos: &mut usize| {
+        let start = *pos;
+        while bytes.get(*pos).is_some_and(u8::is_ascii_digit) {
+            *pos += 1;
+        }
+        *pos - start
+    };
+    if bytes.get(pos) == Some(&b'-') {
+        pos += 1;
+    }
+    let start = pos;
+    match digits(&mut pos) {
+        0 => return None,
+        n if n > 1 && bytes[start] == b'0' => return None,
+        _ => {}
+    }
+    if bytes.get(pos) == Some(&b'.') {
+        pos += 1;
+        if digits(&mut pos) == 0 {
+            return None;
+        }
+    }
+    if let Some(b'e' | b'E') = bytes.get(pos) {
+        pos += 1;
+        if let Some(b'+' | b'-') = bytes.get(pos) {
+            pos += 1;
+        }
+        if digits(&mut pos) == 0 {
+            return None;
+        }
+    }
+    if pos != bytes.len() {
+        return None;
+    }
+    s.parse().ok()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FunctionError {
+    BadOperand,
+    BadOption(String),
+    /// カスタム関数が書式化できる値を返さなかった
+    BadResult,
+    /// カスタム関数の呼び出しが失敗した (例外を投げた場合など)
+    Failed,
+}
+
+/// レジストリで解決済みの関数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageFunction {
+    String,
+    Number,
+    Integer,
+    /// ホスト（JSのランタイム）が名前で呼び出すカスタム関数
+    Custom,
+}
+
+impl MessageFunction {
+    pub fn call(
+        &self,
+        operand: Option<&MessageValue>,
+        options: &HashMap<String, MessageValue>,
+    ) -> Result<MessageValue, FunctionError> {
+        let operand = operand.ok_or(FunctionError::BadOperand)?;
+        match self {
+            MessageFunction::String => Ok(MessageValue::String(operand.plain())),
+            MessageFunction::Number => number(operand, options).map(MessageValue::Number),
+            MessageFunction::Integer => {
+                let mut number = number(operand, options)?;
+                number.value = number.value.trunc();
+                number.options.minimum_fraction_digits = 0;
+                number.options.maximum_fraction_digits = 0;
+                Ok(MessageValue::Number(number))
+            }
+            MessageFunction::Custom => {
+                unreachable!("custom functions are called through CompiledMessage::format_with")
+            }
+        }
+    }
+}
+
+/// オペランドを数値として解決し、オプションを適用する
+fn number(
+    operand: &MessageValue,
+    options: &HashMap<String, MessageValue>,
+) -> Result<NumberValue, FunctionError> {
+    let mut number = match operand {
+        MessageValue::Number(number) => number.clone(),
+        MessageValue::String(s) => {
+            NumberValue::new(parse_number_literal(s).ok_or(FunctionError::BadOperand)?)
+        }
+    };
+    for (name, value) in options {
+        let value = value.plain();
+        let bad_option = || FunctionError::BadOption(name.clone());
+        match name.as_str() {
+            "minimumFractionDigits" | "maximumFractionDigits" => {
+                let digits = value
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|d| *d <= 100)
+                    .ok_or_else(bad_option)?;
+                if name == "minimumFractionDigits" {
+                    number.options.minimum_fraction_digits = digits;
+                } else {
+                    number.options.maximum_fraction_digits = digits;
+                }
+            }
+            "useGrouping" => {
+                number.options.use_grouping = match value.as_str() {
+                    "auto" | "always" | "min2" => true,
+                    "never" => false,
+                    _ => return Err(bad_option()),
+                }
+            }
+            "select" => {
+                number.options.select = match value.as_str() {
+                    "plural" => NumberSelect::Plural,
+                    "ordinal" => NumberSelect::Ordinal,
+                    "exact" => NumberSelect::Exact,
+                    _ => return Err(bad_option()),
+                }
+            }
+            // 未知のオプションは無視する
+            _ => {}
+        }
+    }
+    Ok(number)
+}
+
+/// 関数名から関数を解決するレジストリ
+#[derive(Clone)]
+pub struct FunctionRegistry {
+    functions: HashMap<String, MessageFunction>,
+}
+
+impl Default for FunctionRegistry {
+    fn default() -> Self {
+        let functions = [
+            ("string", MessageFunction::String),
+            ("number", MessageFunction::Number),
+            ("integer", MessageFunction::Integer),
+        ]
+        .into_iter()
+        .map(|(name, function)| (name.to_string(), function))
+        .collect();
+        Self { functions }
+    }
+}
+
+impl FunctionRegistry {
+    pub fn get(&self, name: &str) -> Option<MessageFunction> {
+        self.functions.get(name).copied()
+    }
+
+    /// カスタム関数を登録する。同名の組み込み関数は上書きされる
+    pub fn register_custom(&mut self, name: &str) {
+        self.functions
+            .insert(name.to_string(), MessageFunction::Custom);
+    }
+
+    /// 登録されている関数名（名前順）
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.functions.keys().map(String::as_str).collect();
+        names.sort();
+        names
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn locale(tag: &str) -> Locale {
+        Locale::parse(tag).unwrap()
+    }
+
+    fn format_number(value: f64, min: usize, max: usize) -> String {
+        let mut number = NumberValue::new(value);
+        number.options.minimum_fraction_digits = min;
+        number.options.maximum_fraction_digits = max;
+        number.format(&locale("en-US"))
+    }
+
+    #[test]
+    fn test_format_number() {
+        assert_eq!(format_number(4.2, 0, 3), "4.2");
+        assert_eq!(format_number(4.2, 2, 3), "4.20");
+        assert_eq!(format_number(-4.0, 0, 3), "-4");
+        assert_eq!(format_number(1234567.891, 0, 2), "1,234,567.89");
+        assert_eq!(format_number(0.125, 0, 2), "0.13");
+        // 負の数は 0 に丸められても符号を残す
+        assert_eq!(format_number(-0.0001, 0, 3), "-0");
+        assert_eq!(format_number(-0.0, 0, 3), "-0");
+        assert_eq!(format_number(9.999, 0, 2), "10");
+        assert_eq!(format_number(1.005, 0, 2), "1.01");
+        assert_eq!(format_number(1e21, 0, 3), "1,000,000,000,000,000,000,000");
+        assert_eq!(format_number(f64::NAN, 0, 3), "NaN");
+    }
+
+    #[test]
+    fn test_integer_truncates() {
+        let en = locale("en");
+        for (value, expected) in [(12.5, "12"), (4.9, "4"), (-2.5, "-2"), (-0.4, "-0")] {
+            let number = MessageValue::Number(NumberValue::new(value));
+            let result = MessageFunction::Integer.call(Some(&number), &HashMap::new());
+            let Ok(MessageValue::Number(result)) = result else {
+                panic!("{:?}", result);
+            };
+            assert_eq!(result.format(&en), expected, "{}", value);
+        }
+    }
+
+    #[test]
+    fn test_format_number_with_locale() {
+        let number = NumberValue::new(1234.5);
+        assert_eq!(number.format(&locale("de")), "1.234,5");
+        assert_eq!(number.format(&locale("fr")), "1\u{202F}234,5");
+        assert_eq!(number.format(&locale("en-u-nu-fullwide")), "１,２３４.５");
+    }
+
+    #[test]
+    fn test_parse_number_literal() {
+        assert_eq!(parse_number_literal("0.42e+1"), Some(4.2));
+        assert_eq!(parse_number_literal("-4.20"), Some(-4.2));
+        for invalid in [".1", "1.", "01", "+1", "1e", "foo", ""] {
+            assert_eq!(parse_number_literal(invalid), None, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_plural_and_ordinal_categories() {
+        let en = locale("en");
+        let mut number = NumberValue::new(1.0);
+        assert_eq!(number.category(&en), "one");
+        assert_eq!(number.category(&locale("ja")), "other");
+        number.options.minimum_fraction_digits = 1;
+        assert_eq!(number.category(&en), "other");
+        number.options.select = NumberSelect::Ordinal;
+        for (value, category) in [(1.0, "one"), (22.0, "two"), (13.0, "other"), (103.0, "few")] {
+            number.value = value;
+            assert_eq!(number.category(&en), category);
+        }
+    }
+
+    #[test]
+    fn test_plural_categories_by_language() {
+        let mut number = NumberValue::new(0.0);
+        assert_eq!(number.category(&locale("fr")), "one");
+        assert_eq!(number.category(&locale("en")), "other");
+        for (value, category) in [
+            (1.0, "one"),
+            (3.0, "few"),
+            (5.0, "many"),
+            (11.0, "many"),
+            (21.0, "one"),
+        ] {
+            number.value = value;
+            assert_eq!(number.category(&locale("ru")), category, "{}", value);
+        }
+        for (value, category) in [(1.0, "one"), (2.0, "two"), (0.5, "one"), (3.0, "other")] {
+            number.value = value;
+            assert_eq!(number.category(&locale("he")), category, "{}", value);
+        }
+        for (value, category) in [
+            (1.0, "one"),
+            (4.0, "few"),
+            (5.0, "many"),
+            (12.0, "many"),
+            (22.0, "few"),
+            (1.5, "other"),
+        ] {
+            number.value = value;
+            assert_eq!(number.category(&locale("pl")), category, "{}", value);
+        }
+        for (value, category) in [(1.0, "one"), (3.0, "few"), (5.0, "other"), (1.5, "many")] {
+            number.value = value;
+            assert_eq!(number.category(&locale("cs")), category, "{}", value);
+        }
+        for (value, category) in [(1.0, "one"), (2.0, "other"), (1e6, "many"), (2e6, "many")] {
+            number.value = value;
+            assert_eq!(number.category(&locale("es")), category, "{}", value);
+        }
+        number.value = 0.5;
+        assert_eq!(number.category(&locale("da")), "one");
+        assert_eq!(number.category(&locale("de")), "other");
+    }
+}
@@ -0,0 +1,292 @@
+// 最小限のJSONパーサー
+// Reference: https://www.rfc-editor.org/rfc/rfc8259
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    /// キーの出現順を保持する
+    Object(Vec<(String, JsonValue)>),
+}
+
+// テストランナーで使うアクセサ
+#[cfg(test)]
+impl JsonValue {
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<JsonValue>> {
+        match self {
+            JsonValue::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+pub struct JsonParser {
+    input: Vec<char>,
+    pos: usize,
+    /// オブジェクトのキーの位置（出現順）
+    key_positions: Vec<usize>,
+}
+
+impl JsonParser {
+    pub fn new(src: &str) -> Self {
+        Self {
+            input: src.chars().collect(),
+            pos: 0,
+            key_positions: Vec::new(),
+        }
+    }
+
+    pub fn parse(&mut self) -> Result<JsonValue, JsonError> {
+        self.pos = 0;
+        self.key_positions.clear();
+        let value = self.parse_value()?;
+        self.skip_whitespaces();
+        if self.pos < self.input.len() {
+            return Err(self.error("unexpected content after JSON value"));
+        }
+        Ok(value)
+    }
+
+    /// 現在位置の行と列（1始まり）を返す
+    pub fn line_column(&self, pos: usize) -> (usize, usize) {
+        let mut line = 1;
+        let mut column = 1;
+        for c in self.input.iter().take(pos) {
+            if *c == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+        }
+        (line, column)
+    }
+
+    /// オブジェクトのキーが現れた行（1始まり）を、ネストしたものも含めて出現順に返す
+    pub fn key_lines(&self) -> Vec<usize> {
+        self.key_positions
+            .iter()
+            .map(|pos| self.line_column(*pos).0)
+            .collect()
+    }
+
+    fn error(&self, message: &str) -> JsonError {
+        let (line, column) = self.line_column(self.pos);
+        JsonError {
+            line,
+            column,
+            message: message.to_string(),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn skip_whitespaces(&mut self) {
+        while let Some(' ' | '\t' | '\n' | '\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), JsonError> {
+        if self.peek() != Some(expected) {
+            return Err(self.error(&format!("expected '{}'", expected)));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn expect_keyword(&mut self, keyword: &str, value: JsonValue) -> Result<JsonValue, JsonError> {
+        for c in keyword.chars() {
+            self.expect(c)?;
+        }
+        Ok(value)
+    }
+
+    fn parse_value(&mut self) -> Result<JsonValue, JsonError> {
+        self.skip_whitespaces();
+        match self.peek() {
+            Some('{') => self.parse_object(),
+            Some('[') => self.parse_array(),
+            Some('"') => Ok(JsonValue::String(self.parse_string()?)),
+            Some('t') => self.expect_keyword("true", JsonValue::Bool(true)),
+            Some('f') => self.expect_keyword("false", JsonValue::Bool(false)),
+            Some('n') => self.expect_keyword("null", JsonValue::Null),
+            Some('-' | '0'..='9') => self.parse_number(),
+            _ => Err(self.error("expected a JSON value")),
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<JsonValue, JsonError> {
+        self.pos += 1; // consume '{'
+        let mut entries = Vec::new();
+        self.skip_whitespaces();
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(JsonValue::Object(entries));
+        }
+        loop {
+            self.skip_whitespaces();
+            if self.peek() != Some('"') {
+                return Err(self.error("expected a string key"));
+            }
+            self.key_positions.push(self.pos);
+            let key = self.parse_string()?;
+            self.skip_whitespaces();
+            self.expect(':')?;
+            let value = self.parse_value()?;
+            entries.push((key, value));
+            self.skip_whitespaces();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some('}') => {
+                    self.pos += 1;
+                    return Ok(JsonValue::Object(entries));
+                }
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<JsonValue, JsonError> {
+        self.pos += 1; // consume '['
+        let mut items = Vec::new();
+        self.skip_whitespaces();
+        if self.peek() == Some(']') {
+            self.pos += 1;
+            return Ok(JsonValue::Array(items));
+        }
+        loop {
+            items.push(self.parse_value()?);
+            self.skip_whitespaces();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(']') => {
+                    self.pos += 1;
+                    return Ok(JsonValue::Array(items));
+                }
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, JsonError> {
+        let digits: String = self.input.iter().skip(self.pos).take(4).collect();
+        if digits.len() != 4 {
+            return Err(self.error("incomplete unicode escape"));
+        }
+        let code =
+            u32::from_str_radix(&digits, 16).map_err(|_| self.error("bad unicode escape"))?;
+        self.pos += 4;
+        Ok(code)
+    }
+
+    fn parse_string(&mut self) -> Result<String, JsonError> {
+        self.pos += 1; // consume the opening quote
+        let mut value = String::new();
+        loop {
+            let c = match self.peek() {
+                Some(c) => c,
+                None => return Err(self.error("unterminated string")),
+            };
+            self.pos += 1;
+            match c {
+                '"' => return Ok(value),
+                '\\' => {
+                    let escaped = self
+                        .peek()
+                        .ok_or_else(|| self.error("unterminated string"))?;
+                    self.pos += 1;
+                    match escaped {
+                        '"' => value.push('"'),
+                        '\\' => value.push('\\'),
+                        '/' => value.push('/'),
+                        'b' => value.push('\u{8}'),
+                        'f' => value.push('\u{C}'),
+                        'n' => value.push('\n'),
+                        'r' => value.push('\r'),
+                        't' => value.push('\t'),
+                        'u' => {
+                            let mut code = self.parse_hex4()?;
+                            // サロゲートペアを結合する
+                            if (0xD800..0xDC00).contains(&code)
+                                && self.input.get(self.pos) == Some(&'\\')
+                                && self.input.get(self.pos + 1) == Some(&'u')
+                            {
+                                self.pos += 2;
+                                let low = self.parse_hex4()?;
+                                if (0xDC00..0xE000).contains(&low) {
+                                    code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                                } else {
+                                    // 対になっていない上位サロゲートは U+FFFD にして、2つ目はそのまま読む
+                                    value.push('\u{FFFD}');
+                                    code = low;
+                                }
+                            }
+                            value.push(char::from_u32(code).unwrap_or('\u{FFFD}'));
+                        }
+                        _ => return Err(self.error("bad escape")),
+                    }
+                }
+                _ => value.push(c),
+            }
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<JsonValue, JsonError> {
+        let start = self.pos;
+        while let Some('-' | '+' | '.' | 'e' | 'E' | '0'..='9') = self.peek() {
+            self.pos += 1;
+        }
+        let number: String = self.input[start..self.pos].iter().collect();
+        number
+            .parse()
+            .map(JsonValue::Number)
+            .map_err(|_| self.error("bad number"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_string(src: &str) -> String {
+        match JsonParser::new(src).parse() {
+            Ok(JsonValue::String(value)) => value,
+            other => panic!("expected a string, got {:?}", other.map_err(|e| e.message)),
+        }
+    }
+
+    #[test]
+    fn test_surrogate_pairs() {
+        assert_eq!(parse_string(r#""\uD83D\uDE00""#), "😀");
+        assert_eq!(parse_string(r#""\uD800\u0041""#), "\u{FFFD}A");
+        assert_eq!(parse_string(r#""\uD800\uD800""#), "\u{FFFD}\u{FFFD}");
+        assert_eq!(parse_string(r#""\uDC00x""#), "\u{FFFD}x");
+    }
+}
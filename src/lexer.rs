@@ -1,36 +1,271 @@
-static RESERVED_WORDS: [&str; 4] = ["const", "function", "return", "new"];
+use crate::numeric::BigInt;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+static RESERVED_WORDS: [&str; 25] = [
+    "const", "let", "var", "function", "return", "new", "throw", "try", "catch", "finally",
+    "typeof", "if", "else", "while", "do", "for", "in", "switch", "case", "default", "break",
+    "continue", "true", "false", "null",
+];
+
+/// 演算子。最長一致で読むので長いものから並べる
+/// Reference: https://262.ecma-international.org/#sec-punctuators
+static OPERATORS: [&str; 29] = [
+    "**=", "&&=", "||=", "??=", "===", "!==", "+=", "-=", "*=", "/=", "%=", "**", "++", "--", "==",
+    "!=", "<=", ">=", "&&", "||", "??", "+", "-", "*", "/", "%", "<", ">", "!",
+];
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     Punctuator(char),
-    Number(u64),
+    /// アロー関数の `=>`
+    Arrow,
+    /// 残余引数の `...`
+    Ellipsis,
+    /// 単項演算子と二項演算子
+    Operator(&'static str),
+    Number(f64),
+    /// 末尾に n の付いた整数リテラル
+    BigInt(BigInt),
     Identifier(String),
     Keyword(String),
     StringLiteral(String),
+    /// テンプレートリテラル。quasis は expressions より1つ多い
+    /// expressions は ${} の中のソースで、パーサーが式として解析する
+    Template {
+        quasis: Vec<String>,
+        expressions: Vec<(String, Position)>,
+    },
+    /// 字句解析できなかった入力。値は構文エラーのメッセージ
+    Invalid(String),
+}
+
+/// ソース中の位置。行と列はどちらも1始まりで、列は文字数で数える
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    pub const START: Position = Position { line: 1, column: 1 };
+}
+
+const INVALID_TOKEN: &str = "Invalid or unexpected token";
+
+/// テンプレートの ${} の中に書けるテンプレートの入れ子の上限。読み飛ばしは再帰なので、超えたらエラーにする
+const MAX_TEMPLATE_DEPTH: usize = 1000;
+
+/// ECMAScript の WhiteSpace。Rust の char::is_whitespace と違い U+FEFF を含み U+0085 を含まない
+/// Reference: https://262.ecma-international.org/#sec-white-space
+pub fn is_whitespace(c: char) -> bool {
+    c == '\u{FEFF}' || (c.is_whitespace() && c != '\u{85}' && !is_line_terminator(c))
+}
+
+/// Reference: https://262.ecma-international.org/#sec-line-terminators
+pub fn is_line_terminator(c: char) -> bool {
+    matches!(c, '\n' | '\r' | '\u{2028}' | '\u{2029}')
 }
 
 pub struct Lexer {
     pos: usize,
     input: Vec<char>,
+    /// 各行の先頭の文字の添字。\r\n は1つの改行として数える
+    line_starts: Vec<usize>,
+    /// input の先頭の位置
+    origin: Position,
+    /// 読み飛ばしている ${} の中のテンプレートの入れ子の深さ
+    template_depth: usize,
 }
 
 impl Lexer {
-    pub fn new(src: &str) -> Self {
+    /// src が origin の位置から始まるものとして位置を数える。テンプレートの ${} の中身に使う
+    pub fn with_origin(src: &str, origin: Position) -> Self {
+        let input: Vec<char> = src.chars().collect();
+        let mut line_starts = vec![0];
+        for (i, &c) in input.iter().enumerate() {
+            if is_line_terminator(c) && !(c == '\r' && input.get(i + 1) == Some(&'\n')) {
+                line_starts.push(i + 1);
+            }
+        }
         Self {
             pos: 0,
-            input: src.chars().collect(),
+            input,
+            line_starts,
+            origin,
+            template_depth: 0,
         }
     }
 
-    fn consume_number(&mut self) -> u64 {
+    fn position_at(&self, pos: usize) -> Position {
+        let line = self.line_starts.partition_point(|&start| start <= pos) - 1;
+        let column = pos - self.line_starts[line] + 1;
+        if line == 0 {
+            Position {
+                line: self.origin.line,
+                column: self.origin.column + column - 1,
+            }
+        } else {
+            Position {
+                line: self.origin.line + line,
+                column,
+            }
+        }
+    }
+
+    /// 入力の終わりの位置
+    pub fn end_position(&self) -> Position {
+        self.position_at(self.input.len())
+    }
+
+    fn input_starts_with(&self, s: &str) -> bool {
+        s.chars()
+            .enumerate()
+            .all(|(i, c)| self.input.get(self.pos + i) == Some(&c))
+    }
+
+    /// = や ? で始まる演算子 (==, ??) は区切り記号より優先する
+    fn starts_with_operator(&self) -> bool {
+        OPERATORS.iter().any(|op| self.input_starts_with(op))
+    }
+
+    /// 空白、改行、コメントを読み飛ばし、改行があったかを返す。閉じていないブロックコメントはエラー
+    /// Reference: https://262.ecma-international.org/#sec-comments
+    fn skip_whitespace_and_comments(&mut self) -> Result<bool, String> {
         let start = self.pos;
+        while let Some(&c) = self.input.get(self.pos) {
+            match (c, self.input.get(self.pos + 1)) {
+                (c, _) if is_whitespace(c) || is_line_terminator(c) => self.pos += 1,
+                ('/', Some('/')) => {
+                    while self
+                        .input
+                        .get(self.pos)
+                        .is_some_and(|&c| !is_line_terminator(c))
+                    {
+                        self.pos += 1;
+                    }
+                }
+                ('/', Some('*')) => {
+                    let start = self.pos + 2;
+                    let end = (start..self.input.len().saturating_sub(1))
+                        .find(|&i| self.input[i] == '*' && self.input[i + 1] == '/')
+                        .ok_or(INVALID_TOKEN)?;
+                    self.pos = end + 2;
+                }
+                _ => break,
+            }
+        }
+        // 改行を含むブロックコメントも改行とみなす
+        Ok(self.input[start..self.pos]
+            .iter()
+            .any(|&c| is_line_terminator(c)))
+    }
 
-        while self.pos < self.input.len() && self.input[self.pos].is_ascii_digit() {
-            self.pos += 1;
+    /// 数値リテラル。10進、0x / 0o / 0b の整数、区切り文字 _ と BigInt の n に対応する
+    /// Reference: https://262.ecma-international.org/#sec-literals-numeric-literals
+    fn consume_number(&mut self) -> Result<Token, String> {
+        let radix = match (self.input[self.pos], self.input.get(self.pos + 1)) {
+            ('0', Some('x' | 'X')) => 16,
+            ('0', Some('o' | 'O')) => 8,
+            ('0', Some('b' | 'B')) => 2,
+            _ => 10,
+        };
+
+        let token = if radix != 10 {
+            self.pos += 2; // Skip the prefix
+            let digits = self.consume_digits(radix)?;
+            if digits.is_empty() {
+                return Err(INVALID_TOKEN.to_string());
+            }
+            if self.input.get(self.pos) == Some(&'n') {
+                self.pos += 1;
+                Token::BigInt(BigInt::from_digits(&digits, radix))
+            } else {
+                // u128 に収まる値は as で正しく丸められる
+                let value = u128::from_str_radix(&digits, radix)
+                    .map(|value| value as f64)
+                    .unwrap_or_else(|_| BigInt::from_digits(&digits, radix).to_f64());
+                Token::Number(value)
+            }
+        } else {
+            // strict mode では 0 で始まる 10 進数（旧来の 8 進数を含む）は書けない
+            match (self.input[self.pos], self.input.get(self.pos + 1)) {
+                ('0', Some('0'..='7')) => {
+                    return Err("Octal literals are not allowed in strict mode.".to_string());
+                }
+                ('0', Some('8' | '9')) => {
+                    return Err(
+                        "Decimals with leading zeros are not allowed in strict mode.".to_string(),
+                    );
+                }
+                ('0', Some('_')) => {
+                    return Err("Numeric separator can not be used after leading 0.".to_string());
+                }
+                _ => {}
+            }
+            let mut text = self.consume_digits(10)?;
+            if self.input.get(self.pos) == Some(&'n') && !text.is_empty() {
+                self.pos += 1;
+                Token::BigInt(BigInt::from_digits(&text, 10))
+            } else {
+                if self.input.get(self.pos) == Some(&'.') {
+                    self.pos += 1;
+                    text.push('.');
+                    text.push_str(&self.consume_digits(10)?);
+                }
+                if let Some('e' | 'E') = self.input.get(self.pos) {
+                    self.pos += 1;
+                    text.push('e');
+                    if let Some(&sign @ ('+' | '-')) = self.input.get(self.pos) {
+                        self.pos += 1;
+                        text.push(sign);
+                    }
+                    let exponent = self.consume_digits(10)?;
+                    if exponent.is_empty() {
+                        return Err(INVALID_TOKEN.to_string());
+                    }
+                    text.push_str(&exponent);
+                }
+                Token::Number(text.parse().unwrap())
+            }
+        };
+
+        // 数値リテラルの直後に識別子や数字は続けられない
+        if self
+            .input
+            .get(self.pos)
+            .is_some_and(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '$')
+        {
+            return Err(INVALID_TOKEN.to_string());
         }
+        Ok(token)
+    }
 
-        let number_str: String = self.input[start..self.pos].iter().collect();
-        number_str.parse().unwrap()
+    /// radix 進の数字の並びを読む。区切り文字の _ は数字の間にだけ書けて、結果には含めない
+    fn consume_digits(&mut self, radix: u32) -> Result<String, String> {
+        let mut digits = String::new();
+        while let Some(&c) = self.input.get(self.pos) {
+            if c.is_digit(radix) {
+                digits.push(c);
+            } else if c == '_' {
+                match self.input.get(self.pos + 1) {
+                    Some('_') => {
+                        return Err(
+                            "Only one underscore is allowed as numeric separator".to_string()
+                        );
+                    }
+                    Some(next) if next.is_digit(radix) && !digits.is_empty() => {}
+                    _ => {
+                        return Err(
+                            "Numeric separators are not allowed at the end of numeric literals"
+                                .to_string(),
+                        );
+                    }
+                }
+            } else {
+                break;
+            }
+            self.pos += 1;
+        }
+        Ok(digits)
     }
 
     fn consume_identifier(&mut self) -> String {
@@ -44,54 +279,249 @@ impl Lexer {
             self.pos += 1;
         }
 
-        let identifier: String = self.input[start..self.pos].iter().collect();
-        // Check if it's a reserved word
-        if RESERVED_WORDS.contains(&identifier.as_str()) {
-            identifier // Return as is, will be treated as Keyword in Token
-        } else {
-            identifier // Return as is, will be treated as Identifier in Token 
-        }
+        // Reserved words are returned as is and turned into Keyword tokens by the caller
+        self.input[start..self.pos].iter().collect()
     }
 
-    fn consume_string(&mut self) -> String {
+    /// ' か " で囲まれた文字列リテラル
+    /// Reference: https://262.ecma-international.org/#sec-literals-string-literals
+    fn consume_string(&mut self) -> Result<String, String> {
+        let quote = self.input[self.pos];
         self.pos += 1; // Skip the opening quote
-        let start = self.pos;
+        let mut value = String::new();
 
-        while self.pos < self.input.len() && self.input[self.pos] != '"' {
-            self.pos += 1;
+        loop {
+            match self.input.get(self.pos) {
+                Some(&c) if c == quote => {
+                    self.pos += 1; // Skip the closing quote
+                    return Ok(value);
+                }
+                Some('\\') => {
+                    self.pos += 1;
+                    self.consume_escape(&mut value)?;
+                }
+                // 文字列リテラルの中に改行は書けない（U+2028/U+2029 は書ける）
+                Some('\n' | '\r') | None => return Err(INVALID_TOKEN.to_string()),
+                Some(&c) => {
+                    value.push(c);
+                    self.pos += 1;
+                }
+            }
         }
+    }
 
-        let string_literal: String = self.input[start..self.pos].iter().collect();
-        self.pos += 1; // Skip the closing quote
-        string_literal
+    /// \ の後のエスケープシーケンスを読んで value に追加する
+    /// Reference: https://262.ecma-international.org/#prod-EscapeSequence
+    fn consume_escape(&mut self, value: &mut String) -> Result<(), String> {
+        let Some(&c) = self.input.get(self.pos) else {
+            return Err(INVALID_TOKEN.to_string());
+        };
+        self.pos += 1;
+        match c {
+            'n' => value.push('\n'),
+            't' => value.push('\t'),
+            'r' => value.push('\r'),
+            'b' => value.push('\u{8}'),
+            'f' => value.push('\u{c}'),
+            'v' => value.push('\u{b}'),
+            '0' if !self.input.get(self.pos).is_some_and(|c| c.is_ascii_digit()) => {
+                value.push('\0')
+            }
+            '0'..='9' => return Err("Octal escape sequences are not allowed".to_string()),
+            'x' => {
+                let code = self
+                    .consume_hex_digits(2)
+                    .ok_or("Invalid hexadecimal escape sequence")?;
+                value.push(char::from_u32(code).unwrap());
+            }
+            'u' => value.push(self.consume_unicode_escape()?),
+            // 行継続。改行そのものは値に含めない
+            '\r' => {
+                if self.input.get(self.pos) == Some(&'\n') {
+                    self.pos += 1;
+                }
+            }
+            '\n' | '\u{2028}' | '\u{2029}' => {}
+            _ => value.push(c),
+        }
+        Ok(())
     }
-}
 
-impl Iterator for Lexer {
-    type Item = Token;
+    /// \u の後の XXXX か {X...}。サロゲートペアは1文字にまとめ、対にならないサロゲートは U+FFFD にする
+    fn consume_unicode_escape(&mut self) -> Result<char, String> {
+        let invalid = || "Invalid Unicode escape sequence".to_string();
+        let code = if self.input.get(self.pos) == Some(&'{') {
+            self.pos += 1;
+            let start = self.pos;
+            while self
+                .input
+                .get(self.pos)
+                .is_some_and(|c| c.is_ascii_hexdigit())
+            {
+                self.pos += 1;
+            }
+            let digits: String = self.input[start..self.pos].iter().collect();
+            if self.input.get(self.pos) != Some(&'}') {
+                return Err(invalid());
+            }
+            self.pos += 1;
+            u32::from_str_radix(&digits, 16)
+                .ok()
+                .filter(|code| *code <= 0x10FFFF)
+                .ok_or_else(invalid)?
+        } else {
+            self.consume_hex_digits(4).ok_or_else(invalid)?
+        };
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.pos >= self.input.len() {
+        if (0xD800..0xDC00).contains(&code)
+            && self.input.get(self.pos) == Some(&'\\')
+            && self.input.get(self.pos + 1) == Some(&'u')
+        {
+            let save = self.pos;
+            self.pos += 2;
+            match self.consume_hex_digits(4) {
+                Some(low) if (0xDC00..0xE000).contains(&low) => {
+                    let code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                    return Ok(char::from_u32(code).unwrap());
+                }
+                _ => self.pos = save,
+            }
+        }
+        Ok(char::from_u32(code).unwrap_or('\u{FFFD}'))
+    }
+
+    fn consume_hex_digits(&mut self, count: usize) -> Option<u32> {
+        let digits: String = self.input.get(self.pos..self.pos + count)?.iter().collect();
+        if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
             return None;
         }
+        self.pos += count;
+        u32::from_str_radix(&digits, 16).ok()
+    }
+
+    /// ` で囲まれたテンプレートリテラル。${} の中身はソースのまま返す
+    /// Reference: https://262.ecma-international.org/#sec-template-literal-lexical-components
+    fn consume_template(&mut self) -> Result<Token, String> {
+        self.pos += 1; // Skip the opening backquote
+        let mut quasis = Vec::new();
+        let mut expressions = Vec::new();
+        let mut quasi = String::new();
+
+        loop {
+            match self.input.get(self.pos) {
+                Some('`') => {
+                    self.pos += 1;
+                    quasis.push(quasi);
+                    return Ok(Token::Template {
+                        quasis,
+                        expressions,
+                    });
+                }
+                Some('\\') => {
+                    self.pos += 1;
+                    self.consume_escape(&mut quasi)?;
+                }
+                Some('$') if self.input.get(self.pos + 1) == Some(&'{') => {
+                    self.pos += 2;
+                    let start = self.pos;
+                    self.skip_template_substitution()?;
+                    let src = self.input[start..self.pos].iter().collect();
+                    expressions.push((src, self.position_at(start)));
+                    self.pos += 1; // Skip the closing '}'
+                    quasis.push(std::mem::take(&mut quasi));
+                }
+                // 改行は LF に正規化する
+                Some('\r') => {
+                    self.pos += 1;
+                    if self.input.get(self.pos) == Some(&'\n') {
+                        self.pos += 1;
+                    }
+                    quasi.push('\n');
+                }
+                Some(&c) => {
+                    quasi.push(c);
+                    self.pos += 1;
+                }
+                None => return Err("Unterminated template literal".to_string()),
+            }
+        }
+    }
 
-        while self.input[self.pos] == ' ' || self.input[self.pos] == '\n' {
+    /// ${ の後から対応する } の手前まで進める。中の文字列やテンプレートの } は数えない
+    fn skip_template_substitution(&mut self) -> Result<(), String> {
+        let mut depth = 0;
+        loop {
+            match self.input.get(self.pos) {
+                Some('}') if depth == 0 => return Ok(()),
+                Some('}') => depth -= 1,
+                Some('{') => depth += 1,
+                Some('"' | '\'') => {
+                    self.consume_string()?;
+                    continue;
+                }
+                Some('`') => {
+                    if self.template_depth >= MAX_TEMPLATE_DEPTH {
+                        return Err("Too deeply nested".to_string());
+                    }
+                    self.template_depth += 1;
+                    let template = self.consume_template();
+                    self.template_depth -= 1;
+                    template?;
+                    continue;
+                }
+                Some(_) => {}
+                None => return Err("Unterminated template literal".to_string()),
+            }
             self.pos += 1;
+        }
+    }
+}
 
-            if self.pos >= self.input.len() {
-                return None;
+/// トークン、その位置、直前に改行があったか（自動セミコロン挿入の判定に使う）
+impl Iterator for Lexer {
+    type Item = (Token, Position, bool);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let newline_before = match self.skip_whitespace_and_comments() {
+            Ok(newline_before) => newline_before,
+            Err(message) => {
+                let position = self.position_at(self.pos);
+                self.pos = self.input.len();
+                return Some((Token::Invalid(message), position, false));
             }
+        };
+        if self.pos >= self.input.len() {
+            return None;
         }
 
+        let position = self.position_at(self.pos);
         let c = self.input[self.pos];
 
         let token = match c {
-            '+' | '-' | ';' | '=' | '(' | ')' | '{' | '}' | ',' | '.' | ':' => {
+            '=' if self.input.get(self.pos + 1) == Some(&'>') => {
+                self.pos += 2;
+                Token::Arrow
+            }
+            '.' if self.input_starts_with("...") => {
+                self.pos += 3;
+                Token::Ellipsis
+            }
+            '0'..='9' => self.consume_number().unwrap_or_else(Token::Invalid),
+            // .5 のように . で始まる数値
+            '.' if self
+                .input
+                .get(self.pos + 1)
+                .is_some_and(char::is_ascii_digit) =>
+            {
+                self.consume_number().unwrap_or_else(Token::Invalid)
+            }
+            ';' | '=' | '(' | ')' | '{' | '}' | '[' | ']' | ',' | '.' | ':' | '?'
+                if !self.starts_with_operator() =>
+            {
                 let t = Token::Punctuator(c);
                 self.pos += 1;
                 t
             }
-            '0'..='9' => Token::Number(self.consume_number()),
             'a'..='z' | 'A'..='Z' | '_' | '$' => {
                 let identifier = self.consume_identifier();
                 if RESERVED_WORDS.contains(&identifier.as_str()) {
@@ -100,10 +530,23 @@ impl Iterator for Lexer {
                     Token::Identifier(identifier)
                 }
             }
-            '"' => Token::StringLiteral(self.consume_string()),
-            _ => unimplemented!("char {:?} is not supported yet", c),
+            '"' | '\'' => match self.consume_string() {
+                Ok(value) => Token::StringLiteral(value),
+                Err(message) => Token::Invalid(message),
+            },
+            '`' => self.consume_template().unwrap_or_else(Token::Invalid),
+            _ => match OPERATORS.iter().find(|op| self.input_starts_with(op)) {
+                Some(op) => {
+                    self.pos += op.len();
+                    Token::Operator(op)
+                }
+                None => {
+                    self.pos += 1;
+                    Token::Invalid(INVALID_TOKEN.to_string())
+                }
+            },
         };
 
-        Some(token)
+        Some((token, position, newline_before))
     }
 }
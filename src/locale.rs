@@ -0,0 +1,503 @@
+// BCP 47 言語タグ（Unicode locale identifier）のパースと正規化
+// Reference: https://www.unicode.org/reports/tr35/#Unicode_locale_identifier
+// Reference: https://tc39.es/ecma402/#sec-canonicalizeunicodelocaleid
+use core::fmt::Display;
+use core::fmt::Formatter;
+
+/// 非推奨の言語サブタグと置き換え先
+const LANGUAGE_ALIASES: [(&str, &str); 14] = [
+    ("iw", "he"),
+    ("in", "id"),
+    ("ji", "yi"),
+    ("jw", "jv"),
+    ("mo", "ro"),
+    ("eng", "en"),
+    ("jpn", "ja"),
+    ("deu", "de"),
+    ("ger", "de"),
+    ("fra", "fr"),
+    ("fre", "fr"),
+    ("spa", "es"),
+    ("zho", "zh"),
+    ("chi", "zh"),
+];
+
+/// 非推奨の地域サブタグと置き換え先
+const REGION_ALIASES: [(&str, &str); 5] = [
+    ("BU", "MM"),
+    ("DD", "DE"),
+    ("TP", "TL"),
+    ("UK", "GB"),
+    ("ZR", "CD"),
+];
+
+/// 構文上は不正だが、置き換え先が定義されている grandfathered タグ
+const GRANDFATHERED: [(&str, &str); 5] = [
+    ("art-lojban", "jbo"),
+    ("i-klingon", "tlh"),
+    ("i-navajo", "nv"),
+    ("zh-guoyu", "zh"),
+    ("zh-hakka", "hak"),
+];
+
+/// このエンジンが数値の書式や複数形規則のデータを持っているロケール
+const AVAILABLE_LOCALES: [&str; 29] = [
+    "cs", "da", "de", "en", "en-GB", "en-US", "es", "fi", "fr", "he", "id", "it", "ja", "ko", "ms",
+    "nb", "nl", "pl", "pt", "pt-BR", "ru", "sv", "th", "tr", "uk", "vi", "zh", "zh-Hans",
+    "zh-Hant",
+];
+
+/// 要求されたロケールがどれも利用できない場合に使うロケール
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// 交渉結果に引き継ぐ `-u-` 拡張のキー（数値の記数法）
+const RELEVANT_EXTENSION_KEYS: [&str; 1] = ["nu"];
+
+/// 右から左に書く言語（スクリプト省略時の既定スクリプトが RTL のもの）
+const RTL_LANGUAGES: [&str; 10] = ["ar", "ckb", "dv", "fa", "he", "ps", "sd", "ug", "ur", "yi"];
+
+/// 右から左に書くスクリプト
+const RTL_SCRIPTS: [&str; 6] = ["Adlm", "Arab", "Hebr", "Nkoo", "Syrc", "Thaa"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocaleMatcher {
+    Lookup,
+    BestFit,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocaleError(pub String);
+
+impl Display for LocaleError {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        write!(f, "Incorrect locale information provided: {}", self.0)
+    }
+}
+
+/// `-u-` 以外の拡張
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Extension {
+    singleton: char,
+    subtags: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Locale {
+    language: String,
+    script: Option<String>,
+    region: Option<String>,
+    /// 正規化後はアルファベット順
+    variants: Vec<String>,
+    /// `-u-` 拡張の属性
+    attributes: Vec<String>,
+    /// `-u-` 拡張のキーワード。正規化後はキー順
+    keywords: Vec<(String, String)>,
+    /// その他の拡張。正規化後はシングルトン順
+    extensions: Vec<Extension>,
+    private_use: Vec<String>,
+}
+
+fn is_alpha(s: &str, len: std::ops::RangeInclusive<usize>) -> bool {
+    len.contains(&s.len()) && s.bytes().all(|b| b.is_ascii_alphabetic())
+}
+
+fn is_alphanum(s: &str, len: std::ops::RangeInclusive<usize>) -> bool {
+    len.contains(&s.len()) && s.bytes().all(|b| b.is_ascii_alphanumeric())
+}
+
+fn title_case(s: &str) -> String {
+    let mut result = s[..1].to_ascii_uppercase();
+    result.push_str(&s[1..]);
+    result
+}
+
+impl Locale {
+    /// 言語タグをパースし、大文字小文字・非推奨サブタグ・サブタグの順序を正規化する
+    pub fn parse(tag: &str) -> Result<Self, LocaleError> {
+        let error = || LocaleError(tag.to_string());
+        let lower = tag.to_ascii_lowercase();
+        let lower = GRANDFATHERED
+            .iter()
+            .find(|(from, _)| *from == lower)
+            .map(|(_, to)| to.to_string())
+            .unwrap_or(lower);
+
+        let mut subtags = lower.split('-').peekable();
+        let language = subtags.next().unwrap_or_default().to_string();
+        if !is_alpha(&language, 2..=3) && !is_alpha(&language, 5..=8) {
+            return Err(error());
+        }
+        let mut locale = Self {
+            language,
+            script: None,
+            region: None,
+            variants: Vec::new(),
+            attributes: Vec::new(),
+            keywords: Vec::new(),
+            extensions: Vec::new(),
+            private_use: Vec::new(),
+        };
+
+        if let Some(script) = subtags.next_if(|s| is_alpha(s, 4..=4)) {
+            locale.script = Some(title_case(script));
+        }
+        if let Some(region) = subtags.next_if(|s| {
+            is_alpha(s, 2..=2) || (s.len() == 3 && s.bytes().all(|b| b.is_ascii_digit()))
+        }) {
+            locale.region = Some(region.to_ascii_uppercase());
+        }
+        while let Some(variant) = subtags.next_if(|s| {
+            is_alphanum(s, 5..=8)
+                || (s.len() == 4 && s.as_bytes()[0].is_ascii_digit() && is_alphanum(s, 4..=4))
+        }) {
+            if locale.variants.iter().any(|v| v == variant) {
+                return Err(error());
+            }
+            locale.variants.push(variant.to_string());
+        }
+
+        while let Some(singleton) = subtags.next() {
+            if singleton.len() != 1 || !is_alphanum(singleton, 1..=1) {
+                return Err(error());
+            }
+            let singleton = singleton.as_bytes()[0] as char;
+            if singleton == 'x' {
+                locale.private_use = subtags.by_ref().map(|s| s.to_string()).collect();
+                if locale.private_use.is_empty()
+                    || !locale.private_use.iter().all(|s| is_alphanum(s, 1..=8))
+                {
+                    return Err(error());
+                }
+                break;
+            }
+            let extension: Vec<&str> =
+                std::iter::from_fn(|| subtags.next_if(|s| s.len() > 1)).collect();
+            if extension.is_empty()
+                || !extension.iter().all(|s| is_alphanum(s, 2..=8))
+                || locale.extensions.iter().any(|e| e.singleton == singleton)
+                || (singleton == 'u'
+                    && (!locale.attributes.is_empty() || !locale.keywords.is_empty()))
+            {
+                return Err(error());
+            }
+            if singleton == 'u' {
+                locale
+                    .parse_unicode_extension(&extension)
+                    .ok_or_else(error)?;
+            } else {
+                locale.extensions.push(Extension {
+                    singleton,
+                    subtags: extension.iter().map(|s| s.to_string()).collect(),
+                });
+            }
+        }
+
+        locale.canonicalize();
+        Ok(locale)
+    }
+
+    fn parse_unicode_extension(&mut self, subtags: &[&str]) -> Option<()> {
+        let mut subtags = subtags.iter().peekable();
+        while let Some(attribute) = subtags.next_if(|s| s.len() >= 3) {
+            self.attributes.push(attribute.to_string());
+        }
+        while let Some(key) = subtags.next() {
+            let bytes = key.as_bytes();
+            if bytes.len() != 2 || !bytes[1].is_ascii_alphabetic() {
+                return None;
+            }
+            let mut value = Vec::new();
+            while let Some(type_subtag) = subtags.next_if(|s| s.len() >= 3) {
+                value.push(*type_subtag);
+            }
+            // 同じキーが複数ある場合は最初のものを使う
+            if !self.keywords.iter().any(|(k, _)| k == key) {
+                self.keywords.push((key.to_string(), value.join("-")));
+            }
+        }
+        Some(())
+    }
+
+    fn canonicalize(&mut self) {
+        if let Some((_, to)) = LANGUAGE_ALIASES
+            .iter()
+            .find(|(from, _)| *from == self.language)
+        {
+            self.language = to.to_string();
+        }
+        if let Some(region) = &self.region
+            && let Some((_, to)) = REGION_ALIASES.iter().find(|(from, _)| from == region)
+        {
+            self.region = Some(to.to_string());
+        }
+        self.variants.sort();
+        self.keywords.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (_, value) in &mut self.keywords {
+            // "true" は省略する
+            if value == "true" {
+                value.clear();
+            }
+        }
+        self.extensions.sort_by_key(|e| e.singleton);
+    }
+
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    pub fn script(&self) -> Option<&str> {
+        self.script.as_deref()
+    }
+
+    pub fn region(&self) -> Option<&str> {
+        self.region.as_deref()
+    }
+
+    /// 拡張を除いた language-script-region-variants
+    pub fn base_name(&self) -> String {
+        let mut subtags = vec![self.language.as_str()];
+        subtags.extend(self.script.as_deref());
+        subtags.extend(self.region.as_deref());
+        subtags.extend(self.variants.iter().map(String::as_str));
+        subtags.join("-")
+    }
+
+    /// 右から左に書く文字体系か。スクリプトが明示されていなければ言語から推定する
+    pub fn is_rtl(&self) -> bool {
+        match self.script.as_deref() {
+            Some(script) => RTL_SCRIPTS.contains(&script),
+            None => RTL_LANGUAGES.contains(&self.language.as_str()),
+        }
+    }
+
+    /// `-u-` 拡張のキーワードの値。例: "nu" → "arab"
+    pub fn unicode_keyword(&self, key: &str) -> Option<&str> {
+        self.keywords
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn set_unicode_keyword(&mut self, key: &str, value: &str) {
+        self.keywords.retain(|(k, _)| k != key);
+        self.keywords.push((key.to_string(), value.to_string()));
+        self.keywords.sort_by(|(a, _), (b, _)| a.cmp(b));
+    }
+}
+
+impl Display for Locale {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        write!(f, "{}", self.base_name())?;
+        // シングルトン順に並べるので、-u- は t より後、その他の拡張の間に入る
+        let mut unicode_written = self.attributes.is_empty() && self.keywords.is_empty();
+        for extension in &self.extensions {
+            if !unicode_written && extension.singleton > 'u' {
+                self.write_unicode_extension(f)?;
+                unicode_written = true;
+            }
+            write!(
+                f,
+                "-{}-{}",
+                extension.singleton,
+                extension.subtags.join("-")
+            )?;
+        }
+        if !unicode_written {
+            self.write_unicode_extension(f)?;
+        }
+        if !self.private_use.is_empty() {
+            write!(f, "-x-{}", self.private_use.join("-"))?;
+        }
+        Ok(())
+    }
+}
+
+impl Locale {
+    fn write_unicode_extension(&self, f: &mut Formatter) -> core::fmt::Result {
+        write!(f, "-u")?;
+        for attribute in &self.attributes {
+            write!(f, "-{}", attribute)?;
+        }
+        for (key, value) in &self.keywords {
+            write!(f, "-{}", key)?;
+            if !value.is_empty() {
+                write!(f, "-{}", value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Intl.getCanonicalLocales: 正規化して重複を取り除く
+/// Reference: https://tc39.es/ecma402/#sec-canonicalizelocalelist
+pub fn canonicalize_locale_list(tags: &[String]) -> Result<Vec<String>, LocaleError> {
+    let mut result: Vec<String> = Vec::new();
+    for tag in tags {
+        let canonical = Locale::parse(tag)?.to_string();
+        if !result.contains(&canonical) {
+            result.push(canonical);
+        }
+    }
+    Ok(result)
+}
+
+/// 利用可能なロケールの中から、サブタグを末尾から削りながら一致するものを探す
+/// Reference: https://tc39.es/ecma402/#sec-bestavailablelocale
+fn best_available_locale(base_name: &str) -> Option<&'static str> {
+    let mut candidate = base_name;
+    loop {
+        if let Some(available) = AVAILABLE_LOCALES.iter().find(|l| **l == candidate) {
+            return Some(available);
+        }
+        let mut pos = candidate.rfind('-')?;
+        // 削った結果が1文字のサブタグで終わる場合はそれも削る
+        if pos >= 2 && candidate.as_bytes()[pos - 2] == b'-' {
+            pos -= 2;
+        }
+        candidate = &candidate[..pos];
+    }
+}
+
+/// best fit では、地域から推測できる用字を補ってから探す（例: zh-TW → zh-Hant-TW）
+fn best_fit_base_name(locale: &Locale) -> String {
+    let mut locale = locale.clone();
+    if locale.language == "no" {
+        locale.language = "nb".to_string();
+    }
+    if locale.language == "zh" && locale.script.is_none() {
+        let script = match locale.region.as_deref() {
+            Some("TW" | "HK" | "MO") => "Hant",
+            _ => "Hans",
+        };
+        locale.script = Some(script.to_string());
+    }
+    locale.base_name()
+}
+
+/// 要求されたロケールのリストを利用可能なロケールと照合する
+/// Reference: https://tc39.es/ecma402/#sec-resolvelocale
+pub fn resolve_locale(requested: &[String], matcher: LocaleMatcher) -> Result<Locale, LocaleError> {
+    for tag in canonicalize_locale_list(requested)? {
+        let locale = Locale::parse(&tag)?;
+        let base_name = match matcher {
+            LocaleMatcher::Lookup => locale.base_name(),
+            LocaleMatcher::BestFit => best_fit_base_name(&locale),
+        };
+        if let Some(available) = best_available_locale(&base_name) {
+            let mut resolved = Locale::parse(available)?;
+            for key in RELEVANT_EXTENSION_KEYS {
+                if let Some(value) = locale.unicode_keyword(key) {
+                    resolved.set_unicode_keyword(key, value);
+                }
+            }
+            return Ok(resolved);
+        }
+    }
+    Locale::parse(DEFAULT_LOCALE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canonical(tag: &str) -> String {
+        Locale::parse(tag).unwrap().to_string()
+    }
+
+    #[test]
+    fn test_parse_subtags() {
+        let locale = Locale::parse("zh-hant-tw-u-nu-hanidec-hc-h23").unwrap();
+        assert_eq!(locale.language(), "zh");
+        assert_eq!(locale.script(), Some("Hant"));
+        assert_eq!(locale.region(), Some("TW"));
+        assert_eq!(locale.base_name(), "zh-Hant-TW");
+        assert_eq!(locale.unicode_keyword("nu"), Some("hanidec"));
+        assert_eq!(locale.unicode_keyword("hc"), Some("h23"));
+        assert_eq!(locale.unicode_keyword("ca"), None);
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        assert_eq!(canonical("EN-us"), "en-US");
+        assert_eq!(canonical("de-1996-1901"), "de-1901-1996");
+        assert_eq!(
+            canonical("en-u-nu-latn-ca-gregory"),
+            "en-u-ca-gregory-nu-latn"
+        );
+        assert_eq!(canonical("en-u-kn-true"), "en-u-kn");
+        assert_eq!(canonical("en-x-private-t-ja"), "en-x-private-t-ja");
+        assert_eq!(
+            canonical("en-z-zz-a-aa-u-nu-arab"),
+            "en-a-aa-u-nu-arab-z-zz"
+        );
+    }
+
+    #[test]
+    fn test_deprecated_subtags() {
+        assert_eq!(canonical("iw-IL"), "he-IL");
+        assert_eq!(canonical("jpn"), "ja");
+        assert_eq!(canonical("de-DD"), "de-DE");
+        assert_eq!(canonical("art-lojban"), "jbo");
+    }
+
+    #[test]
+    fn test_invalid_tags() {
+        for tag in [
+            "",
+            "e",
+            "en-",
+            "en--US",
+            "abcd",
+            "en-US-x",
+            "de-1996-1996",
+            "en-u",
+            "en-a-aa-a-bb",
+            "en_US",
+            "日本語",
+        ] {
+            assert!(Locale::parse(tag).is_err(), "{}", tag);
+        }
+    }
+
+    #[test]
+    fn test_canonicalize_locale_list() {
+        let tags = ["EN-us", "en-US", "ja"].map(String::from);
+        assert_eq!(
+            canonicalize_locale_list(&tags).unwrap(),
+            vec!["en-US", "ja"]
+        );
+        assert!(canonicalize_locale_list(&["en".to_string(), "-".to_string()]).is_err());
+    }
+
+    fn resolve(requested: &[&str], matcher: LocaleMatcher) -> String {
+        let requested: Vec<String> = requested.iter().map(|s| s.to_string()).collect();
+        resolve_locale(&requested, matcher).unwrap().to_string()
+    }
+
+    #[test]
+    fn test_resolve_locale_lookup() {
+        assert_eq!(resolve(&["ja-JP", "en"], LocaleMatcher::Lookup), "ja");
+        assert_eq!(resolve(&["xx", "fr-CA"], LocaleMatcher::Lookup), "fr");
+        assert_eq!(resolve(&["en-GB-oxendict"], LocaleMatcher::Lookup), "en-GB");
+        assert_eq!(resolve(&["he-IL"], LocaleMatcher::Lookup), "he");
+        assert_eq!(resolve(&["xx-YY"], LocaleMatcher::Lookup), DEFAULT_LOCALE);
+        assert_eq!(resolve(&[], LocaleMatcher::Lookup), DEFAULT_LOCALE);
+        // 数値の記数法は引き継ぎ、それ以外の拡張は落とす
+        assert_eq!(
+            resolve(
+                &["ar", "de-AT-u-nu-latn-ca-buddhist"],
+                LocaleMatcher::Lookup
+            ),
+            "de-u-nu-latn"
+        );
+    }
+
+    #[test]
+    fn test_resolve_locale_best_fit() {
+        assert_eq!(resolve(&["zh-TW"], LocaleMatcher::Lookup), "zh");
+        assert_eq!(resolve(&["zh-TW"], LocaleMatcher::BestFit), "zh-Hant");
+        assert_eq!(resolve(&["zh-CN"], LocaleMatcher::BestFit), "zh-Hans");
+        assert_eq!(resolve(&["no-NO"], LocaleMatcher::BestFit), "nb");
+        assert!(resolve_locale(&["en_US".to_string()], LocaleMatcher::BestFit).is_err());
+    }
+}
@@ -1,18 +1,54 @@
+mod catalog;
+mod compiled;
+#[cfg(test)]
+mod conformance;
+mod console;
+mod functions;
+mod json;
 mod lexer;
+mod locale;
+mod numeric;
+mod object;
 mod parser;
-mod printer;
 mod runtime;
 
+use crate::catalog::Catalog;
+use crate::functions::FunctionRegistry;
 use crate::parser::Parser;
 use std::env;
 use std::fs;
+use std::path::Path;
+use std::thread;
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    // --catalog <path> でメッセージカタログを読み込む（複数指定可）
+    let mut catalog = Catalog::new("en");
+    let mut args = Vec::new();
+    let mut iter = env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        if arg != "--catalog" {
+            args.push(arg);
+            continue;
+        }
+        let Some(path) = iter.next() else {
+            eprintln!("Missing path for --catalog");
+            std::process::exit(1);
+        };
+        match catalog.load_file(Path::new(&path), &FunctionRegistry::default()) {
+            Ok(errors) => {
+                for error in errors {
+                    eprintln!("{}", error);
+                }
+            }
+            Err(err) => {
+                eprintln!("Error reading catalog '{}': {}", path, err);
+                std::process::exit(1);
+            }
+        }
+    }
 
-    let input = if args.len() > 1 {
+    let input = if let Some(filename) = args.first() {
         // ファイルパスが指定された場合
-        let filename = &args[1];
         match fs::read_to_string(filename) {
             Ok(contents) => contents,
             Err(err) => {
@@ -27,9 +63,38 @@ fn main() {
 
     println!("{}", input);
 
-    let mut parser = Parser::new(&input);
-    let program = parser.parse();
-    let mut runtime = runtime::Runtime::new();
-    runtime.execute(program.clone());
+    let filename = args.first().map_or("<input>", String::as_str).to_string();
+    // 構文解析も評価も再帰で動くので、どちらも大きなスタックを持つスレッドで実行する
+    let execution = thread::Builder::new()
+        .stack_size(runtime::STACK_SIZE)
+        .spawn(move || {
+            let mut parser = Parser::new(&input);
+            let program = match parser.parse() {
+                Ok(program) => program,
+                Err(error) => {
+                    // V8 と同じように、エラーの位置と該当する行を示してから表示する
+                    let position = error.position;
+                    eprintln!("{}:{}:{}", filename, position.line, position.column);
+                    if let Some(line) = input.lines().nth(position.line - 1) {
+                        eprintln!("{}", line);
+                        eprintln!("{}^", " ".repeat(position.column - 1));
+                    }
+                    eprintln!();
+                    eprintln!("{}", error);
+                    std::process::exit(1);
+                }
+            };
+            // 例外の値はスレッドをまたげないので、文字列にして返す
+            let mut runtime = runtime::Runtime::new(catalog);
+            runtime
+                .execute(program)
+                .map_err(|exception| exception.to_string())
+        })
+        .expect("failed to spawn the script thread");
+    // 捕捉されなかった例外は終了コード 1 で終える
+    if let Err(exception) = execution.join().expect("the script thread panicked") {
+        eprintln!("Uncaught {}", exception);
+        std::process::exit(1);
+    }
     // println!("{:?}", program);
 }
@@ -0,0 +1,456 @@
+use core::cmp::Ordering;
+use core::fmt::{Display, Formatter};
+use core::ops::{Add, Mul, Neg, Sub};
+
+use crate::lexer::{is_line_terminator, is_whitespace};
+
+/// Number::toString(x, 10)。最短で元の値に戻る桁を使う
+/// Reference: https://262.ecma-international.org/#sec-numeric-types-number-tostring
+pub fn number_to_string(value: f64) -> String {
+    if value.is_nan() {
+        return "NaN".to_string();
+    }
+    if value == 0.0 {
+        // -0 も "0"
+        return "0".to_string();
+    }
+    if value < 0.0 {
+        return format!("-{}", number_to_string(-value));
+    }
+    if value.is_infinite() {
+        return "Infinity".to_string();
+    }
+
+    // {:e} は最短の桁を "d.ddde±n" の形で返す。s × 10^(n - k) = value となる s, k, n を取り出す
+    let scientific = format!("{:e}", value);
+    let (mantissa, exponent) = scientific.split_once('e').unwrap();
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let k = digits.len() as i32;
+    let n = exponent.parse::<i32>().unwrap() + 1;
+
+    if k <= n && n <= 21 {
+        format!("{}{}", digits, "0".repeat((n - k) as usize))
+    } else if 0 < n && n <= 21 {
+        let (integer, fraction) = digits.split_at(n as usize);
+        format!("{}.{}", integer, fraction)
+    } else if -6 < n && n <= 0 {
+        format!("0.{}{}", "0".repeat(-n as usize), digits)
+    } else {
+        let sign = if n - 1 < 0 { '-' } else { '+' };
+        let (first, rest) = digits.split_at(1);
+        if rest.is_empty() {
+            format!("{}e{}{}", first, sign, (n - 1).abs())
+        } else {
+            format!("{}.{}e{}{}", first, rest, sign, (n - 1).abs())
+        }
+    }
+}
+
+/// StringToNumber。数値として読めない文字列は NaN
+/// Reference: https://262.ecma-international.org/#sec-stringtonumber
+pub fn string_to_number(s: &str) -> f64 {
+    let s = s.trim_matches(|c| is_whitespace(c) || is_line_terminator(c));
+    if s.is_empty() {
+        return 0.0;
+    }
+    let radix = match s.get(..2) {
+        Some("0x" | "0X") => 16,
+        Some("0o" | "0O") => 8,
+        Some("0b" | "0B") => 2,
+        _ => 10,
+    };
+    if radix != 10 {
+        let digits = &s[2..];
+        if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+            return f64::NAN;
+        }
+        return BigInt::from_digits(digits, radix).to_f64();
+    }
+    match s {
+        "Infinity" | "+Infinity" => f64::INFINITY,
+        "-Infinity" => f64::NEG_INFINITY,
+        // Rust の parse は "inf" や "NaN" も受け付けるので、使える文字を先に確かめる
+        _ if s
+            .chars()
+            .all(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+' | '-')) =>
+        {
+            s.parse().unwrap_or(f64::NAN)
+        }
+        _ => f64::NAN,
+    }
+}
+
+/// BigInt の値。任意の大きさの符号付き整数
+/// Reference: https://262.ecma-international.org/#sec-ecmascript-language-types-bigint-type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BigInt {
+    /// 0 は常に非負とする
+    negative: bool,
+    /// 2^32 進の桁。下位から並べ、上位の 0 は持たない
+    magnitude: Vec<u32>,
+}
+
+/// BigInt の演算のエラー。RangeError として投げる
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BigIntError {
+    DivisionByZero,
+    NegativeExponent,
+    TooLarge,
+}
+
+impl Display for BigIntError {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        let message = match self {
+            BigIntError::DivisionByZero => "Division by zero",
+            BigIntError::NegativeExponent => "Exponent must be non-negative",
+            BigIntError::TooLarge => "Maximum BigInt size exceeded",
+        };
+        write!(f, "{}", message)
+    }
+}
+
+impl BigInt {
+    /// radix 進の数字列から作る。数字列は字句解析で検査済みとする
+    pub fn from_digits(digits: &str, radix: u32) -> Self {
+        let mut magnitude: Vec<u32> = Vec::new();
+        for c in digits.chars() {
+            let mut carry = c.to_digit(radix).unwrap() as u64;
+            for limb in magnitude.iter_mut() {
+                let value = *limb as u64 * radix as u64 + carry;
+                *limb = value as u32;
+                carry = value >> 32;
+            }
+            if carry > 0 {
+                magnitude.push(carry as u32);
+            }
+        }
+        Self {
+            negative: false,
+            magnitude,
+        }
+    }
+
+    fn from_magnitude(negative: bool, mut magnitude: Vec<u32>) -> Self {
+        while magnitude.last() == Some(&0) {
+            magnitude.pop();
+        }
+        Self {
+            negative: negative && !magnitude.is_empty(),
+            magnitude,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.magnitude.is_empty()
+    }
+
+    /// 商と余り。商は 0 に向かって切り捨て、余りの符号は被除数に合わせる
+    pub fn div_rem(&self, divisor: &BigInt) -> Result<(BigInt, BigInt), BigIntError> {
+        if divisor.is_zero() {
+            return Err(BigIntError::DivisionByZero);
+        }
+        // 1ビットずつ引いていく筆算
+        let mut quotient = vec![0u32; self.magnitude.len()];
+        let mut remainder = Vec::new();
+        for i in (0..self.magnitude.len() * 32).rev() {
+            remainder = shift_left_one(&remainder, (self.magnitude[i / 32] >> (i % 32)) & 1);
+            if compare_magnitude(&remainder, &divisor.magnitude) != Ordering::Less {
+                remainder = sub_magnitude(&remainder, &divisor.magnitude);
+                quotient[i / 32] |= 1 << (i % 32);
+            }
+        }
+        Ok((
+            BigInt::from_magnitude(self.negative != divisor.negative, quotient),
+            BigInt::from_magnitude(self.negative, remainder),
+        ))
+    }
+
+    /// Reference: https://262.ecma-international.org/#sec-numeric-types-bigint-exponentiate
+    pub fn pow(&self, exponent: &BigInt) -> Result<BigInt, BigIntError> {
+        if exponent.negative {
+            return Err(BigIntError::NegativeExponent);
+        }
+        // 0, 1, -1 は指数が大きくても結果が決まる
+        if self.is_zero() || self.magnitude == [1] {
+            let odd = exponent.magnitude.first().is_some_and(|limb| limb & 1 == 1);
+            return Ok(match (exponent.is_zero(), self.negative && !odd) {
+                (true, _) => BigInt::from_digits("1", 10),
+                (false, true) => -self.clone(),
+                (false, false) => self.clone(),
+            });
+        }
+        if exponent.magnitude.len() > 1 || exponent.magnitude.first() > Some(&(1 << 24)) {
+            return Err(BigIntError::TooLarge);
+        }
+        let mut exponent = exponent.magnitude.first().copied().unwrap_or(0);
+        let mut base = self.clone();
+        let mut result = BigInt::from_digits("1", 10);
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = &result * &base;
+            }
+            base = &base * &base;
+            exponent >>= 1;
+        }
+        Ok(result)
+    }
+
+    /// Number に変換する。u128 に収まらない値は上位の桁から近似する
+    pub fn to_f64(&self) -> f64 {
+        if self.magnitude.len() <= 4 {
+            let value = self
+                .magnitude
+                .iter()
+                .rev()
+                .fold(0u128, |acc, limb| (acc << 32) | *limb as u128);
+            let value = value as f64;
+            return if self.negative { -value } else { value };
+        }
+        let value = self
+            .magnitude
+            .iter()
+            .rev()
+            .fold(0.0, |acc, limb| acc * 4294967296.0 + *limb as f64);
+        if self.negative { -value } else { value }
+    }
+}
+
+fn compare_magnitude(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0u64;
+    for i in 0..a.len().max(b.len()) {
+        let sum = *a.get(i).unwrap_or(&0) as u64 + *b.get(i).unwrap_or(&0) as u64 + carry;
+        result.push(sum as u32);
+        carry = sum >> 32;
+    }
+    if carry > 0 {
+        result.push(carry as u32);
+    }
+    result
+}
+
+/// a - b。a >= b であること
+fn sub_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+    for (i, limb) in a.iter().enumerate() {
+        let mut diff = *limb as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+        borrow = 0;
+        if diff < 0 {
+            diff += 1 << 32;
+            borrow = 1;
+        }
+        result.push(diff as u32);
+    }
+    while result.last() == Some(&0) {
+        result.pop();
+    }
+    result
+}
+
+/// (a << 1) | bit
+fn shift_left_one(a: &[u32], bit: u32) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len() + 1);
+    let mut carry = bit;
+    for limb in a {
+        result.push((limb << 1) | carry);
+        carry = limb >> 31;
+    }
+    if carry > 0 {
+        result.push(carry);
+    }
+    result
+}
+
+impl Neg for BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::from_magnitude(!self.negative, self.magnitude)
+    }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, rhs: &BigInt) -> BigInt {
+        if self.negative == rhs.negative {
+            return BigInt::from_magnitude(
+                self.negative,
+                add_magnitude(&self.magnitude, &rhs.magnitude),
+            );
+        }
+        // 符号が違うときは絶対値の大きい方から小さい方を引く
+        match compare_magnitude(&self.magnitude, &rhs.magnitude) {
+            Ordering::Less => {
+                BigInt::from_magnitude(rhs.negative, sub_magnitude(&rhs.magnitude, &self.magnitude))
+            }
+            _ => BigInt::from_magnitude(
+                self.negative,
+                sub_magnitude(&self.magnitude, &rhs.magnitude),
+            ),
+        }
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, rhs: &BigInt) -> BigInt {
+        self + &-rhs.clone()
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, rhs: &BigInt) -> BigInt {
+        let mut result = vec![0u32; self.magnitude.len() + rhs.magnitude.len()];
+        for (i, a) in self.magnitude.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, b) in rhs.magnitude.iter().enumerate() {
+                let value = *a as u64 * *b as u64 + result[i + j] as u64 + carry;
+                result[i + j] = value as u32;
+                carry = value >> 32;
+            }
+            result[i + rhs.magnitude.len()] = carry as u32;
+        }
+        BigInt::from_magnitude(self.negative != rhs.negative, result)
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => compare_magnitude(&self.magnitude, &other.magnitude),
+            (true, true) => compare_magnitude(&other.magnitude, &self.magnitude),
+        }
+    }
+}
+
+impl Display for BigInt {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        if self.magnitude.is_empty() {
+            return write!(f, "0");
+        }
+        // 10^9 で割った余りを下位から集める
+        let mut magnitude = self.magnitude.clone();
+        let mut chunks = Vec::new();
+        while !magnitude.is_empty() {
+            let mut remainder = 0u64;
+            for limb in magnitude.iter_mut().rev() {
+                let value = (remainder << 32) | *limb as u64;
+                *limb = (value / 1_000_000_000) as u32;
+                remainder = value % 1_000_000_000;
+            }
+            while magnitude.last() == Some(&0) {
+                magnitude.pop();
+            }
+            chunks.push(remainder);
+        }
+        let mut chunks = chunks.iter().rev();
+        if self.negative {
+            write!(f, "-")?;
+        }
+        write!(f, "{}", chunks.next().unwrap())?;
+        for chunk in chunks {
+            write!(f, "{:09}", chunk)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_number_to_string() {
+        assert_eq!(number_to_string(1.5), "1.5");
+        assert_eq!(number_to_string(-3.0), "-3");
+        assert_eq!(number_to_string(-0.0), "0");
+        assert_eq!(number_to_string(0.1 + 0.2), "0.30000000000000004");
+        assert_eq!(number_to_string(1e21), "1e+21");
+        assert_eq!(
+            number_to_string(123456789012345680000.0),
+            "123456789012345680000"
+        );
+        assert_eq!(number_to_string(0.000001), "0.000001");
+        assert_eq!(number_to_string(1.5e-7), "1.5e-7");
+        assert_eq!(number_to_string(f64::NAN), "NaN");
+        assert_eq!(number_to_string(f64::NEG_INFINITY), "-Infinity");
+    }
+
+    #[test]
+    fn test_bigint() {
+        assert_eq!(BigInt::from_digits("0", 10).to_string(), "0");
+        assert_eq!(
+            BigInt::from_digits("123456789012345678901234567890", 10).to_string(),
+            "123456789012345678901234567890"
+        );
+        assert_eq!(
+            BigInt::from_digits("ffffffffffffffffffffffff", 16).to_string(),
+            "79228162514264337593543950335"
+        );
+        assert_eq!(BigInt::from_digits("777", 8).to_f64(), 511.0);
+    }
+
+    #[test]
+    fn test_bigint_arithmetic() {
+        let big = |s: &str| match s.strip_prefix('-') {
+            Some(digits) => -BigInt::from_digits(digits, 10),
+            None => BigInt::from_digits(s, 10),
+        };
+        assert_eq!((&big("5") - &big("7")).to_string(), "-2");
+        assert_eq!((&big("-5") + &big("5")).to_string(), "0");
+        assert_eq!(
+            (&big("4294967296") * &big("-4294967296")).to_string(),
+            "-18446744073709551616"
+        );
+        let (quotient, remainder) = big("-7").div_rem(&big("2")).unwrap();
+        assert_eq!(
+            (quotient.to_string(), remainder.to_string()),
+            ("-3".into(), "-1".into())
+        );
+        assert_eq!(
+            big("1").div_rem(&big("0")),
+            Err(BigIntError::DivisionByZero)
+        );
+        assert_eq!(
+            big("2").pow(&big("100")).unwrap().to_string(),
+            "1267650600228229401496703205376"
+        );
+        assert_eq!(
+            big("-1").pow(&big("1000000000001")).unwrap().to_string(),
+            "-1"
+        );
+        assert_eq!(big("2").pow(&big("-1")), Err(BigIntError::NegativeExponent));
+        assert!(big("-10") < big("3"));
+    }
+
+    #[test]
+    fn test_string_to_number() {
+        assert_eq!(string_to_number("  42\n"), 42.0);
+        assert_eq!(string_to_number(""), 0.0);
+        assert_eq!(string_to_number("-1.5e3"), -1500.0);
+        assert_eq!(string_to_number("0x1F"), 31.0);
+        assert_eq!(string_to_number("-Infinity"), f64::NEG_INFINITY);
+        assert!(string_to_number("inf").is_nan());
+        assert!(string_to_number("1_000").is_nan());
+        assert!(string_to_number("12px").is_nan());
+    }
+}
@@ -0,0 +1,189 @@
+// JavaScript のオブジェクトモデル。オブジェクトと配列は参照で共有する
+// Reference: https://262.ecma-international.org/#sec-object-type
+use core::fmt::Debug;
+use core::fmt::Formatter;
+use std::cell::{Ref, RefCell, RefMut};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::runtime::RuntimeValue;
+
+/// 参照で共有する値。代入や引数で渡しても同じ値を指し、比較は同一性で行う
+pub struct Shared<T>(Rc<RefCell<T>>);
+
+impl<T> Shared<T> {
+    pub fn new(value: T) -> Self {
+        Self(Rc::new(RefCell::new(value)))
+    }
+
+    pub fn borrow(&self) -> Ref<'_, T> {
+        self.0.borrow()
+    }
+
+    pub fn borrow_mut(&self) -> RefMut<'_, T> {
+        self.0.borrow_mut()
+    }
+}
+
+impl<T> Clone for Shared<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> PartialEq for Shared<T> {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+// 循環する参照をたどらないように中身は出力しない
+impl<T> Debug for Shared<T> {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        f.debug_struct("Shared").finish_non_exhaustive()
+    }
+}
+
+/// 配列の添字として読めるキー。"01" や "1.0" は添字ではなく、2^32 - 1 以上も添字ではない
+/// Reference: https://262.ecma-international.org/#array-index
+pub fn array_index(key: &str) -> Option<usize> {
+    key.parse::<u32>()
+        .ok()
+        .filter(|index| *index != u32::MAX && index.to_string() == key)
+        .map(|index| index as usize)
+}
+
+/// 普通のオブジェクト。プロパティは作った順に並ぶ
+/// Reference: https://262.ecma-international.org/#sec-ordinary-object-internal-methods-and-internal-slots
+#[derive(Debug, Default)]
+pub struct Object {
+    properties: Vec<(String, RuntimeValue)>,
+    prototype: Option<Shared<Object>>,
+}
+
+impl Object {
+    pub fn with_properties(properties: impl IntoIterator<Item = (String, RuntimeValue)>) -> Self {
+        let mut object = Self::default();
+        for (key, value) in properties {
+            object.set(key, value);
+        }
+        object
+    }
+
+    pub fn get_own(&self, key: &str) -> Option<&RuntimeValue> {
+        self.properties
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value)
+    }
+
+    /// 自身になければプロトタイプをたどって探す
+    /// Reference: https://262.ecma-international.org/#sec-ordinary-get
+    pub fn get(&self, key: &str) -> Option<RuntimeValue> {
+        match self.get_own(key) {
+            Some(value) => Some(value.clone()),
+            None => self.prototype.as_ref()?.borrow().get(key),
+        }
+    }
+
+    /// 自身のプロパティを作るか書き換える。プロトタイプの同名のプロパティは隠れる
+    pub fn set(&mut self, key: String, value: RuntimeValue) {
+        match self.properties.iter_mut().find(|(name, _)| *name == key) {
+            Some((_, current)) => *current = value,
+            None => self.properties.push((key, value)),
+        }
+    }
+
+    pub fn set_prototype(&mut self, prototype: Option<Shared<Object>>) {
+        self.prototype = prototype;
+    }
+
+    /// 自身のキー。整数のキーを小さい順に並べ、残りは作った順
+    /// Reference: https://262.ecma-international.org/#sec-ordinaryownpropertykeys
+    pub fn own_keys(&self) -> Vec<String> {
+        let (mut keys, names): (Vec<_>, Vec<_>) = self
+            .properties
+            .iter()
+            .map(|(key, _)| key.clone())
+            .partition(|key| array_index(key).is_some());
+        keys.sort_by_key(|key| array_index(key));
+        keys.extend(names);
+        keys
+    }
+
+    /// for...in で列挙するキー。自身のキーの後にプロトタイプのキーが続く
+    /// Reference: https://262.ecma-international.org/#sec-enumerate-object-properties
+    pub fn enumerable_keys(&self) -> Vec<String> {
+        let mut keys = self.own_keys();
+        if let Some(prototype) = &self.prototype {
+            for key in prototype.borrow().enumerable_keys() {
+                if !keys.contains(&key) {
+                    keys.push(key);
+                }
+            }
+        }
+        keys
+    }
+
+    /// プロトタイプから継承したものも含めたプロパティの一覧
+    pub fn to_map(&self) -> HashMap<String, RuntimeValue> {
+        let mut map = match &self.prototype {
+            Some(prototype) => prototype.borrow().to_map(),
+            None => HashMap::new(),
+        };
+        for (key, value) in &self.properties {
+            map.insert(key.clone(), value.clone());
+        }
+        map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(value: &str) -> RuntimeValue {
+        RuntimeValue::StringLiteral(value.to_string())
+    }
+
+    #[test]
+    fn test_own_keys_order() {
+        let object = Object::with_properties([
+            ("b".to_string(), string("1")),
+            ("10".to_string(), string("2")),
+            ("a".to_string(), string("3")),
+            ("2".to_string(), string("4")),
+            ("01".to_string(), string("5")),
+        ]);
+        assert_eq!(object.own_keys(), ["2", "10", "b", "a", "01"]);
+    }
+
+    #[test]
+    fn test_prototype_chain() {
+        let base = Shared::new(Object::with_properties([
+            ("greeting".to_string(), string("hello")),
+            ("name".to_string(), string("base")),
+        ]));
+        let mut derived = Object::with_properties([("name".to_string(), string("derived"))]);
+        derived.set_prototype(Some(base.clone()));
+
+        assert_eq!(derived.get("greeting"), Some(string("hello")));
+        assert_eq!(derived.get("name"), Some(string("derived")));
+        assert_eq!(derived.get("missing"), None);
+        assert_eq!(derived.get_own("greeting"), None);
+        assert_eq!(derived.enumerable_keys(), ["name", "greeting"]);
+
+        // プロトタイプへの変更は継承した側からも見える
+        base.borrow_mut().set("extra".to_string(), string("!"));
+        assert_eq!(derived.get("extra"), Some(string("!")));
+        assert_eq!(derived.prototype, Some(base));
+    }
+
+    #[test]
+    fn test_shared_identity() {
+        let a = Shared::new(Object::default());
+        let b = Shared::new(Object::default());
+        assert_eq!(a, a.clone());
+        assert_ne!(a, b);
+    }
+}
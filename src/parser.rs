@@ -1,12 +1,22 @@
 use std::iter::Peekable;
 
+use core::fmt::Display;
+use core::fmt::Formatter;
+
 use crate::lexer;
+use crate::numeric::{BigInt, number_to_string};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Node {
+    /// kind は "const"、"let"、"var" のいずれか
     VariableDeclaration {
+        kind: String,
+        declarations: Vec<Node>,
+    },
+    /// 初期化子のない let と var は init が None
+    VariableDeclarator {
         id: Box<Node>,
-        init: Box<Node>,
+        init: Option<Box<Node>>,
     },
     ExpressionStatement {
         expression: Box<Node>,
@@ -16,6 +26,38 @@ pub enum Node {
         left: Box<Node>,
         right: Box<Node>,
     },
+    /// 算術、比較、等価の演算子
+    BinaryExpression {
+        operator: String,
+        left: Box<Node>,
+        right: Box<Node>,
+    },
+    /// 短絡評価する &&, ||, ??
+    LogicalExpression {
+        operator: String,
+        left: Box<Node>,
+        right: Box<Node>,
+    },
+    UnaryExpression {
+        operator: String,
+        argument: Box<Node>,
+    },
+    /// ++ と --。prefix が false なら後置
+    UpdateExpression {
+        operator: String,
+        prefix: bool,
+        argument: Box<Node>,
+    },
+    /// test ? consequent : alternate
+    ConditionalExpression {
+        test: Box<Node>,
+        consequent: Box<Node>,
+        alternate: Box<Node>,
+    },
+    /// カンマ演算子。最後の式の値になる
+    SequenceExpression {
+        expressions: Vec<Node>,
+    },
     NewExpression {
         callee: Box<Node>,
         arguments: Vec<Node>,
@@ -24,27 +66,134 @@ pub enum Node {
         callee: Box<Node>,
         arguments: Vec<Node>,
     },
+    /// computed なら a[property]、そうでなければ a.property で property は Identifier
     MemberExpression {
         object: Box<Node>,
         property: Box<Node>,
+        computed: bool,
     },
     ObjectExpression {
         properties: Vec<Node>,
     },
+    /// 省略された要素 (例: [1, , 3] の2番目) は None
+    ArrayExpression {
+        elements: Vec<Option<Node>>,
+    },
+    /// 計算しないキーは StringLiteral にしておく。例: { 1: a } の key は "1"
     Property {
-        key: String,
+        key: Box<Node>,
         value: Box<Node>,
     },
+    /// 本体は式か BlockStatement
+    ArrowFunctionExpression {
+        params: Vec<Node>,
+        body: Box<Node>,
+    },
+    FunctionDeclaration {
+        id: String,
+        params: Vec<Node>,
+        body: Box<Node>,
+    },
+    /// 名前付きの関数式の名前は本体の中でだけ参照できる
+    FunctionExpression {
+        id: Option<String>,
+        params: Vec<Node>,
+        body: Box<Node>,
+    },
+    /// 既定値のある仮引数。例: (a = 1) => a
+    AssignmentPattern {
+        left: Box<Node>,
+        right: Box<Node>,
+    },
+    /// 残余引数。例: (...rest) => rest
+    RestElement {
+        argument: Box<Node>,
+    },
+    BlockStatement {
+        body: Vec<Node>,
+    },
+    ReturnStatement {
+        argument: Option<Box<Node>>,
+    },
+    ThrowStatement {
+        argument: Box<Node>,
+    },
+    IfStatement {
+        test: Box<Node>,
+        consequent: Box<Node>,
+        alternate: Option<Box<Node>>,
+    },
+    WhileStatement {
+        test: Box<Node>,
+        body: Box<Node>,
+    },
+    DoWhileStatement {
+        body: Box<Node>,
+        test: Box<Node>,
+    },
+    /// init は VariableDeclaration か式
+    ForStatement {
+        init: Option<Box<Node>>,
+        test: Option<Box<Node>>,
+        update: Option<Box<Node>>,
+        body: Box<Node>,
+    },
+    /// left は初期化子のない VariableDeclaration か代入先の式
+    ForOfStatement {
+        left: Box<Node>,
+        right: Box<Node>,
+        body: Box<Node>,
+    },
+    ForInStatement {
+        left: Box<Node>,
+        right: Box<Node>,
+        body: Box<Node>,
+    },
+    SwitchStatement {
+        discriminant: Box<Node>,
+        cases: Vec<Node>,
+    },
+    /// test が None なら default 節
+    SwitchCase {
+        test: Option<Box<Node>>,
+        consequent: Vec<Node>,
+    },
+    BreakStatement {
+        label: Option<String>,
+    },
+    ContinueStatement {
+        label: Option<String>,
+    },
+    LabeledStatement {
+        label: String,
+        body: Box<Node>,
+    },
+    EmptyStatement,
+    /// handler と finalizer の少なくとも一方がある
+    TryStatement {
+        block: Box<Node>,
+        param: Option<String>,
+        handler: Option<Box<Node>>,
+        finalizer: Option<Box<Node>>,
+    },
+    /// quasis は expressions より1つ多い
+    TemplateLiteral {
+        quasis: Vec<String>,
+        expressions: Vec<Node>,
+    },
     Identifier(String),
     StringLiteral(String),
-    NumericLiteral(u64),
+    NumericLiteral(f64),
+    BigIntLiteral(BigInt),
+    BooleanLiteral(bool),
+    NullLiteral,
 }
 
 impl Node {
-    pub fn new_variable_declaration(id: Node, init: Node) -> Self {
-        Node::VariableDeclaration {
+    pub fn new_variable_declarator(id: Node, init: Option<Node>) -> Self {
+        Node::VariableDeclarator {
             id: Box::new(id),
-            init: Box::new(init),
+            init: init.map(Box::new),
         }
     }
     pub fn new_new_expression(callee: Node, arguments: Vec<Node>) -> Self {
@@ -56,10 +205,11 @@ impl Node {
     pub fn new_string_literal(value: String) -> Self {
         Node::StringLiteral(value)
     }
-    pub fn new_member_expression(object: Node, property: Node) -> Self {
+    pub fn new_member_expression(object: Node, property: Node, computed: bool) -> Self {
         Node::MemberExpression {
             object: Box::new(object),
             property: Box::new(property),
+            computed,
         }
     }
     pub fn new_call_expression(callee: Node, arguments: Vec<Node>) -> Self {
@@ -80,18 +230,112 @@ impl Node {
             right: Box::new(right),
         }
     }
+    pub fn new_binary_expression(operator: &str, left: Node, right: Node) -> Self {
+        let (left, right) = (Box::new(left), Box::new(right));
+        let operator = operator.to_string();
+        match operator.as_str() {
+            "&&" | "||" | "??" => Node::LogicalExpression {
+                operator,
+                left,
+                right,
+            },
+            _ => Node::BinaryExpression {
+                operator,
+                left,
+                right,
+            },
+        }
+    }
+    pub fn new_unary_expression(operator: &str, argument: Node) -> Self {
+        Node::UnaryExpression {
+            operator: operator.to_string(),
+            argument: Box::new(argument),
+        }
+    }
     pub fn new_identifier(name: String) -> Self {
         Node::Identifier(name)
     }
-    pub fn new_property(key: String, value: Node) -> Self {
+    pub fn new_property(key: Node, value: Node) -> Self {
         Node::Property {
-            key,
+            key: Box::new(key),
             value: Box::new(value),
         }
     }
+    pub fn new_arrow_function_expression(params: Vec<Node>, body: Node) -> Self {
+        Node::ArrowFunctionExpression {
+            params,
+            body: Box::new(body),
+        }
+    }
+    pub fn new_return_statement(argument: Option<Node>) -> Self {
+        Node::ReturnStatement {
+            argument: argument.map(Box::new),
+        }
+    }
+    pub fn new_throw_statement(argument: Node) -> Self {
+        Node::ThrowStatement {
+            argument: Box::new(argument),
+        }
+    }
 }
 
+/// 構文エラー。実行せずに SyntaxError として報告する
 #[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    pub message: String,
+    /// エラーになったトークンの位置
+    pub position: lexer::Position,
+}
+
+impl Display for SyntaxError {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        write!(f, "SyntaxError: {}", self.message)
+    }
+}
+
+/// V8 と同じ形式の "Unexpected ..." メッセージ
+fn unexpected_message(token: Option<lexer::Token>) -> String {
+    match token {
+        None => "Unexpected end of input".to_string(),
+        Some(lexer::Token::Punctuator(c)) => format!("Unexpected token '{}'", c),
+        Some(lexer::Token::Arrow) => "Unexpected token '=>'".to_string(),
+        Some(lexer::Token::Ellipsis) => "Unexpected token '...'".to_string(),
+        Some(lexer::Token::Operator(op)) => format!("Unexpected token '{}'", op),
+        Some(lexer::Token::Number(_) | lexer::Token::BigInt(_)) => "Unexpected number".to_string(),
+        Some(lexer::Token::StringLiteral(_)) => "Unexpected string".to_string(),
+        Some(lexer::Token::Template { .. }) => "Unexpected template string".to_string(),
+        Some(lexer::Token::Identifier(name)) => format!("Unexpected identifier '{}'", name),
+        Some(lexer::Token::Keyword(name)) => format!("Unexpected token '{}'", name),
+        Some(lexer::Token::Invalid(message)) => message,
+    }
+}
+
+type ParseResult<T> = Result<T, SyntaxError>;
+
+/// 式と文の入れ子の上限。再帰下降で読むので、超えたらスタックが溢れる前に構文エラーにする
+const MAX_NESTING_DEPTH: usize = 1000;
+
+/// 複合代入演算子。&&=、||=、??= は右辺を短絡評価する
+static COMPOUND_ASSIGNMENT_OPERATORS: [&str; 9] =
+    ["+=", "-=", "*=", "/=", "%=", "**=", "&&=", "||=", "??="];
+
+/// 二項演算子の優先順位。大きいほど強く結びつく
+/// Reference: https://262.ecma-international.org/#sec-ecmascript-language-expressions
+fn binary_precedence(operator: &str) -> Option<u8> {
+    match operator {
+        "??" => Some(1),
+        "||" => Some(2),
+        "&&" => Some(3),
+        "==" | "!=" | "===" | "!==" => Some(4),
+        "<" | ">" | "<=" | ">=" => Some(5),
+        "+" | "-" => Some(6),
+        "*" | "/" | "%" => Some(7),
+        "**" => Some(8),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Program {
     pub body: Vec<Node>,
 }
@@ -106,282 +350,1241 @@ impl Program {
     }
 }
 
+/// break と continue の飛び先。関数の本体に入ると空からやり直す
+#[derive(Default)]
+struct JumpTargets {
+    /// 囲んでいるラベルと、それがループに付いているか
+    labels: Vec<(String, bool)>,
+    /// 囲んでいるループの数
+    loops: usize,
+    /// 囲んでいる switch の数
+    switches: usize,
+}
+
 pub struct Parser {
     lexer: Peekable<lexer::Lexer>,
+    /// 最後に読んだトークンの位置。入力の終わりに達した後は end
+    position: lexer::Position,
+    end: lexer::Position,
+    /// return を書ける関数本体の入れ子の深さ
+    function_depth: usize,
+    /// 読んでいる式と文の入れ子の深さ
+    nesting_depth: usize,
+    jump_targets: JumpTargets,
 }
 
 impl Parser {
     pub fn new(src: &str) -> Self {
-        let lexer = lexer::Lexer::new(src).peekable();
-        Self { lexer }
+        Self::with_origin(src, lexer::Position::START)
     }
 
-    fn parse_literal(&mut self) -> Option<Node> {
-        let t = match self.lexer.next() {
-            Some(token) => token,
-            None => return None,
-        };
-        match t {
-            lexer::Token::StringLiteral(s) => Some(Node::new_string_literal(s)),
-            lexer::Token::Number(n) => Some(Node::NumericLiteral(n)),
-            _ => None,
+    fn with_origin(src: &str, origin: lexer::Position) -> Self {
+        let lexer = lexer::Lexer::with_origin(src, origin);
+        let end = lexer.end_position();
+        Self {
+            lexer: lexer.peekable(),
+            position: origin,
+            end,
+            function_depth: 0,
+            nesting_depth: 0,
+            jump_targets: JumpTargets::default(),
+        }
+    }
+
+    fn next_token(&mut self) -> Option<lexer::Token> {
+        match self.lexer.next() {
+            Some((token, position, _)) => {
+                self.position = position;
+                Some(token)
+            }
+            None => {
+                self.position = self.end;
+                None
+            }
         }
     }
 
-    fn parse_object_expression(&mut self) -> Node {
+    fn peek_token(&mut self) -> Option<&lexer::Token> {
+        self.lexer.peek().map(|(token, _, _)| token)
+    }
+
+    /// 次のトークンの前に改行があるか
+    fn newline_before_next(&mut self) -> bool {
+        matches!(self.lexer.peek(), Some((_, _, true)))
+    }
+
+    /// 最後に読んだトークンの位置で構文エラーを作る
+    fn error(&self, message: &str) -> SyntaxError {
+        SyntaxError {
+            message: message.to_string(),
+            position: self.position,
+        }
+    }
+
+    fn unexpected(&self, token: Option<lexer::Token>) -> SyntaxError {
+        SyntaxError {
+            message: unexpected_message(token),
+            position: self.position,
+        }
+    }
+
+    /// 次のトークンを読み、それが予期しないトークンであるというエラーを作る
+    fn unexpected_next(&mut self) -> SyntaxError {
+        let token = self.next_token();
+        self.unexpected(token)
+    }
+
+    /// 入れ子を一段深くして parse で読む
+    fn nested<T>(&mut self, parse: fn(&mut Self) -> ParseResult<T>) -> ParseResult<T> {
+        if self.nesting_depth >= MAX_NESTING_DEPTH {
+            return Err(self.error("Too deeply nested"));
+        }
+        self.nesting_depth += 1;
+        let result = parse(self);
+        self.nesting_depth -= 1;
+        result
+    }
+
+    fn expect_punctuator(&mut self, expected: char) -> ParseResult<()> {
+        match self.next_token() {
+            Some(lexer::Token::Punctuator(c)) if c == expected => Ok(()),
+            t => Err(self.unexpected(t)),
+        }
+    }
+
+    fn parse_literal(&mut self) -> ParseResult<Node> {
+        match self.next_token() {
+            Some(lexer::Token::StringLiteral(s)) => Ok(Node::new_string_literal(s)),
+            Some(lexer::Token::Number(n)) => Ok(Node::NumericLiteral(n)),
+            Some(lexer::Token::BigInt(n)) => Ok(Node::BigIntLiteral(n)),
+            Some(lexer::Token::Keyword(k)) if k == "true" || k == "false" => {
+                Ok(Node::BooleanLiteral(k == "true"))
+            }
+            Some(lexer::Token::Keyword(k)) if k == "null" => Ok(Node::NullLiteral),
+            Some(lexer::Token::Template {
+                quasis,
+                expressions,
+            }) => self.parse_template_literal(quasis, expressions),
+            t => Err(self.unexpected(t)),
+        }
+    }
+
+    /// ${} の中のソースをそれぞれ1つの式として解析する
+    fn parse_template_literal(
+        &mut self,
+        quasis: Vec<String>,
+        expressions: Vec<(String, lexer::Position)>,
+    ) -> ParseResult<Node> {
+        let expressions = expressions
+            .iter()
+            .map(|(src, origin)| {
+                let mut parser = Parser::with_origin(src, *origin);
+                parser.function_depth = self.function_depth;
+                parser.nesting_depth = self.nesting_depth;
+                let expression = parser.parse_assignment_expression()?;
+                match parser.next_token() {
+                    None => Ok(expression),
+                    t => Err(parser.unexpected(t)),
+                }
+            })
+            .collect::<ParseResult<Vec<_>>>()?;
+        Ok(Node::TemplateLiteral {
+            quasis,
+            expressions,
+        })
+    }
+
+    /// { ... } の中身。{ name } は { name: name } の省略記法
+    /// Reference: https://262.ecma-international.org/#sec-object-initializer
+    fn parse_object_expression(&mut self) -> ParseResult<Node> {
         let mut properties = Vec::new();
 
         loop {
-            let t = match self.lexer.peek() {
-                Some(token) => token.clone(),
-                None => break,
+            let property = match self.next_token() {
+                Some(lexer::Token::Punctuator('}')) => break,
+                Some(lexer::Token::Identifier(name))
+                    if matches!(self.peek_token(), Some(lexer::Token::Punctuator(',' | '}'))) =>
+                {
+                    Node::new_property(Node::StringLiteral(name.clone()), Node::Identifier(name))
+                }
+                token => {
+                    let key = self.parse_property_key(token)?;
+                    self.expect_punctuator(':')?;
+                    Node::new_property(key, self.parse_assignment_expression()?)
+                }
             };
-            match t {
-                lexer::Token::Identifier(name) => {
-                    self.lexer.next(); // consume identifier
-                    let key = name.clone();
-                    let t = match self.lexer.next() {
-                        Some(token) => token,
-                        None => panic!("Unexpected end of input after object key"),
-                    };
-                    match t {
-                        lexer::Token::Punctuator(':') => {
-                            let value = match self.parse_literal() {
-                                Some(v) => v,
-                                None => {
-                                    panic!("Expected primitive value after ':' in object property")
-                                }
-                            };
-                            properties.push(Node::new_property(key, value));
-                            let t = match self.lexer.peek() {
-                                Some(token) => token.clone(),
-                                None => break,
-                            };
-                            match t {
-                                lexer::Token::Punctuator(',') => {
-                                    self.lexer.next(); // consume ','
-                                }
-                                lexer::Token::Punctuator('}') => {
-                                    self.lexer.next(); // consume '}'
-                                    break;
-                                }
-                                _ => panic!("Unexpected token in object expression: {:?}", t),
-                            }
-                        }
-                        _ => panic!("Expected ':' after object key"),
-                    }
+            properties.push(property);
+            match self.next_token() {
+                Some(lexer::Token::Punctuator(',')) => {}
+                Some(lexer::Token::Punctuator('}')) => break,
+                t => return Err(self.unexpected(t)),
+            }
+        }
+        Ok(Node::ObjectExpression { properties })
+    }
+
+    /// プロパティのキー。名前、文字列、数値は文字列にし、[式] は式のまま返す
+    fn parse_property_key(&mut self, token: Option<lexer::Token>) -> ParseResult<Node> {
+        match token {
+            Some(
+                lexer::Token::Identifier(name)
+                | lexer::Token::Keyword(name)
+                | lexer::Token::StringLiteral(name),
+            ) => Ok(Node::StringLiteral(name)),
+            Some(lexer::Token::Number(value)) => Ok(Node::StringLiteral(number_to_string(value))),
+            Some(lexer::Token::Punctuator('[')) => {
+                let key = self.parse_assignment_expression()?;
+                self.expect_punctuator(']')?;
+                Ok(key)
+            }
+            t => Err(self.unexpected(t)),
+        }
+    }
+
+    /// '[' の後を読む。要素は ',' で区切り、',' が続けば要素を省略した穴になる
+    /// Reference: https://262.ecma-international.org/#sec-array-initializer
+    fn parse_array_expression(&mut self) -> ParseResult<Node> {
+        let mut elements = Vec::new();
+
+        loop {
+            match self.peek_token() {
+                Some(lexer::Token::Punctuator(',')) => {
+                    self.next_token(); // consume ','
+                    elements.push(None);
+                    continue;
                 }
-                lexer::Token::Punctuator('}') => {
-                    self.lexer.next(); // consume '}'
+                Some(lexer::Token::Punctuator(']')) => {
+                    self.next_token(); // consume ']'
                     break;
                 }
-                _ => panic!("Unexpected token in object expression: {:?}", t),
+                _ => elements.push(Some(self.parse_assignment_expression()?)),
+            }
+            // 要素の後は ',' か ']'。最後の ',' は要素を増やさない
+            match self.next_token() {
+                Some(lexer::Token::Punctuator(',')) => {}
+                Some(lexer::Token::Punctuator(']')) => break,
+                t => return Err(self.unexpected(t)),
             }
         }
-        Node::ObjectExpression { properties }
+        Ok(Node::ArrayExpression { elements })
     }
 
-    fn parse_arguments(&mut self) -> Vec<Node> {
+    /// '(' の後の引数リストを読む。引数は ',' で区切り、最後の ',' は省略できる
+    /// Reference: https://262.ecma-international.org/#sec-argument-lists
+    fn parse_arguments(&mut self) -> ParseResult<Vec<Node>> {
         let mut args = Vec::new();
 
         loop {
-            let t = match self.lexer.peek() {
-                Some(token) => token.clone(),
-                None => break,
-            };
-            match t {
-                lexer::Token::StringLiteral(s) => {
-                    args.push(Node::new_string_literal(s.clone()));
-                    self.lexer.next(); // consume string literal
-                }
-                lexer::Token::Punctuator('{') => {
-                    self.lexer.next(); // consume '{' 
-                    args.push(self.parse_object_expression());
+            if let Some(lexer::Token::Punctuator(')')) = self.peek_token() {
+                self.next_token(); // consume ')'
+                break;
+            }
+            args.push(self.parse_assignment_expression()?);
+            match self.next_token() {
+                Some(lexer::Token::Punctuator(',')) => {}
+                Some(lexer::Token::Punctuator(')')) => break,
+                t => return Err(self.unexpected(t)),
+            }
+        }
+        Ok(args)
+    }
+
+    /// '(' の後を読む。'=>' が続けばアロー関数の仮引数リスト、そうでなければ括弧で囲んだ式
+    /// 括弧で囲んだ式には呼び出しやプロパティ参照が続けられる。例: (x => x + 1)(7)
+    fn parse_parenthesized_or_arrow(&mut self) -> ParseResult<Node> {
+        let mut expressions = Vec::new();
+        if let Some(lexer::Token::Punctuator(')')) = self.peek_token() {
+            self.next_token(); // consume ')'
+        } else {
+            loop {
+                // 残余引数は最後に置くアロー関数の仮引数にしか書けない
+                if let Some(lexer::Token::Ellipsis) = self.peek_token() {
+                    expressions.push(self.parse_rest_element()?);
+                    self.expect_punctuator(')')?;
+                    if !matches!(self.peek_token(), Some(lexer::Token::Arrow)) {
+                        return Err(self.unexpected_next());
+                    }
+                    break;
                 }
-                lexer::Token::Punctuator(',') => {
-                    self.lexer.next(); // consume ','
+                expressions.push(self.parse_assignment_expression()?);
+                match self.next_token() {
+                    Some(lexer::Token::Punctuator(',')) => {}
+                    Some(lexer::Token::Punctuator(')')) => break,
+                    t => return Err(self.unexpected(t)),
                 }
-                lexer::Token::Punctuator(')') => {
-                    self.lexer.next(); // consume ')'
+            }
+        }
+
+        if let Some(lexer::Token::Arrow) = self.peek_token() {
+            // 式として読んだものを仮引数に読み替える
+            let params = expressions
+                .into_iter()
+                .map(|expression| match expression {
+                    Node::Identifier(_) | Node::RestElement { .. } => Ok(expression),
+                    Node::AssignmentExpression {
+                        operator,
+                        left,
+                        right,
+                    } if operator == "=" && matches!(*left, Node::Identifier(_)) => {
+                        Ok(Node::AssignmentPattern { left, right })
+                    }
+                    _ => Err(self.error("Invalid destructuring assignment target")),
+                })
+                .collect::<ParseResult<Vec<_>>>()?;
+            self.next_token(); // consume '=>'
+            return self.parse_arrow_function(params);
+        }
+        let expression = match expressions.len() {
+            0 => return Err(self.unexpected_next()),
+            1 => expressions.pop().unwrap(),
+            _ => Node::SequenceExpression { expressions },
+        };
+        self.parse_call_tail(expression)
+    }
+
+    /// ...name
+    fn parse_rest_element(&mut self) -> ParseResult<Node> {
+        self.next_token(); // consume '...'
+        let argument = self.parse_identifier()?;
+        Ok(Node::RestElement {
+            argument: Box::new(argument),
+        })
+    }
+
+    /// '(' の後の仮引数リストを ')' まで読む
+    /// Reference: https://262.ecma-international.org/#prod-FormalParameters
+    fn parse_formal_parameters(&mut self) -> ParseResult<Vec<Node>> {
+        let mut params = Vec::new();
+        loop {
+            match self.peek_token() {
+                Some(lexer::Token::Punctuator(')')) => {
+                    self.next_token(); // consume ')'
                     break;
                 }
-                _ => panic!("Unexpected token in arguments: {:?}", t),
+                Some(lexer::Token::Ellipsis) => {
+                    params.push(self.parse_rest_element()?);
+                    match self.next_token() {
+                        Some(lexer::Token::Punctuator(')')) => break,
+                        _ => return Err(self.error("Rest parameter must be last formal parameter")),
+                    }
+                }
+                _ => {
+                    let param = self.parse_identifier()?;
+                    let param = match self.peek_token() {
+                        Some(lexer::Token::Punctuator('=')) => {
+                            self.next_token(); // consume '='
+                            Node::AssignmentPattern {
+                                left: Box::new(param),
+                                right: Box::new(self.parse_assignment_expression()?),
+                            }
+                        }
+                        _ => param,
+                    };
+                    params.push(param);
+                    match self.next_token() {
+                        Some(lexer::Token::Punctuator(',')) => {}
+                        Some(lexer::Token::Punctuator(')')) => break,
+                        t => return Err(self.unexpected(t)),
+                    }
+                }
             }
         }
-        args
+        Ok(params)
+    }
+
+    /// function の後の名前、仮引数リスト、本体。名前は式の場合だけ省略できる
+    /// Reference: https://262.ecma-international.org/#sec-function-definitions
+    fn parse_function(&mut self, is_declaration: bool) -> ParseResult<Node> {
+        let id = match self.peek_token() {
+            Some(lexer::Token::Identifier(_)) => match self.parse_identifier()? {
+                Node::Identifier(name) => Some(name),
+                _ => unreachable!("parse_identifier returns an Identifier"),
+            },
+            _ if is_declaration => {
+                self.next_token();
+                return Err(self.error("Function statements require a function name"));
+            }
+            _ => None,
+        };
+        self.expect_punctuator('(')?;
+        let params = self.parse_formal_parameters()?;
+        let body = Box::new(self.parse_function_body()?);
+        Ok(match id {
+            Some(id) if is_declaration => Node::FunctionDeclaration { id, params, body },
+            id => Node::FunctionExpression { id, params, body },
+        })
     }
 
-    fn parse_call_expression(&mut self) -> Option<Node> {
-        let callee = self.parse_member_expression();
-        let t = match self.lexer.peek() {
-            Some(token) => token.clone(),
-            None => return None,
+    /// '=>' の後の本体を読む。本体は式か { 文... }
+    fn parse_arrow_function(&mut self, params: Vec<Node>) -> ParseResult<Node> {
+        let body = match self.peek_token() {
+            Some(lexer::Token::Punctuator('{')) => self.parse_function_body()?,
+            _ => self.parse_assignment_expression()?,
         };
-        match t {
-            lexer::Token::Punctuator('(') => {
-                self.lexer.next(); // consume '('
-                let args = self.parse_arguments();
-                Some(Node::new_call_expression(callee, args))
+        Ok(Node::new_arrow_function_expression(params, body))
+    }
+
+    /// 関数の本体。外側のループやラベルには break や continue で飛べない
+    fn parse_function_body(&mut self) -> ParseResult<Node> {
+        self.function_depth += 1;
+        let jump_targets = std::mem::take(&mut self.jump_targets);
+        let body = self.parse_block();
+        self.jump_targets = jump_targets;
+        self.function_depth -= 1;
+        body
+    }
+
+    /// { 文... }
+    fn parse_block(&mut self) -> ParseResult<Node> {
+        self.expect_punctuator('{')?;
+        let mut body = Vec::new();
+        loop {
+            match self.peek_token() {
+                Some(lexer::Token::Punctuator('}')) => {
+                    self.next_token(); // consume '}'
+                    break;
+                }
+                Some(_) => body.extend(self.nested(Self::parse_statement)?),
+                None => return Err(self.unexpected_next()),
             }
-            _ => panic!("Expected '(' after callee in call expression"),
         }
+        Ok(Node::BlockStatement { body })
     }
 
-    fn parse_member_expression(&mut self) -> Node {
-        let mut members = Vec::new();
+    fn parse_call_expression(&mut self) -> ParseResult<Node> {
+        let callee = self.parse_member_expression()?;
+        match self.peek_token() {
+            // 仮引数が1つで括弧のないアロー関数。例: x => x
+            Some(lexer::Token::Arrow) => match callee {
+                Node::Identifier(_) => {
+                    self.next_token(); // consume '=>'
+                    self.parse_arrow_function(vec![callee])
+                }
+                _ => Err(self.unexpected_next()),
+            },
+            _ => self.parse_call_tail(callee),
+        }
+    }
 
+    /// 式に続く呼び出しとプロパティ参照。例: mf.resolvedOptions().locale、f(1)(2)
+    fn parse_call_tail(&mut self, mut expression: Node) -> ParseResult<Node> {
         loop {
-            let t = match self.lexer.next() {
-                Some(token) => token,
-                None => panic!("Unexpected end of input while parsing member expression"),
-            };
-            match t {
-                lexer::Token::Identifier(name) => {
-                    let property = name.clone();
-                    members.push(property);
-                    match &self.lexer.peek() {
-                        Some(lexer::Token::Punctuator('.')) => {
-                            self.lexer.next(); // consume '.'
-                        }
-                        _ => break,
-                    }
+            match self.peek_token() {
+                Some(lexer::Token::Punctuator('(')) => {
+                    self.next_token(); // consume '('
+                    let args = self.parse_arguments()?;
+                    expression = Node::new_call_expression(expression, args);
                 }
-                _ => break,
+                Some(lexer::Token::Punctuator('.' | '[')) => {
+                    expression = self.parse_property_access(expression)?;
+                }
+                _ => return Ok(expression),
             }
         }
-        let mut object = Node::Identifier(members[0].clone());
-        for property in &members[1..] {
-            object = Node::new_member_expression(object, Node::Identifier(property.clone()));
+    }
+
+    fn parse_member_expression(&mut self) -> ParseResult<Node> {
+        let mut object = match self.next_token() {
+            Some(lexer::Token::Identifier(name)) => Node::Identifier(name),
+            t => return Err(self.unexpected(t)),
+        };
+        while let Some(lexer::Token::Punctuator('.' | '[')) = self.peek_token() {
+            object = self.parse_property_access(object)?;
         }
-        object
+        Ok(object)
     }
 
-    fn parse_new_expression(&mut self) -> Node {
-        let callee = self.parse_member_expression();
-        let t = match self.lexer.peek() {
-            Some(token) => token,
-            None => panic!("Unexpected end of input after 'new' callee"),
+    /// .name か [式] を読んでプロパティ参照にする
+    fn parse_property_access(&mut self, object: Node) -> ParseResult<Node> {
+        match self.next_token() {
+            // 予約語もプロパティ名に使える。例: options.default
+            Some(lexer::Token::Punctuator('.')) => match self.next_token() {
+                Some(lexer::Token::Identifier(name) | lexer::Token::Keyword(name)) => Ok(
+                    Node::new_member_expression(object, Node::Identifier(name), false),
+                ),
+                t => Err(self.unexpected(t)),
+            },
+            Some(lexer::Token::Punctuator('[')) => {
+                let property = self.parse_assignment_expression()?;
+                self.expect_punctuator(']')?;
+                Ok(Node::new_member_expression(object, property, true))
+            }
+            t => Err(self.unexpected(t)),
+        }
+    }
+
+    fn parse_new_expression(&mut self) -> ParseResult<Node> {
+        let callee = self.parse_member_expression()?;
+        self.expect_punctuator('(')?;
+        let args = self.parse_arguments()?;
+        Ok(Node::new_new_expression(callee, args))
+    }
+
+    fn parse_left_hand_side_expression(&mut self) -> ParseResult<Node> {
+        match self.peek_token() {
+            Some(lexer::Token::Keyword(k)) if k == "new" => {
+                self.next_token(); // consume 'new'
+                let expression = self.parse_new_expression()?;
+                self.parse_call_tail(expression)
+            }
+            Some(lexer::Token::Keyword(k)) if k == "function" => {
+                self.next_token(); // consume 'function'
+                let expression = self.parse_function(false)?;
+                self.parse_call_tail(expression)
+            }
+            Some(lexer::Token::Identifier(_)) => self.parse_call_expression(),
+            // リテラルにもプロパティ参照や呼び出しが続けられる。例: "abc"[1]
+            Some(
+                lexer::Token::StringLiteral(_)
+                | lexer::Token::Number(_)
+                | lexer::Token::BigInt(_)
+                | lexer::Token::Template { .. },
+            ) => {
+                let literal = self.parse_literal()?;
+                self.parse_call_tail(literal)
+            }
+            Some(lexer::Token::Keyword(k)) if matches!(k.as_str(), "true" | "false" | "null") => {
+                let literal = self.parse_literal()?;
+                self.parse_call_tail(literal)
+            }
+            Some(lexer::Token::Punctuator('[')) => {
+                self.next_token(); // consume '['
+                let array = self.parse_array_expression()?;
+                self.parse_call_tail(array)
+            }
+            Some(lexer::Token::Punctuator('{')) => {
+                self.next_token(); // consume '{'
+                let object = self.parse_object_expression()?;
+                self.parse_call_tail(object)
+            }
+            Some(lexer::Token::Punctuator('(')) => {
+                self.next_token(); // consume '('
+                self.parse_parenthesized_or_arrow()
+            }
+            _ => Err(self.unexpected_next()),
+        }
+    }
+
+    /// Reference: https://262.ecma-international.org/#sec-unary-operators
+    fn parse_unary_expression(&mut self) -> ParseResult<Node> {
+        let operator = match self.peek_token() {
+            Some(lexer::Token::Operator(op @ ("!" | "-" | "+"))) => *op,
+            Some(lexer::Token::Keyword(k)) if k == "typeof" => "typeof",
+            Some(lexer::Token::Operator(op @ ("++" | "--"))) => {
+                let operator = op.to_string();
+                self.next_token(); // consume the operator
+                let argument = self.nested(Self::parse_unary_expression)?;
+                return self.update_expression(operator, true, argument);
+            }
+            _ => return self.parse_postfix_expression(),
         };
-        match t {
-            lexer::Token::Punctuator('(') => {
-                self.lexer.next(); // consume '('
-                let args = self.parse_arguments();
-                Node::new_new_expression(callee, args)
+        self.next_token(); // consume the operator
+        let argument = self.nested(Self::parse_unary_expression)?;
+        Ok(Node::new_unary_expression(operator, argument))
+    }
+
+    /// 後置の ++ と --。例: i++
+    fn parse_postfix_expression(&mut self) -> ParseResult<Node> {
+        let argument = self.parse_left_hand_side_expression()?;
+        // 改行の後の ++ と -- は次の文の前置演算子
+        if self.newline_before_next() {
+            return Ok(argument);
+        }
+        match self.peek_token() {
+            Some(lexer::Token::Operator(op @ ("++" | "--"))) => {
+                let operator = op.to_string();
+                self.next_token(); // consume the operator
+                self.update_expression(operator, false, argument)
+            }
+            _ => Ok(argument),
+        }
+    }
+
+    /// ++ と -- の対象は変数かプロパティに限る
+    fn update_expression(
+        &self,
+        operator: String,
+        prefix: bool,
+        argument: Node,
+    ) -> ParseResult<Node> {
+        match argument {
+            Node::Identifier(_) | Node::MemberExpression { .. } => Ok(Node::UpdateExpression {
+                operator,
+                prefix,
+                argument: Box::new(argument),
+            }),
+            _ if prefix => Err(self.error("Invalid left-hand side expression in prefix operation")),
+            _ => Err(self.error("Invalid left-hand side expression in postfix operation")),
+        }
+    }
+
+    /// 優先順位が min_precedence 以上の二項演算子をまとめる (precedence climbing)
+    fn parse_binary_expression(&mut self, min_precedence: u8) -> ParseResult<Node> {
+        let starts_with_unary = matches!(
+            self.peek_token(),
+            Some(lexer::Token::Operator("!" | "-" | "+"))
+        ) || matches!(self.peek_token(), Some(lexer::Token::Keyword(k)) if k == "typeof");
+        let mut left = self.nested(Self::parse_unary_expression)?;
+        // ?? は括弧なしで || や && と混ぜられない
+        let (mut coalesces, mut short_circuits) = (false, false);
+        while let Some(lexer::Token::Operator(operator)) = self.peek_token() {
+            let operator = *operator;
+            let Some(precedence) = binary_precedence(operator) else {
+                break;
+            };
+            if precedence < min_precedence {
+                break;
+            }
+            match operator {
+                "??" if short_circuits => return Err(self.unexpected_next()),
+                "||" | "&&" if coalesces => return Err(self.unexpected_next()),
+                "??" => coalesces = true,
+                "||" | "&&" => short_circuits = true,
+                _ => {}
             }
-            _ => panic!("Expected '(' after callee in new expression"),
+            self.next_token(); // consume the operator
+            // -2 ** 2 は曖昧なので括弧が必要
+            if operator == "**" && starts_with_unary && matches!(left, Node::UnaryExpression { .. })
+            {
+                return Err(self.error(
+                    "Unary operator used immediately before exponentiation expression. Parenthesis must be used to disambiguate operator precedence",
+                ));
+            }
+            // ** だけは右結合
+            // ?? の右辺は || と && を含まないので、続く || や && はこのループで検出する
+            let right = match operator {
+                "**" => self.parse_binary_expression(precedence)?,
+                "??" => self.parse_binary_expression(binary_precedence("&&").unwrap() + 1)?,
+                _ => self.parse_binary_expression(precedence + 1)?,
+            };
+            left = Node::new_binary_expression(operator, left, right);
+        }
+        Ok(left)
+    }
+
+    /// Reference: https://262.ecma-international.org/#sec-conditional-operator
+    fn parse_conditional_expression(&mut self) -> ParseResult<Node> {
+        let test = self.parse_binary_expression(0)?;
+        if let Some(lexer::Token::Punctuator('?')) = self.peek_token() {
+            self.next_token(); // consume '?'
+            let consequent = self.parse_assignment_expression()?;
+            self.expect_punctuator(':')?;
+            let alternate = self.parse_assignment_expression()?;
+            return Ok(Node::ConditionalExpression {
+                test: Box::new(test),
+                consequent: Box::new(consequent),
+                alternate: Box::new(alternate),
+            });
         }
+        Ok(test)
     }
 
-    fn parse_left_hand_side_expression(&mut self) -> Option<Node> {
-        let t = match self.lexer.peek() {
-            Some(token) => token,
-            None => return None,
+    /// = と複合代入 (+= など)。右結合
+    /// Reference: https://262.ecma-international.org/#sec-assignment-operators
+    pub fn parse_assignment_expression(&mut self) -> ParseResult<Node> {
+        let expr = self.parse_conditional_expression()?;
+        let operator = match self.peek_token() {
+            Some(lexer::Token::Punctuator('=')) => "=",
+            Some(lexer::Token::Operator(op)) if COMPOUND_ASSIGNMENT_OPERATORS.contains(op) => *op,
+            _ => return Ok(expr),
         };
-        match t {
-            lexer::Token::Keyword(k) => match k.as_str() {
-                "new" => {
-                    self.lexer.next(); // consume 'new'
-                    return Some(self.parse_new_expression());
+        self.next_token(); // consume the operator
+        if !matches!(expr, Node::Identifier(_) | Node::MemberExpression { .. }) {
+            return Err(self.error("Invalid left-hand side in assignment"));
+        }
+        Ok(Node::new_assignment_expression(
+            operator.to_string(),
+            expr,
+            self.parse_assignment_expression()?,
+        ))
+    }
+
+    fn parse_identifier(&mut self) -> ParseResult<Node> {
+        match self.next_token() {
+            Some(lexer::Token::Identifier(name)) => Ok(Node::new_identifier(name)),
+            t => Err(self.unexpected(t)),
+        }
+    }
+
+    pub fn parse_initializer(&mut self, kind: &str) -> ParseResult<Option<Node>> {
+        match self.peek_token() {
+            Some(lexer::Token::Punctuator('=')) => {
+                self.next_token(); // consume '='
+                Ok(Some(self.parse_assignment_expression()?))
+            }
+            // const には初期化子が必要
+            _ if kind == "const" => {
+                self.next_token();
+                Err(self.error("Missing initializer in const declaration"))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// const / let / var の後の宣言の並び。例: let a = 1, b
+    pub fn parse_variable_declaration(&mut self, kind: &str) -> ParseResult<Node> {
+        let id = self.parse_identifier()?;
+        self.parse_variable_declaration_from(kind, id)
+    }
+
+    /// 最初の名前を読んだ後の宣言の並び
+    fn parse_variable_declaration_from(&mut self, kind: &str, id: Node) -> ParseResult<Node> {
+        let mut declarations = Vec::new();
+        let mut id = id;
+        loop {
+            let init = self.parse_initializer(kind)?;
+            declarations.push(Node::new_variable_declarator(id, init));
+            match self.peek_token() {
+                Some(lexer::Token::Punctuator(',')) => {
+                    self.next_token(); // consume ','
+                    id = self.parse_identifier()?;
                 }
-                _ => panic!("Unexpected keyword: {}", k),
-            },
-            lexer::Token::Identifier(_) => self.parse_call_expression(),
-            _ => self.parse_left_hand_side_expression(),
+                _ => break,
+            }
         }
+        Ok(Node::VariableDeclaration {
+            kind: kind.to_string(),
+            declarations,
+        })
     }
 
-    pub fn parse_assignment_expression(&mut self) -> Option<Node> {
-        let expr = self.parse_left_hand_side_expression();
-        let t = match self.lexer.peek() {
-            Some(token) => token,
-            None => return expr,
-        };
-        match t {
-            lexer::Token::Punctuator('=') => {
-                self.lexer.next();
-                // TODO: unwrapをなんとかする
-                Some(Node::new_assignment_expression(
-                    "=".to_string(),
-                    expr.unwrap(),
-                    self.parse_assignment_expression().unwrap(),
+    /// if や while の本体になる文。宣言は書けない
+    fn parse_substatement(&mut self) -> ParseResult<Node> {
+        match self.peek_token() {
+            Some(lexer::Token::Keyword(k)) if k == "let" || k == "const" => {
+                self.next_token();
+                Err(self.error("Lexical declaration cannot appear in a single-statement context"))
+            }
+            Some(lexer::Token::Keyword(k)) if k == "function" => {
+                self.next_token();
+                Err(self.error(
+                    "In strict mode code, functions can only be declared at top level or inside a block.",
                 ))
             }
-            _ => expr,
+            _ => match self.nested(Self::parse_statement)? {
+                Some(statement) => Ok(statement),
+                None => Err(self.unexpected_next()),
+            },
         }
     }
 
-    fn parse_identifier(&mut self) -> Option<Node> {
-        let t = match self.lexer.next() {
-            Some(token) => token,
-            None => return None,
-        };
-        match t {
-            lexer::Token::Identifier(name) => Some(Node::new_identifier(name)),
+    /// if や while の ( 条件 )
+    fn parse_condition(&mut self) -> ParseResult<Node> {
+        self.expect_punctuator('(')?;
+        let test = self.parse_assignment_expression()?;
+        self.expect_punctuator(')')?;
+        Ok(test)
+    }
+
+    /// ループの本体。中では break と continue を書ける
+    fn parse_loop_body(&mut self) -> ParseResult<Node> {
+        self.jump_targets.loops += 1;
+        let body = self.parse_substatement();
+        self.jump_targets.loops -= 1;
+        body
+    }
+
+    fn parse_if_statement(&mut self) -> ParseResult<Node> {
+        let test = self.parse_condition()?;
+        let consequent = self.parse_substatement()?;
+        let alternate = match self.peek_token() {
+            Some(lexer::Token::Keyword(k)) if k == "else" => {
+                self.next_token(); // consume 'else'
+                Some(Box::new(self.parse_substatement()?))
+            }
             _ => None,
+        };
+        Ok(Node::IfStatement {
+            test: Box::new(test),
+            consequent: Box::new(consequent),
+            alternate,
+        })
+    }
+
+    fn parse_while_statement(&mut self) -> ParseResult<Node> {
+        let test = self.parse_condition()?;
+        let body = self.parse_loop_body()?;
+        Ok(Node::WhileStatement {
+            test: Box::new(test),
+            body: Box::new(body),
+        })
+    }
+
+    fn parse_do_while_statement(&mut self) -> ParseResult<Node> {
+        let body = self.parse_loop_body()?;
+        match self.next_token() {
+            Some(lexer::Token::Keyword(k)) if k == "while" => {}
+            t => return Err(self.unexpected(t)),
+        }
+        let test = self.parse_condition()?;
+        Ok(Node::DoWhileStatement {
+            body: Box::new(body),
+            test: Box::new(test),
+        })
+    }
+
+    /// for の後を読む。( の中の最初の部分の後に of か in が続けば for...of か for...in
+    /// Reference: https://262.ecma-international.org/#sec-for-statement
+    fn parse_for_statement(&mut self) -> ParseResult<Node> {
+        self.expect_punctuator('(')?;
+        let init = match self.peek_token().cloned() {
+            Some(lexer::Token::Punctuator(';')) => None,
+            Some(lexer::Token::Keyword(kind))
+                if matches!(kind.as_str(), "const" | "let" | "var") =>
+            {
+                self.next_token(); // consume the keyword
+                let id = self.parse_identifier()?;
+                if self.at_for_in_of() {
+                    let declaration = Node::VariableDeclaration {
+                        kind,
+                        declarations: vec![Node::new_variable_declarator(id, None)],
+                    };
+                    return self.parse_for_in_of(declaration);
+                }
+                Some(self.parse_variable_declaration_from(&kind, id)?)
+            }
+            _ => {
+                let expression = self.parse_assignment_expression()?;
+                if self.at_for_in_of() {
+                    return match expression {
+                        Node::Identifier(_) | Node::MemberExpression { .. } => {
+                            self.parse_for_in_of(expression)
+                        }
+                        _ => Err(self.error("Invalid left-hand side in for-loop")),
+                    };
+                }
+                Some(expression)
+            }
+        };
+        self.expect_punctuator(';')?;
+        let test = self.parse_optional_expression(';')?;
+        let update = self.parse_optional_expression(')')?;
+        let body = self.parse_loop_body()?;
+        Ok(Node::ForStatement {
+            init: init.map(Box::new),
+            test: test.map(Box::new),
+            update: update.map(Box::new),
+            body: Box::new(body),
+        })
+    }
+
+    fn at_for_in_of(&mut self) -> bool {
+        match self.peek_token() {
+            Some(lexer::Token::Identifier(k)) => k == "of",
+            Some(lexer::Token::Keyword(k)) => k == "in",
+            _ => false,
         }
     }
 
-    pub fn parse_initializer(&mut self) -> Option<Node> {
-        let t = match self.lexer.next() {
-            Some(token) => token,
-            None => return None,
+    /// of か in の後の式と本体
+    fn parse_for_in_of(&mut self, left: Node) -> ParseResult<Node> {
+        let is_of = matches!(self.next_token(), Some(lexer::Token::Identifier(k)) if k == "of");
+        let right = Box::new(self.parse_assignment_expression()?);
+        self.expect_punctuator(')')?;
+        let body = Box::new(self.parse_loop_body()?);
+        let left = Box::new(left);
+        Ok(if is_of {
+            Node::ForOfStatement { left, right, body }
+        } else {
+            Node::ForInStatement { left, right, body }
+        })
+    }
+
+    /// 省略できる式と、その後の end。例: for (;;) の条件と更新
+    fn parse_optional_expression(&mut self, end: char) -> ParseResult<Option<Node>> {
+        let expression = match self.peek_token() {
+            Some(lexer::Token::Punctuator(c)) if *c == end => None,
+            _ => Some(self.parse_assignment_expression()?),
         };
-        match t {
-            lexer::Token::Punctuator(c) => match c {
-                '=' => self.parse_assignment_expression(),
-                _ => None,
+        self.expect_punctuator(end)?;
+        Ok(expression)
+    }
+
+    fn parse_switch_statement(&mut self) -> ParseResult<Node> {
+        let discriminant = self.parse_condition()?;
+        self.expect_punctuator('{')?;
+        self.jump_targets.switches += 1;
+        let cases = self.parse_switch_cases();
+        self.jump_targets.switches -= 1;
+        Ok(Node::SwitchStatement {
+            discriminant: Box::new(discriminant),
+            cases: cases?,
+        })
+    }
+
+    /// '}' までの case 節と default 節。default は1つまで
+    fn parse_switch_cases(&mut self) -> ParseResult<Vec<Node>> {
+        let mut cases = Vec::new();
+        let mut has_default = false;
+        loop {
+            let test = match self.next_token() {
+                Some(lexer::Token::Punctuator('}')) => return Ok(cases),
+                Some(lexer::Token::Keyword(k)) if k == "case" => {
+                    Some(Box::new(self.parse_assignment_expression()?))
+                }
+                Some(lexer::Token::Keyword(k)) if k == "default" => {
+                    if has_default {
+                        return Err(self.error("More than one default clause in switch statement"));
+                    }
+                    has_default = true;
+                    None
+                }
+                t => return Err(self.unexpected(t)),
+            };
+            self.expect_punctuator(':')?;
+            let mut consequent = Vec::new();
+            loop {
+                match self.peek_token() {
+                    Some(lexer::Token::Punctuator('}')) => break,
+                    Some(lexer::Token::Keyword(k)) if k == "case" || k == "default" => break,
+                    _ => match self.nested(Self::parse_statement)? {
+                        Some(statement) => consequent.push(statement),
+                        None => return Err(self.unexpected_next()),
+                    },
+                }
+            }
+            cases.push(Node::SwitchCase { test, consequent });
+        }
+    }
+
+    /// break か continue の後の省略できるラベル。飛び先がなければ構文エラー
+    fn parse_jump_statement(&mut self, keyword: &str) -> ParseResult<Node> {
+        // 改行の後の名前はラベルではなく次の文
+        let newline = self.newline_before_next();
+        let label = match self.peek_token() {
+            Some(lexer::Token::Identifier(_)) if newline => None,
+            Some(lexer::Token::Identifier(_)) => match self.parse_identifier()? {
+                Node::Identifier(name) => Some(name),
+                _ => unreachable!("parse_identifier returns an Identifier"),
             },
             _ => None,
+        };
+        let targets = &self.jump_targets;
+        let is_continue = keyword == "continue";
+        let error = match &label {
+            Some(label) => match targets.labels.iter().find(|(name, _)| name == label) {
+                None => Some(format!("Undefined label '{}'", label)),
+                Some((_, false)) if is_continue => Some(format!(
+                    "Illegal continue statement: '{}' does not denote an iteration statement",
+                    label
+                )),
+                Some(_) => None,
+            },
+            None if is_continue && targets.loops == 0 => {
+                Some("Illegal continue statement: no surrounding iteration statement".to_string())
+            }
+            None if targets.loops + targets.switches == 0 => {
+                Some("Illegal break statement".to_string())
+            }
+            None => None,
+        };
+        if let Some(message) = error {
+            return Err(self.error(&message));
+        }
+        Ok(if is_continue {
+            Node::ContinueStatement { label }
+        } else {
+            Node::BreakStatement { label }
+        })
+    }
+
+    /// ラベルの後の文。ループに付いたラベルだけが continue の飛び先になる
+    fn parse_labeled_statement(&mut self, label: String) -> ParseResult<Node> {
+        if self
+            .jump_targets
+            .labels
+            .iter()
+            .any(|(name, _)| *name == label)
+        {
+            return Err(self.error(&format!("Label '{}' has already been declared", label)));
         }
+        let is_loop = matches!(
+            self.peek_token(),
+            Some(lexer::Token::Keyword(k)) if matches!(k.as_str(), "for" | "while" | "do")
+        );
+        self.jump_targets.labels.push((label.clone(), is_loop));
+        let body = self.parse_substatement();
+        self.jump_targets.labels.pop();
+        Ok(Node::LabeledStatement {
+            label,
+            body: Box::new(body?),
+        })
     }
 
-    pub fn parse_variable_declaration(&mut self) -> Node {
-        // TODO: unwrapをなんとかする
-        let id = self.parse_identifier().unwrap();
-        // TODO: unwrapをなんとかする
-        let init = self.parse_initializer().unwrap();
-        Node::new_variable_declaration(id, init)
+    /// try ブロックの後に catch 節と finally 節の少なくとも一方が続く
+    fn parse_try_statement(&mut self) -> ParseResult<Node> {
+        let block = self.parse_block()?;
+        let mut param = None;
+        let mut handler = None;
+        if let Some(lexer::Token::Keyword(k)) = self.peek_token()
+            && k == "catch"
+        {
+            self.next_token(); // consume 'catch'
+            // catch (e) の (e) は省略できる
+            if let Some(lexer::Token::Punctuator('(')) = self.peek_token() {
+                self.next_token(); // consume '('
+                match self.next_token() {
+                    Some(lexer::Token::Identifier(name)) => param = Some(name),
+                    t => return Err(self.unexpected(t)),
+                }
+                self.expect_punctuator(')')?;
+            }
+            handler = Some(Box::new(self.parse_block()?));
+        }
+        let mut finalizer = None;
+        if let Some(lexer::Token::Keyword(k)) = self.peek_token()
+            && k == "finally"
+        {
+            self.next_token(); // consume 'finally'
+            finalizer = Some(Box::new(self.parse_block()?));
+        }
+        if handler.is_none() && finalizer.is_none() {
+            self.next_token();
+            return Err(self.error("Missing catch or finally after try"));
+        }
+        Ok(Node::TryStatement {
+            block: Box::new(block),
+            param,
+            handler,
+            finalizer,
+        })
     }
 
-    pub fn parse_statement(&mut self) -> Option<Node> {
-        let t = match self.lexer.peek() {
-            Some(t) => t,
-            None => return None,
+    /// 文を1つ読む。入力の終わりでは None
+    pub fn parse_statement(&mut self) -> ParseResult<Option<Node>> {
+        let Some(t) = self.peek_token().cloned() else {
+            return Ok(None);
         };
         let node = match t {
             lexer::Token::Keyword(k) => match k.as_str() {
-                "const" => {
-                    self.lexer.next(); // consume 'const'
-                    Some(self.parse_variable_declaration())
+                "const" | "let" | "var" => {
+                    self.next_token(); // consume the keyword
+                    self.parse_variable_declaration(&k)?
+                }
+                "return" if self.function_depth > 0 => {
+                    self.next_token(); // consume 'return'
+                    // return の直後の改行では値を取らない
+                    let newline = self.newline_before_next();
+                    let argument = match self.peek_token() {
+                        Some(lexer::Token::Punctuator(';' | '}')) | None => None,
+                        _ if newline => None,
+                        _ => Some(self.parse_assignment_expression()?),
+                    };
+                    Node::new_return_statement(argument)
+                }
+                "return" => {
+                    self.next_token(); // consume 'return'
+                    return Err(self.error("Illegal return statement"));
                 }
-                _ => Some(Node::new_string_literal("".to_string())), // ダミー
+                "throw" => {
+                    self.next_token(); // consume 'throw'
+                    if self.newline_before_next() {
+                        return Err(self.error("Illegal newline after throw"));
+                    }
+                    Node::new_throw_statement(self.parse_assignment_expression()?)
+                }
+                "try" => {
+                    self.next_token(); // consume 'try'
+                    self.parse_try_statement()?
+                }
+                // 式で始まる文
+                "new" | "typeof" | "true" | "false" | "null" => {
+                    Node::new_expression_statement(self.parse_assignment_expression()?)
+                }
+                "function" => {
+                    self.next_token(); // consume 'function'
+                    self.parse_function(true)?
+                }
+                "if" => {
+                    self.next_token(); // consume 'if'
+                    self.parse_if_statement()?
+                }
+                "while" => {
+                    self.next_token(); // consume 'while'
+                    self.parse_while_statement()?
+                }
+                "do" => {
+                    self.next_token(); // consume 'do'
+                    self.parse_do_while_statement()?
+                }
+                "for" => {
+                    self.next_token(); // consume 'for'
+                    self.parse_for_statement()?
+                }
+                "switch" => {
+                    self.next_token(); // consume 'switch'
+                    self.parse_switch_statement()?
+                }
+                "break" | "continue" => {
+                    self.next_token(); // consume the keyword
+                    self.parse_jump_statement(&k)?
+                }
+                _ => return Err(self.unexpected_next()),
+            },
+            // 文の先頭の { はオブジェクトではなくブロック
+            lexer::Token::Punctuator('{') => self.parse_block()?,
+            // 空文。後の ';' は下で読む
+            lexer::Token::Punctuator(';') => Node::EmptyStatement,
+            _ => match self.parse_assignment_expression()? {
+                // 名前の後に ':' が続けばラベル。例: outer: for (...) {}
+                Node::Identifier(label)
+                    if matches!(self.peek_token(), Some(lexer::Token::Punctuator(':'))) =>
+                {
+                    self.next_token(); // consume ':'
+                    self.parse_labeled_statement(label)?
+                }
+                expression => Node::new_expression_statement(expression),
             },
-            _ => Some(Node::new_expression_statement(
-                self.parse_assignment_expression().unwrap(),
-            )),
         };
-        if let Some(lexer::Token::Punctuator(c)) = self.lexer.peek() {
-            // ';'を消費する
-            if c == &';' {
-                self.lexer.next();
+        // 自動セミコロン挿入: ; で終わらない文は、次が } か入力の終わりか、改行の後でなければならない
+        // Reference: https://262.ecma-international.org/#sec-rules-of-automatic-semicolon-insertion
+        let needs_semicolon = matches!(
+            node,
+            Node::ExpressionStatement { .. }
+                | Node::VariableDeclaration { .. }
+                | Node::ReturnStatement { .. }
+                | Node::ThrowStatement { .. }
+                | Node::BreakStatement { .. }
+                | Node::ContinueStatement { .. }
+        );
+        let newline = self.newline_before_next();
+        match self.peek_token() {
+            Some(lexer::Token::Punctuator(';')) => {
+                self.next_token(); // consume ';'
+            }
+            Some(lexer::Token::Punctuator('}')) | None => {}
+            Some(_) if needs_semicolon && !newline => {
+                return Err(self.unexpected_next());
             }
+            Some(_) => {}
         }
-        node
+        Ok(Some(node))
     }
 
-    pub fn parse(&mut self) -> Program {
+    pub fn parse(&mut self) -> ParseResult<Program> {
         let mut program = Program::new();
         let mut body = Vec::new();
-        loop {
-            let node = self.parse_statement();
+        while let Some(node) = self.parse_statement()? {
+            body.push(node);
+        }
+        program.set_body(body);
+        Ok(program)
+    }
+}
 
-            match node {
-                Some(n) => body.push(n),
-                None => {
-                    program.set_body(body);
-                    return program;
-                }
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn syntax_error(src: &str) -> String {
+        Parser::new(src).parse().unwrap_err().message
+    }
+
+    #[test]
+    fn test_statements_must_be_separated() {
+        assert_eq!(syntax_error("1 2;"), "Unexpected number");
+        assert_eq!(syntax_error(r#""a" "b";"#), "Unexpected string");
+        assert_eq!(syntax_error("if (1) 2 3;"), "Unexpected number");
+        assert_eq!(
+            syntax_error("e instanceof Error"),
+            "Unexpected identifier 'instanceof'"
+        );
+        assert_eq!(
+            syntax_error("throw\nnew Error();"),
+            "Illegal newline after throw"
+        );
+        // 改行、}、入力の終わりの前ではセミコロンを省略できる
+        for src in [
+            "1\n2",
+            "{ 1 }",
+            "let a = 1",
+            "if (1) {} 2",
+            "do {} while (0) 1",
+        ] {
+            assert!(Parser::new(src).parse().is_ok(), "{}", src);
+        }
+    }
+
+    #[test]
+    fn test_restricted_productions() {
+        // return の後の改行では値を取らない
+        let program = Parser::new("function f() { return\n1; }").parse().unwrap();
+        let Node::FunctionDeclaration { body, .. } = &program.body[0] else {
+            panic!("expected a function declaration");
+        };
+        let Node::BlockStatement { body } = body.as_ref() else {
+            panic!("expected a block");
+        };
+        assert_eq!(body[0], Node::new_return_statement(None));
+        // 改行の後の ++ は次の文の前置演算子
+        let program = Parser::new("a\n++b").parse().unwrap();
+        assert_eq!(program.body.len(), 2);
+    }
+
+    #[test]
+    fn test_coalesce_cannot_mix_with_logical_operators() {
+        assert_eq!(syntax_error("a || b ?? c;"), "Unexpected token '??'");
+        assert_eq!(syntax_error("a ?? b && c;"), "Unexpected token '&&'");
+        assert_eq!(syntax_error("a && b || c ?? d;"), "Unexpected token '??'");
+        for src in [
+            "(a || b) ?? c;",
+            "a ?? (b && c);",
+            "a ?? b ?? c;",
+            "a ?? b == c;",
+        ] {
+            assert!(Parser::new(src).parse().is_ok(), "{}", src);
+        }
+    }
+
+    #[test]
+    fn test_array_elements_must_be_separated() {
+        assert_eq!(syntax_error("[1 2];"), "Unexpected number");
+        let program = Parser::new("[1, , 3];").parse().unwrap();
+        let Node::ExpressionStatement { expression } = &program.body[0] else {
+            panic!("expected an expression statement");
+        };
+        let Node::ArrayExpression { elements } = expression.as_ref() else {
+            panic!("expected an array expression");
+        };
+        assert_eq!(elements.len(), 3);
+        assert_eq!(elements[1], None);
+    }
+
+    #[test]
+    fn test_arguments_must_be_separated() {
+        assert_eq!(syntax_error("foo(1 2);"), "Unexpected number");
+        assert_eq!(syntax_error("foo(1, , 2);"), "Unexpected token ','");
+        assert_eq!(
+            syntax_error("new Intl.Locale(\"en\" \"fr\");"),
+            "Unexpected string"
+        );
+        for src in ["foo();", "foo(1, 2);", "foo(1, 2,);"] {
+            assert!(Parser::new(src).parse().is_ok(), "{}", src);
         }
     }
+
+    #[test]
+    fn test_nesting_depth_limit() {
+        // 上限までの入れ子はスクリプトと同じく大きなスタックのスレッドで読む
+        let parse = |src: String| {
+            std::thread::Builder::new()
+                .stack_size(crate::runtime::STACK_SIZE)
+                .spawn(move || Parser::new(&src).parse().map(|_| ()).map_err(|e| e.message))
+                .unwrap()
+                .join()
+                .unwrap()
+        };
+        let nested = |open: &str, inner: &str, close: &str, depth: usize| {
+            format!("{}{}{};", open.repeat(depth), inner, close.repeat(depth))
+        };
+        assert_eq!(parse(nested("(", "1", ")", 500)), Ok(()));
+        assert_eq!(parse(nested("{", "", "}", 500)), Ok(()));
+        let too_deep = Err("Too deeply nested".to_string());
+        assert_eq!(parse(nested("(", "1", ")", 100_000)), too_deep);
+        assert_eq!(parse(nested("[", "", "]", 50_000)), too_deep);
+        assert_eq!(parse(nested("{", "", "}", 50_000)), too_deep);
+        assert_eq!(parse(nested("-", "1", "", 50_000)), too_deep);
+        assert_eq!(parse(nested("if (1) ", "1", "", 50_000)), too_deep);
+        assert_eq!(parse(nested("`${", "1", "}`", 5_000)), too_deep);
+    }
 }
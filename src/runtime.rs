@@ -4,50 +4,499 @@ use std::cell::RefCell;
 use std::collections::HashMap;
 use std::rc::Rc;
 
+use core::cmp::Ordering;
 use core::fmt::Display;
 use core::fmt::Formatter;
 use core::ops::Add;
 //
 
-use crate::printer::{FormatValue, MF2Printer};
-use mf2_parser::parser::Mf2Parser;
+use crate::catalog::Catalog;
+use crate::compiled::{
+    BidiIsolation, CompiledMessage, Direction, FormatError, FormatErrorKind, FormatOptions,
+    FormatValue,
+};
+use crate::console;
+use crate::functions::{FunctionError, FunctionRegistry, MessageValue, NumberValue};
+use crate::locale::{Locale, LocaleMatcher, canonicalize_locale_list, resolve_locale};
+use crate::numeric::{BigInt, number_to_string, string_to_number};
+use crate::object::{Object, Shared, array_index};
 
-#[derive(Debug, Clone, PartialEq)]
+/// スクリプトを実行するスレッドのスタックの大きさ
+pub const STACK_SIZE: usize = 64 * 1024 * 1024;
+
+/// 評価器は再帰で動くので、スタックの残りがこれを下回ったら溢れる前に RangeError を投げる。
+/// 残りは組み込み関数の処理と、例外が捕捉されるまでの後始末に使う
+const STACK_RESERVE: usize = 1024 * 1024;
+
+/// 配列の長さの上限。要素は穴も含めて密に持つので、これより長くする代入は RangeError にする
+const MAX_ARRAY_LENGTH: usize = 1 << 24;
+
+#[derive(Debug)]
 pub struct MessageFormatInstance {
-    locale: String,
-    message: String,
+    /// 交渉済みのロケールと双方向テキストの設定
+    options: FormatOptions,
+    /// 構築時にコンパイル済みのメッセージ。format() のたびに解析し直さない
+    message: CompiledMessage,
+    /// エラーの位置を示すためのメッセージのソース
+    source: String,
+    /// options.functions で渡されたカスタム関数
+    functions: HashMap<String, Function>,
 }
 
 impl MessageFormatInstance {
-    fn new(locale: String, message: String) -> Self {
-        Self { locale, message }
+    pub fn locale(&self) -> &Locale {
+        &self.options.locale
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn new(
+        options: FormatOptions,
+        source: &str,
+        message: CompiledMessage,
+        functions: HashMap<String, Function>,
+    ) -> Self {
+        Self {
+            options,
+            message,
+            source: source.into(),
+            functions,
+        }
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct MessageFormatMethod {
-    instance: MessageFormatInstance,
-    method: String,
+/// 組み込み関数の本体。(runtime, this, 引数) で呼び出す
+type NativeFn = fn(&mut Runtime, RuntimeValue, Vec<RuntimeValue>) -> EvalResult<RuntimeValue>;
+
+/// Rust で実装した組み込み関数。construct があるものは new で呼び出せる
+/// Reference: https://262.ecma-international.org/#sec-built-in-function-objects
+pub struct NativeFunction {
+    /// エラーメッセージに使う、グローバルオブジェクトからの名前。例: Intl.MessageFormat
+    name: &'static str,
+    call: NativeFn,
+    construct: Option<NativeFn>,
 }
 
-impl MessageFormatMethod {
-    fn new(instance: MessageFormatInstance, method: String) -> Self {
-        Self { instance, method }
+impl NativeFunction {
+    /// 関数自身の名前。例: Intl.MessageFormat は MessageFormat
+    pub fn name(&self) -> &str {
+        self.name.rsplit('.').next().unwrap_or_default()
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+impl core::fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        f.debug_struct("NativeFunction")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+fn native_function(name: &'static str, call: NativeFn) -> RuntimeValue {
+    RuntimeValue::NativeFunction(Rc::new(NativeFunction {
+        name,
+        call,
+        construct: None,
+    }))
+}
+
+fn native_constructor(name: &'static str, call: NativeFn, construct: NativeFn) -> RuntimeValue {
+    RuntimeValue::NativeFunction(Rc::new(NativeFunction {
+        name,
+        call,
+        construct: Some(construct),
+    }))
+}
+
+/// アロー関数のクロージャ。定義された環境を保持する
+/// Reference: https://262.ecma-international.org/#sec-ecmascript-function-objects
+#[derive(Clone)]
+pub struct Function {
+    /// 宣言や変数の名前。無名関数は None
+    name: Option<String>,
+    /// Identifier、AssignmentPattern、RestElement のいずれか
+    params: Vec<Node>,
+    body: Rc<Node>,
+    env: Rc<RefCell<Environment>>,
+}
+
+// 環境は関数自身を含みうるので、中身ではなく同一性で比較する
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.body, &other.body) && Rc::ptr_eq(&self.env, &other.env)
+    }
+}
+
+impl Function {
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+}
+
+impl core::fmt::Debug for Function {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        f.debug_struct("Function")
+            .field("params", &self.params)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Clone)]
 pub enum RuntimeValue {
-    /// https://262.ecma-international.org/#sec-numeric-types
-    Number(u64),
+    /// https://262.ecma-international.org/#sec-ecmascript-language-types-undefined-type
+    Undefined,
+    /// https://262.ecma-international.org/#sec-ecmascript-language-types-null-type
+    Null,
+    /// https://262.ecma-international.org/#sec-ecmascript-language-types-number-type
+    Number(f64),
+    /// https://262.ecma-international.org/#sec-ecmascript-language-types-bigint-type
+    BigInt(BigInt),
+    /// https://262.ecma-international.org/#sec-ecmascript-language-types-boolean-type
+    Boolean(bool),
     /// https://262.ecma-international.org/#sec-ecmascript-language-types-string-type
     StringLiteral(String),
-    //
-    MessageFormatInstance(MessageFormatInstance),
-    //
-    MessageFormatMethod(MessageFormatMethod),
-    // Object type for JS objects
-    Object(HashMap<String, RuntimeValue>),
+    /// https://github.com/tc39/proposal-intl-messageformat#messageformat-objects
+    MessageFormatInstance(Rc<MessageFormatInstance>),
+    /// https://tc39.es/ecma402/#locale-objects
+    Locale(Rc<Locale>),
+    /// https://262.ecma-international.org/#sec-object-type
+    Object(Shared<Object>),
+    /// 要素の None は穴 ([1,,3] の 2 番目) で、undefined の要素とは区別する
+    /// https://262.ecma-international.org/#sec-array-objects
+    Array(Shared<Vec<Option<RuntimeValue>>>),
+    /// https://262.ecma-international.org/#sec-ecmascript-function-objects
+    Function(Function),
+    /// https://262.ecma-international.org/#sec-built-in-function-objects
+    NativeFunction(Rc<NativeFunction>),
+    /// name と message をプロパティに持つオブジェクト。オブジェクトと同じく参照で共有する
+    /// https://262.ecma-international.org/#sec-error-objects
+    Error(Shared<Object>),
+}
+
+// プリミティブは値で、オブジェクトにあたる値は同一性で比較する (=== と同じ)
+// Reference: https://262.ecma-international.org/#sec-isstrictlyequal
+impl PartialEq for RuntimeValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (RuntimeValue::Undefined, RuntimeValue::Undefined)
+            | (RuntimeValue::Null, RuntimeValue::Null) => true,
+            (RuntimeValue::Number(a), RuntimeValue::Number(b)) => a == b,
+            (RuntimeValue::BigInt(a), RuntimeValue::BigInt(b)) => a == b,
+            (RuntimeValue::Boolean(a), RuntimeValue::Boolean(b)) => a == b,
+            (RuntimeValue::StringLiteral(a), RuntimeValue::StringLiteral(b)) => a == b,
+            (RuntimeValue::MessageFormatInstance(a), RuntimeValue::MessageFormatInstance(b)) => {
+                Rc::ptr_eq(a, b)
+            }
+            (RuntimeValue::Locale(a), RuntimeValue::Locale(b)) => Rc::ptr_eq(a, b),
+            (RuntimeValue::Object(a), RuntimeValue::Object(b))
+            | (RuntimeValue::Error(a), RuntimeValue::Error(b)) => a == b,
+            (RuntimeValue::Array(a), RuntimeValue::Array(b)) => a == b,
+            (RuntimeValue::Function(a), RuntimeValue::Function(b)) => a == b,
+            (RuntimeValue::NativeFunction(a), RuntimeValue::NativeFunction(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+/// 正常でない完了。例外と return は呼び出し元へ、break と continue は囲んでいる文へ伝播する
+/// Reference: https://262.ecma-international.org/#sec-completion-record-specification-type
+#[derive(Debug, Clone, PartialEq)]
+pub enum Completion {
+    Throw(Box<RuntimeValue>),
+    Return(Box<RuntimeValue>),
+    /// ラベルがなければ一番内側のループか switch を抜ける
+    Break(Option<String>),
+    Continue(Option<String>),
+}
+
+type EvalResult<T> = Result<T, Completion>;
+
+/// ノードの種類ごとの評価メソッド
+type Evaluate =
+    fn(&mut Runtime, Node, Rc<RefCell<Environment>>) -> EvalResult<Option<RuntimeValue>>;
+
+/// 現在のスタックの位置。スタックは下位のアドレスへ伸びる
+fn stack_position() -> usize {
+    let marker = 0u8;
+    std::hint::black_box(&raw const marker).addr()
+}
+
+/// プロパティを並べた順に持つオブジェクトを作る
+fn new_object(properties: impl IntoIterator<Item = (String, RuntimeValue)>) -> RuntimeValue {
+    RuntimeValue::Object(Shared::new(Object::with_properties(properties)))
+}
+
+fn new_array(items: Vec<RuntimeValue>) -> RuntimeValue {
+    RuntimeValue::Array(Shared::new(items.into_iter().map(Some).collect()))
+}
+
+/// グローバルオブジェクト。組み込みのコンストラクタと Intl などの名前空間をプロパティに持つ。
+/// globalThis は自身を指すので、プログラムの終わりまで解放されない
+/// Reference: https://262.ecma-international.org/#sec-global-object
+fn global_object() -> Shared<Object> {
+    // Reference: https://tc39.es/ecma402/#intl-object
+    let intl = new_object([
+        (
+            "getCanonicalLocales".to_string(),
+            native_function("Intl.getCanonicalLocales", get_canonical_locales),
+        ),
+        (
+            "Locale".to_string(),
+            native_constructor(
+                "Intl.Locale",
+                |_, _, _| {
+                    Err(throw_error(
+                        "TypeError",
+                        "Constructor Intl.Locale requires 'new'",
+                    ))
+                },
+                construct_locale,
+            ),
+        ),
+        (
+            "MessageFormat".to_string(),
+            native_constructor(
+                "Intl.MessageFormat",
+                |_, _, _| {
+                    Err(throw_error(
+                        "TypeError",
+                        "Constructor Intl.MessageFormat requires 'new'",
+                    ))
+                },
+                Runtime::construct_message_format,
+            ),
+        ),
+    ]);
+    let catalog = new_object([(
+        "format".to_string(),
+        native_function("catalog.format", |runtime, _, args| {
+            runtime
+                .call_catalog_format(args)
+                .map(RuntimeValue::StringLiteral)
+        }),
+    )]);
+    // Error(message) は new Error(message) と同じ
+    // Reference: https://262.ecma-international.org/#sec-native-error-types-used-in-this-standard
+    let errors: [(&'static str, NativeFn); 5] = [
+        ("Error", |_, _, args| Ok(new_error("Error", args.first()))),
+        ("TypeError", |_, _, args| {
+            Ok(new_error("TypeError", args.first()))
+        }),
+        ("SyntaxError", |_, _, args| {
+            Ok(new_error("SyntaxError", args.first()))
+        }),
+        ("RangeError", |_, _, args| {
+            Ok(new_error("RangeError", args.first()))
+        }),
+        ("ReferenceError", |_, _, args| {
+            Ok(new_error("ReferenceError", args.first()))
+        }),
+    ];
+    let mut global = Object::with_properties(
+        errors.map(|(name, error)| (name.to_string(), native_constructor(name, error, error))),
+    );
+    // Reference: https://console.spec.whatwg.org/#console-namespace
+    let console = new_object([
+        (
+            "log".to_string(),
+            native_function("console.log", |_, _, args| {
+                println!("{}", console::format_args(&args));
+                Ok(undefined())
+            }),
+        ),
+        (
+            "error".to_string(),
+            native_function("console.error", |_, _, args| {
+                eprintln!("{}", console::format_args(&args));
+                Ok(undefined())
+            }),
+        ),
+        (
+            "warn".to_string(),
+            native_function("console.warn", |_, _, args| {
+                eprintln!("{}", console::format_args(&args));
+                Ok(undefined())
+            }),
+        ),
+        (
+            "table".to_string(),
+            native_function("console.table", |_, _, args| {
+                match args
+                    .first()
+                    .and_then(|data| console::table(data, args.get(1)))
+                {
+                    Some(table) => println!("{}", table),
+                    None => println!("{}", console::format_args(&args)),
+                }
+                Ok(undefined())
+            }),
+        ),
+    ]);
+    global.set("Intl".to_string(), intl);
+    global.set("catalog".to_string(), catalog);
+    global.set("console".to_string(), console);
+    let global = Shared::new(global);
+    global.borrow_mut().set(
+        "globalThis".to_string(),
+        RuntimeValue::Object(global.clone()),
+    );
+    global
+}
+
+/// 配列のメソッドを持つ Array.prototype
+/// Reference: https://262.ecma-international.org/#sec-properties-of-the-array-prototype-object
+fn array_prototype() -> Shared<Object> {
+    let methods: [(&'static str, NativeFn); 9] = [
+        ("push", |runtime, this, args| {
+            runtime.call_array_method("push", this, args)
+        }),
+        ("pop", |runtime, this, args| {
+            runtime.call_array_method("pop", this, args)
+        }),
+        ("map", |runtime, this, args| {
+            runtime.call_array_method("map", this, args)
+        }),
+        ("filter", |runtime, this, args| {
+            runtime.call_array_method("filter", this, args)
+        }),
+        ("forEach", |runtime, this, args| {
+            runtime.call_array_method("forEach", this, args)
+        }),
+        ("join", |runtime, this, args| {
+            runtime.call_array_method("join", this, args)
+        }),
+        ("slice", |runtime, this, args| {
+            runtime.call_array_method("slice", this, args)
+        }),
+        ("includes", |runtime, this, args| {
+            runtime.call_array_method("includes", this, args)
+        }),
+        ("reduce", |runtime, this, args| {
+            runtime.call_array_method("reduce", this, args)
+        }),
+    ];
+    Shared::new(Object::with_properties(methods.map(|(name, method)| {
+        (name.to_string(), native_function(name, method))
+    })))
+}
+
+/// format と resolvedOptions を持つ Intl.MessageFormat.prototype
+/// Reference: https://github.com/tc39/proposal-intl-messageformat#api
+fn message_format_prototype() -> Shared<Object> {
+    Shared::new(Object::with_properties([
+        (
+            "format".to_string(),
+            native_function("format", Runtime::message_format_format),
+        ),
+        (
+            "resolvedOptions".to_string(),
+            native_function("resolvedOptions", Runtime::message_format_resolved_options),
+        ),
+    ]))
+}
+
+/// Intl.getCanonicalLocales(locales)
+/// Reference: https://tc39.es/ecma402/#sec-intl.getcanonicallocales
+fn get_canonical_locales(
+    _: &mut Runtime,
+    _: RuntimeValue,
+    args: Vec<RuntimeValue>,
+) -> EvalResult<RuntimeValue> {
+    let tags = match args.first() {
+        Some(RuntimeValue::Array(items)) => items
+            .borrow()
+            .iter()
+            .flatten()
+            .map(|item| item.to_string())
+            .collect(),
+        None | Some(RuntimeValue::Undefined) => Vec::new(),
+        Some(value) => vec![value.to_string()],
+    };
+    let locales =
+        canonicalize_locale_list(&tags).map_err(|error| throw_error("RangeError", error))?;
+    Ok(new_array(
+        locales
+            .into_iter()
+            .map(RuntimeValue::StringLiteral)
+            .collect(),
+    ))
+}
+
+/// new Intl.Locale(tag)
+/// Reference: https://tc39.es/ecma402/#sec-Intl.Locale
+fn construct_locale(
+    _: &mut Runtime,
+    _: RuntimeValue,
+    args: Vec<RuntimeValue>,
+) -> EvalResult<RuntimeValue> {
+    match args.first() {
+        // Intl.Locale を渡しても新しいオブジェクトを作る
+        Some(RuntimeValue::Locale(locale)) => {
+            Ok(RuntimeValue::Locale(Rc::new(locale.as_ref().clone())))
+        }
+        Some(RuntimeValue::StringLiteral(tag)) => {
+            Ok(RuntimeValue::Locale(Rc::new(parse_locale(tag)?)))
+        }
+        _ => Err(throw_error(
+            "TypeError",
+            "First argument to Intl.Locale constructor can't be empty or missing",
+        )),
+    }
+}
+
+/// Intl.MessageFormat.prototype のメソッドの this。MessageFormat でなければ TypeError
+fn this_message_format(this: RuntimeValue, method: &str) -> EvalResult<Rc<MessageFormatInstance>> {
+    match this {
+        RuntimeValue::MessageFormatInstance(instance) => Ok(instance),
+        this => Err(throw_error(
+            "TypeError",
+            format!(
+                "Method Intl.MessageFormat.prototype.{} called on incompatible receiver {}",
+                method, this
+            ),
+        )),
+    }
+}
+
+/// 呼び出せる値か
+/// Reference: https://262.ecma-international.org/#sec-iscallable
+fn is_callable(value: &RuntimeValue) -> bool {
+    matches!(
+        value,
+        RuntimeValue::Function(_) | RuntimeValue::NativeFunction(_)
+    )
+}
+
+/// エラーメッセージに使う呼び出し先の表記。例: mf.format
+fn callee_text(node: &Node) -> Option<String> {
+    match node {
+        Node::Identifier(name) => Some(name.clone()),
+        Node::MemberExpression {
+            object,
+            property,
+            computed: false,
+        } => match property.as_ref() {
+            Node::Identifier(name) => Some(format!("{}.{}", callee_text(object)?, name)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// 組み込みのエラーオブジェクトを投げる
+fn throw_error(name: &str, message: impl Display) -> Completion {
+    Completion::Throw(Box::new(error_object(name, message.to_string())))
+}
+
+fn undefined() -> RuntimeValue {
+    RuntimeValue::Undefined
+}
+
+/// undefined か null。?? や ?. で値が無いとみなす
+fn is_nullish(value: &RuntimeValue) -> bool {
+    matches!(value, RuntimeValue::Undefined | RuntimeValue::Null)
 }
 
 impl Add<RuntimeValue> for RuntimeValue {
@@ -72,20 +521,459 @@ impl Add<RuntimeValue> for RuntimeValue {
 impl Display for RuntimeValue {
     fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
         let s = match self {
-            RuntimeValue::Number(value) => format!("{}", value),
+            RuntimeValue::Undefined => "undefined".to_string(),
+            RuntimeValue::Null => "null".to_string(),
+            RuntimeValue::Number(value) => number_to_string(*value),
+            RuntimeValue::BigInt(value) => value.to_string(),
+            RuntimeValue::Boolean(value) => value.to_string(),
             RuntimeValue::StringLiteral(value) => value.to_string(),
             RuntimeValue::MessageFormatInstance(_) => "[object Intl.MessageFormat]".to_string(),
-            RuntimeValue::MessageFormatMethod(_) => "[object Intl.MessageFormatMethod]".to_string(),
+            RuntimeValue::Locale(locale) => locale.to_string(),
             RuntimeValue::Object(_) => "[object Object]".to_string(),
+            RuntimeValue::Function(_) => "[Function]".to_string(),
+            RuntimeValue::NativeFunction(function) => {
+                format!("function {}() {{ [native code] }}", function.name())
+            }
+            // Error.prototype.toString
+            RuntimeValue::Error(object) => {
+                let object = object.borrow();
+                let property = |key| match object.get(key) {
+                    None | Some(RuntimeValue::Undefined) => None,
+                    Some(value) => Some(value.to_string()),
+                };
+                let name = property("name").unwrap_or_else(|| "Error".to_string());
+                match property("message").unwrap_or_default() {
+                    message if message.is_empty() => name,
+                    message if name.is_empty() => message,
+                    message => format!("{}: {}", name, message),
+                }
+            }
+            // Array.prototype.toString と同じく , で連結する
+            RuntimeValue::Array(items) => join_items(items, ","),
         };
         write!(f, "{}", s)
     }
 }
 
-impl FormatValue for RuntimeValue {}
+impl FormatValue for RuntimeValue {
+    fn to_message_value(&self) -> MessageValue {
+        match self {
+            RuntimeValue::Number(value) => MessageValue::Number(NumberValue::new(*value)),
+            RuntimeValue::BigInt(value) => MessageValue::Number(NumberValue::new(value.to_f64())),
+            _ => MessageValue::String(self.to_string()),
+        }
+    }
+}
+
+impl From<&MessageValue> for RuntimeValue {
+    fn from(value: &MessageValue) -> Self {
+        match value {
+            MessageValue::String(s) => RuntimeValue::StringLiteral(s.clone()),
+            MessageValue::Number(number) => RuntimeValue::Number(number.value),
+        }
+    }
+}
+
+/// ToPrimitive の組み込みの部分。オブジェクトは組み込みの toString の結果の文字列にする
+/// ユーザー定義の valueOf や toString は Runtime::primitive_value で呼ぶ
+/// Reference: https://262.ecma-international.org/#sec-toprimitive
+fn to_primitive(value: RuntimeValue) -> RuntimeValue {
+    if is_primitive(&value) {
+        value
+    } else {
+        RuntimeValue::StringLiteral(value.to_string())
+    }
+}
+
+/// オブジェクトや関数ではない値
+fn is_primitive(value: &RuntimeValue) -> bool {
+    matches!(
+        value,
+        RuntimeValue::Undefined
+            | RuntimeValue::Null
+            | RuntimeValue::Number(_)
+            | RuntimeValue::BigInt(_)
+            | RuntimeValue::Boolean(_)
+            | RuntimeValue::StringLiteral(_)
+    )
+}
+
+/// ToNumeric。結果は Number か BigInt
+/// Reference: https://262.ecma-international.org/#sec-tonumeric
+fn to_numeric(value: RuntimeValue) -> RuntimeValue {
+    match to_primitive(value) {
+        RuntimeValue::Undefined => RuntimeValue::Number(f64::NAN),
+        RuntimeValue::Null => RuntimeValue::Number(0.0),
+        RuntimeValue::Boolean(value) => RuntimeValue::Number(if value { 1.0 } else { 0.0 }),
+        RuntimeValue::StringLiteral(s) => RuntimeValue::Number(string_to_number(&s)),
+        numeric => numeric,
+    }
+}
+
+/// Reference: https://262.ecma-international.org/#sec-tonumber
+fn to_number(value: RuntimeValue) -> EvalResult<f64> {
+    match to_numeric(value) {
+        RuntimeValue::Number(value) => Ok(value),
+        _ => Err(throw_error(
+            "TypeError",
+            "Cannot convert a BigInt value to a number",
+        )),
+    }
+}
+
+/// Reference: https://262.ecma-international.org/#sec-toboolean
+fn to_boolean(value: &RuntimeValue) -> bool {
+    match value {
+        RuntimeValue::Undefined | RuntimeValue::Null => false,
+        RuntimeValue::Number(value) => *value != 0.0 && !value.is_nan(),
+        RuntimeValue::BigInt(value) => !value.is_zero(),
+        RuntimeValue::Boolean(value) => *value,
+        RuntimeValue::StringLiteral(value) => !value.is_empty(),
+        _ => true,
+    }
+}
+
+/// Reference: https://262.ecma-international.org/#sec-typeof-operator
+fn type_of(value: &RuntimeValue) -> &'static str {
+    match value {
+        RuntimeValue::Undefined => "undefined",
+        RuntimeValue::Number(_) => "number",
+        RuntimeValue::BigInt(_) => "bigint",
+        RuntimeValue::Boolean(_) => "boolean",
+        RuntimeValue::StringLiteral(_) => "string",
+        RuntimeValue::Function(_) | RuntimeValue::NativeFunction(_) => "function",
+        _ => "object",
+    }
+}
+
+/// == の比較
+/// Reference: https://262.ecma-international.org/#sec-islooselyequal
+fn is_loosely_equal(left: &RuntimeValue, right: &RuntimeValue) -> bool {
+    use RuntimeValue::{BigInt, Boolean, Null, Number, StringLiteral, Undefined};
+    match (left, right) {
+        _ if core::mem::discriminant(left) == core::mem::discriminant(right) => left == right,
+        // undefined と null は互いにだけ等しい
+        (Undefined | Null, Undefined | Null) => true,
+        (Undefined | Null, _) | (_, Undefined | Null) => false,
+        (Number(n), StringLiteral(s)) | (StringLiteral(s), Number(n)) => *n == string_to_number(s),
+        (BigInt(b), StringLiteral(s)) | (StringLiteral(s), BigInt(b)) => {
+            b.to_f64() == string_to_number(s)
+        }
+        (BigInt(b), Number(n)) | (Number(n), BigInt(b)) => n.is_finite() && b.to_f64() == *n,
+        (Boolean(b), other) | (other, Boolean(b)) => {
+            is_loosely_equal(&Number(if *b { 1.0 } else { 0.0 }), other)
+        }
+        // オブジェクトとプリミティブはオブジェクトをプリミティブにしてから比べる
+        (primitive @ (Number(_) | BigInt(_) | StringLiteral(_)), object)
+        | (object, primitive @ (Number(_) | BigInt(_) | StringLiteral(_))) => {
+            is_loosely_equal(primitive, &to_primitive(object.clone()))
+        }
+        _ => false,
+    }
+}
+
+/// 関係演算子の比較。NaN を含む場合は None (仕様の undefined)
+/// Reference: https://262.ecma-international.org/#sec-islessthan
+fn compare_values(left: RuntimeValue, right: RuntimeValue) -> Option<Ordering> {
+    let (left, right) = (to_primitive(left), to_primitive(right));
+    if let (RuntimeValue::StringLiteral(l), RuntimeValue::StringLiteral(r)) = (&left, &right) {
+        // 文字列は UTF-16 のコード単位で比べる
+        return Some(l.encode_utf16().cmp(r.encode_utf16()));
+    }
+    match (to_numeric(left), to_numeric(right)) {
+        (RuntimeValue::Number(l), RuntimeValue::Number(r)) => l.partial_cmp(&r),
+        (RuntimeValue::BigInt(l), RuntimeValue::BigInt(r)) => Some(l.cmp(&r)),
+        (RuntimeValue::BigInt(l), RuntimeValue::Number(r)) => l.to_f64().partial_cmp(&r),
+        (RuntimeValue::Number(l), RuntimeValue::BigInt(r)) => l.partial_cmp(&r.to_f64()),
+        _ => unreachable!("ToNumeric returns a Number or a BigInt"),
+    }
+}
+
+/// Number::exponentiate。Rust の powf と違い、1 ** NaN や 1 ** Infinity は NaN
+/// Reference: https://262.ecma-international.org/#sec-numeric-types-number-exponentiate
+fn number_exponentiate(base: f64, exponent: f64) -> f64 {
+    if exponent.is_nan() || (base.abs() == 1.0 && exponent.is_infinite()) {
+        return f64::NAN;
+    }
+    base.powf(exponent)
+}
+
+/// 算術演算子。BigInt と Number は混ぜられない
+/// Reference: https://262.ecma-international.org/#sec-applystringornumericbinaryoperator
+fn apply_binary_operator(
+    operator: &str,
+    left: RuntimeValue,
+    right: RuntimeValue,
+) -> EvalResult<RuntimeValue> {
+    let (left, right) = if operator == "+" {
+        let (left, right) = (to_primitive(left), to_primitive(right));
+        // どちらかが文字列なら連結する
+        if matches!(left, RuntimeValue::StringLiteral(_))
+            || matches!(right, RuntimeValue::StringLiteral(_))
+        {
+            return Ok(RuntimeValue::StringLiteral(format!("{}{}", left, right)));
+        }
+        (to_numeric(left), to_numeric(right))
+    } else {
+        (to_numeric(left), to_numeric(right))
+    };
+    match (left, right) {
+        (RuntimeValue::Number(l), RuntimeValue::Number(r)) => {
+            Ok(RuntimeValue::Number(match operator {
+                "+" => l + r,
+                "-" => l - r,
+                "*" => l * r,
+                "/" => l / r,
+                "%" => l % r,
+                "**" => number_exponentiate(l, r),
+                _ => unreachable!("unknown arithmetic operator {}", operator),
+            }))
+        }
+        (RuntimeValue::BigInt(l), RuntimeValue::BigInt(r)) => {
+            let result = match operator {
+                "+" => Ok(&l + &r),
+                "-" => Ok(&l - &r),
+                "*" => Ok(&l * &r),
+                "/" => l.div_rem(&r).map(|(quotient, _)| quotient),
+                "%" => l.div_rem(&r).map(|(_, remainder)| remainder),
+                "**" => l.pow(&r),
+                _ => unreachable!("unknown arithmetic operator {}", operator),
+            };
+            result
+                .map(RuntimeValue::BigInt)
+                .map_err(|error| throw_error("RangeError", error))
+        }
+        _ => Err(throw_error(
+            "TypeError",
+            "Cannot mix BigInt and other types, use explicit conversions",
+        )),
+    }
+}
+
+/// 二項演算子を評価する
+fn evaluate_binary_expression(
+    operator: &str,
+    left: RuntimeValue,
+    right: RuntimeValue,
+) -> EvalResult<RuntimeValue> {
+    let boolean = |value| Ok(RuntimeValue::Boolean(value));
+    match operator {
+        "===" => boolean(left == right),
+        "!==" => boolean(left != right),
+        "==" => boolean(is_loosely_equal(&left, &right)),
+        "!=" => boolean(!is_loosely_equal(&left, &right)),
+        "<" => boolean(compare_values(left, right) == Some(Ordering::Less)),
+        ">" => boolean(compare_values(left, right) == Some(Ordering::Greater)),
+        "<=" => boolean(matches!(
+            compare_values(left, right),
+            Some(Ordering::Less | Ordering::Equal)
+        )),
+        ">=" => boolean(matches!(
+            compare_values(left, right),
+            Some(Ordering::Greater | Ordering::Equal)
+        )),
+        _ => apply_binary_operator(operator, left, right),
+    }
+}
+
+/// Reference: https://262.ecma-international.org/#sec-unary-operators
+fn evaluate_unary_expression(operator: &str, argument: RuntimeValue) -> EvalResult<RuntimeValue> {
+    Ok(match operator {
+        "!" => RuntimeValue::Boolean(!to_boolean(&argument)),
+        "typeof" => RuntimeValue::StringLiteral(type_of(&argument).to_string()),
+        "+" => RuntimeValue::Number(to_number(argument)?),
+        "-" => match to_numeric(argument) {
+            RuntimeValue::BigInt(value) => RuntimeValue::BigInt(-value),
+            RuntimeValue::Number(value) => RuntimeValue::Number(-value),
+            _ => unreachable!("ToNumeric returns a Number or a BigInt"),
+        },
+        _ => unreachable!("unknown unary operator {}", operator),
+    })
+}
+
+/// 不正な言語タグは RangeError
+fn parse_locale(tag: &str) -> EvalResult<Locale> {
+    Locale::parse(tag).map_err(|error| throw_error("RangeError", error))
+}
+
+/// onError に渡すエラーオブジェクト。位置はメッセージ中の UTF-16 のオフセット
+/// Reference: https://github.com/tc39/proposal-intl-messageformat#error-handling
+fn message_error(error: &FormatError, source: &str, cause: Option<RuntimeValue>) -> RuntimeValue {
+    let mut object = Vec::from([
+        (
+            "type".to_string(),
+            RuntimeValue::StringLiteral(error.error_type().to_string()),
+        ),
+        (
+            "message".to_string(),
+            RuntimeValue::StringLiteral(error.message(source)),
+        ),
+    ]);
+    if let (Some(span), Some(utf16)) = (&error.span, error.span_utf16(source)) {
+        object.push((
+            "start".to_string(),
+            RuntimeValue::Number(utf16.start as f64),
+        ));
+        object.push(("end".to_string(), RuntimeValue::Number(utf16.end as f64)));
+        object.push((
+            "source".to_string(),
+            RuntimeValue::StringLiteral(source[span.clone()].to_string()),
+        ));
+    }
+    // カスタム関数が投げた例外
+    if let Some(cause) = cause {
+        object.push(("cause".to_string(), cause));
+    }
+    new_object(object)
+}
+
+/// 文字列のオプションを取り出す。指定がなければ None、許可されない値は RangeError
+/// Reference: https://tc39.es/ecma402/#sec-getoption
+fn get_option<'a>(
+    options: &HashMap<String, RuntimeValue>,
+    property: &str,
+    values: &[&'a str],
+) -> EvalResult<Option<&'a str>> {
+    let Some(value) = options.get(property) else {
+        return Ok(None);
+    };
+    let value = value.to_string();
+    match values.iter().find(|allowed| **allowed == value) {
+        Some(allowed) => Ok(Some(allowed)),
+        None => Err(throw_error(
+            "RangeError",
+            format!(
+                "Value {} out of range for Intl.MessageFormat options property {}",
+                value, property
+            ),
+        )),
+    }
+}
+
+/// options.localeMatcher
+fn locale_matcher_option(options: &HashMap<String, RuntimeValue>) -> EvalResult<LocaleMatcher> {
+    Ok(
+        match get_option(options, "localeMatcher", &["lookup", "best fit"])? {
+            Some("lookup") => LocaleMatcher::Lookup,
+            _ => LocaleMatcher::BestFit,
+        },
+    )
+}
+
+/// options.bidiIsolation と options.dir。指定がなければロケールから決めた既定値を使う
+fn format_options(
+    options: &HashMap<String, RuntimeValue>,
+    locale: Locale,
+) -> EvalResult<FormatOptions> {
+    let mut format_options = FormatOptions::new(locale);
+    match get_option(options, "bidiIsolation", &["default", "none"])? {
+        Some("none") => format_options.bidi_isolation = BidiIsolation::None,
+        Some(_) => format_options.bidi_isolation = BidiIsolation::Default,
+        None => {}
+    }
+    match get_option(options, "dir", &["ltr", "rtl", "auto"])? {
+        Some("ltr") => format_options.dir = Direction::Ltr,
+        Some("rtl") => format_options.dir = Direction::Rtl,
+        Some(_) => format_options.dir = Direction::Auto,
+        None => {}
+    }
+    Ok(format_options)
+}
+
+/// options.functions。関数名から関数へのオブジェクトでなければ TypeError
+fn functions_option(
+    options: &HashMap<String, RuntimeValue>,
+) -> EvalResult<HashMap<String, Function>> {
+    let functions = match options.get("functions") {
+        None => return Ok(HashMap::new()),
+        Some(RuntimeValue::Object(functions)) => functions.borrow().to_map(),
+        Some(value) => {
+            return Err(throw_error(
+                "TypeError",
+                format!(
+                    "Intl.MessageFormat options property functions must be an object, got {}",
+                    value
+                ),
+            ));
+        }
+    };
+    functions
+        .into_iter()
+        .map(|(name, value)| match value {
+            RuntimeValue::Function(function) => Ok((name, function)),
+            _ => Err(throw_error(
+                "TypeError",
+                format!("MessageFormat function :{} is not a function", name),
+            )),
+        })
+        .collect()
+}
+
+/// format に渡す変数。undefined の変数は渡さないので、未解決の変数として扱われる
+/// Reference: https://github.com/tc39/proposal-intl-messageformat#formatting
+fn message_arguments(object: &Object) -> HashMap<String, RuntimeValue> {
+    let mut variables = object.to_map();
+    variables.retain(|_, value| *value != undefined());
+    variables
+}
+
+/// new Error(message) などで作るエラーオブジェクト。message が undefined なら空
+fn new_error(name: &str, message: Option<&RuntimeValue>) -> RuntimeValue {
+    let message = match message {
+        None => String::new(),
+        Some(message) if *message == undefined() => String::new(),
+        Some(message) => message.to_string(),
+    };
+    error_object(name, message)
+}
+
+fn error_object(name: &str, message: String) -> RuntimeValue {
+    RuntimeValue::Error(Shared::new(Object::with_properties([
+        (
+            "name".to_string(),
+            RuntimeValue::StringLiteral(name.to_string()),
+        ),
+        ("message".to_string(), RuntimeValue::StringLiteral(message)),
+    ])))
+}
+
+/// Intl.Locale のプロパティ
+/// Reference: https://tc39.es/ecma402/#sec-properties-of-intl-locale-prototype-object
+fn locale_property(locale: &Locale, name: &str) -> RuntimeValue {
+    let value = match name {
+        "language" => Some(locale.language().to_string()),
+        "script" => locale.script().map(str::to_string),
+        "region" => locale.region().map(str::to_string),
+        "baseName" => Some(locale.base_name()),
+        "calendar" => locale.unicode_keyword("ca").map(str::to_string),
+        "collation" => locale.unicode_keyword("co").map(str::to_string),
+        "hourCycle" => locale.unicode_keyword("hc").map(str::to_string),
+        "numberingSystem" => locale.unicode_keyword("nu").map(str::to_string),
+        _ => None,
+    };
+    value
+        .map(RuntimeValue::StringLiteral)
+        .unwrap_or_else(undefined)
+}
+
+/// 束縛の種類。let と const は宣言の位置で初期化されるまで TDZ にある
+/// Reference: https://262.ecma-international.org/#sec-declarative-environment-records
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BindingKind {
+    Var,
+    Let,
+    Const,
+}
+
+#[derive(Clone)]
+struct Binding {
+    kind: BindingKind,
+    /// None の間は TDZ
+    value: Option<RuntimeValue>,
+}
 
 pub struct Environment {
-    variables: HashMap<String, RuntimeValue>,
+    variables: HashMap<String, Binding>,
     outer: Option<Rc<RefCell<Environment>>>,
     nest: u32,
 }
@@ -100,180 +988,2326 @@ impl Environment {
         }
     }
 
+    /// 初期化済みの変更できる束縛を作る。仮引数や catch の引数に使う
     pub fn define_var(&mut self, name: String, value: RuntimeValue) {
-        self.variables.insert(name.clone(), value);
+        self.initialize(&name, BindingKind::Var, value);
+    }
+
+    /// for 文の繰り返しごとの環境。束縛を同じ値でコピーするので、
+    /// ループの中で作ったクロージャはその回の値を捕捉する
+    /// Reference: https://262.ecma-international.org/#sec-createperiterationenvironment
+    fn copy_for_iteration(&self) -> Self {
+        Self {
+            variables: self.variables.clone(),
+            outer: self.outer.clone(),
+            nest: self.nest,
+        }
+    }
+
+    /// 宣言を巻き上げて束縛を作る。var は undefined で初期化し、let と const は TDZ に置く
+    fn declare(&mut self, name: &str, kind: BindingKind) -> EvalResult<()> {
+        match self.variables.get(name) {
+            Some(binding) if kind == BindingKind::Var && binding.kind == BindingKind::Var => Ok(()),
+            Some(_) => Err(throw_error(
+                "SyntaxError",
+                format!("Identifier '{}' has already been declared", name),
+            )),
+            None => {
+                let value = (kind == BindingKind::Var).then(undefined);
+                self.variables
+                    .insert(name.to_string(), Binding { kind, value });
+                Ok(())
+            }
+        }
+    }
+
+    /// 宣言の位置で束縛を初期化する
+    fn initialize(&mut self, name: &str, kind: BindingKind, value: RuntimeValue) {
+        self.variables.insert(
+            name.to_string(),
+            Binding {
+                kind,
+                value: Some(value),
+            },
+        );
     }
 
-    pub fn get_var(&self, name: &str) -> Option<RuntimeValue> {
-        if let Some(value) = self.variables.get(name) {
-            Some(value.clone())
+    /// 変数の値。未宣言なら None、TDZ にあれば ReferenceError
+    pub fn get_var(&self, name: &str) -> Option<EvalResult<RuntimeValue>> {
+        if let Some(binding) = self.variables.get(name) {
+            Some(binding.value.clone().ok_or_else(|| uninitialized(name)))
         } else if let Some(outer) = &self.outer {
             outer.borrow().get_var(name)
         } else {
             None
         }
     }
-}
 
-pub struct Runtime {}
-impl Runtime {
-    pub fn new() -> Self {
-        Self {}
+    /// 既存の束縛に代入する。const への代入は TypeError
+    /// Reference: https://262.ecma-international.org/#sec-declarative-environment-records-setmutablebinding-n-v-s
+    fn set_var(&mut self, name: &str, value: RuntimeValue) -> EvalResult<()> {
+        self.with_binding(name, |binding| match binding.value {
+            None => Err(uninitialized(name)),
+            Some(_) if binding.kind == BindingKind::Const => {
+                Err(throw_error("TypeError", "Assignment to constant variable."))
+            }
+            Some(_) => {
+                binding.value = Some(value);
+                Ok(())
+            }
+        })
     }
-    pub fn execute(&mut self, program: Program) {
-        let env = Rc::new(RefCell::new(Environment::new(None)));
-        let mut result = None;
-        for node in program.body {
-            result = self.eval(Some(node), env.clone());
-        }
-        if let Some(res) = result {
-            println!("> {}", res.to_string());
+
+    fn with_binding<T>(
+        &mut self,
+        name: &str,
+        f: impl FnOnce(&mut Binding) -> EvalResult<T>,
+    ) -> EvalResult<T> {
+        if let Some(binding) = self.variables.get_mut(name) {
+            f(binding)
+        } else if let Some(outer) = &self.outer {
+            outer.borrow_mut().with_binding(name, f)
+        } else {
+            Err(throw_error(
+                "ReferenceError",
+                format!("{} is not defined", name),
+            ))
         }
     }
+}
 
-    pub fn eval(
-        &mut self,
-        _node: Option<Node>,
-        env: Rc<RefCell<Environment>>,
-    ) -> Option<RuntimeValue> {
-        let node = match _node {
-            Some(n) => n,
-            None => return None,
-        };
-        match node {
-            Node::ExpressionStatement { expression } => self.eval(Some(*expression), env.clone()),
+fn uninitialized(name: &str) -> Completion {
+    throw_error(
+        "ReferenceError",
+        format!("Cannot access '{}' before initialization", name),
+    )
+}
 
-            Node::VariableDeclaration { id, init } => {
-                let var_name = match &*id {
-                    Node::Identifier(name) => name.clone(),
-                    _ => panic!("Expected identifier in variable declaration"),
+/// ブロックで直接宣言された let と const を TDZ の束縛として作る。
+/// 関数宣言はその場で関数を作って初期化するので、宣言より前から呼び出せる
+/// Reference: https://262.ecma-international.org/#sec-blockdeclarationinstantiation
+fn declare_lexical(body: &[Node], env: &Rc<RefCell<Environment>>) -> EvalResult<()> {
+    for statement in body {
+        match statement {
+            Node::VariableDeclaration { kind, declarations } => {
+                let kind = match kind.as_str() {
+                    "let" => BindingKind::Let,
+                    "const" => BindingKind::Const,
+                    _ => continue,
                 };
-                let init_result = self.eval(Some(*init), env.clone());
-                env.borrow_mut().define_var(
-                    var_name,
-                    init_result.unwrap_or(RuntimeValue::StringLiteral("undefined".to_string())),
-                );
-                None
-            }
-            Node::NewExpression { callee, arguments } => {
-                let callee_result = self.eval(Some(*callee), env.clone());
-                let mut args_result = Vec::new();
-                for arg in arguments {
-                    if let Some(arg_value) = self.eval(Some(arg), env.clone()) {
-                        args_result.push(arg_value);
-                    }
-                }
-                if callee_result
-                    == Some(RuntimeValue::StringLiteral(
-                        "Intl.MessageFormat".to_string(),
-                    ))
-                {
-                    if args_result.len() == 2 {
-                        if let (
-                            RuntimeValue::StringLiteral(locale),
-                            RuntimeValue::StringLiteral(message),
-                        ) = (&args_result[0], &args_result[1])
-                        {
-                            return Some(RuntimeValue::MessageFormatInstance(
-                                MessageFormatInstance::new(locale.clone(), message.clone()),
-                            ));
-                        }
-                    }
+                for name in declared_names(declarations) {
+                    env.borrow_mut().declare(name, kind)?;
                 }
-                None
             }
-
-            Node::MemberExpression { object, property } => {
-                let object_result = match self.eval(Some(*object), env.clone()) {
-                    Some(value) => value,
-                    None => return None,
-                };
-                let property_result = match self.eval(Some(*property), env.clone()) {
-                    Some(value) => value,
-                    None => return Some(object_result),
-                };
-
-                if let RuntimeValue::MessageFormatInstance(instance) = object_result {
-                    return Some(RuntimeValue::MessageFormatMethod(MessageFormatMethod::new(
-                        instance,
-                        if let RuntimeValue::StringLiteral(method) = property_result {
-                            method
-                        } else {
-                            panic!("Expected StringLiteral for method name");
-                        },
-                    )));
-                }
-                Some(object_result + RuntimeValue::StringLiteral(".".to_string()) + property_result)
-            }
-            Node::CallExpression { callee, arguments } => {
-                // localな環境を作成
-                let local_env = Rc::new(RefCell::new(Environment::new(Some(env))));
-                let callee = match self.eval(Some(*callee), local_env.clone()) {
-                    Some(value) => value,
-                    None => return None,
-                };
-
-                let first_arg = arguments.get(0).unwrap().clone();
-
-                // calleeがMessageFormatMethodの場合、対応するメソッドを呼び出す
-                if let RuntimeValue::MessageFormatMethod(mf_method) = callee {
-                    return Some(RuntimeValue::StringLiteral(
-                        self.call_intl_message_format_method(
-                            mf_method,
-                            first_arg,
-                            local_env.clone(),
-                        ),
-                    ));
-                }
-                None
-            }
-            Node::ObjectExpression { properties } => {
-                let mut object = HashMap::new();
-                for prop in properties {
-                    if let Node::Property { key, value } = prop {
-                        if let Some(val) = self.eval(Some(*value), env.clone()) {
-                            object.insert(key, val);
-                        }
-                    }
-                }
-                Some(RuntimeValue::Object(object))
-            }
-            Node::Identifier(name) => {
-                match env.borrow_mut().get_var(&name.to_string()) {
-                    Some(v) => Some(v),
-                    // 変数名が初めて使用される場合は、まだ値は保存されていないので、文字列として扱う
-                    // たとえば、var a = 42; のようなコードの場合、aはStringLiteralとして扱われる
-                    None => Some(RuntimeValue::StringLiteral(name.to_string())),
-                }
+            Node::FunctionDeclaration { id, params, body } => {
+                let function = RuntimeValue::Function(Function {
+                    name: Some(id.clone()),
+                    params: params.clone(),
+                    body: Rc::new(body.as_ref().clone()),
+                    env: env.clone(),
+                });
+                let mut env = env.borrow_mut();
+                env.declare(id, BindingKind::Var)?;
+                env.initialize(id, BindingKind::Var, function);
             }
-            Node::NumericLiteral(value) => Some(RuntimeValue::Number(value)),
-            Node::StringLiteral(value) => Some(RuntimeValue::StringLiteral(value.clone())),
-            _ => None,
+            _ => {}
         }
     }
+    Ok(())
+}
 
-    fn call_intl_message_format_method(
-        &mut self,
-        method: MessageFormatMethod,
-        args: Node,
-        env: Rc<RefCell<Environment>>,
-    ) -> String {
-        if method.method != "format" {
-            return "".to_string(); // 未対応のメソッドは空文字を返す
+/// 関数やプログラムの中の var を巻き上げる。入れ子のブロックも探すが、関数の中には入らない
+/// Reference: https://262.ecma-international.org/#sec-static-semantics-varscopeddeclarations
+fn declare_var(node: &Node, env: &Rc<RefCell<Environment>>) -> EvalResult<()> {
+    match node {
+        Node::VariableDeclaration { kind, declarations } if kind == "var" => {
+            for name in declared_names(declarations) {
+                env.borrow_mut().declare(name, BindingKind::Var)?;
+            }
         }
-        // argsをevalして、変数マップを取得
-        let variables = match self.eval(Some(args), env.clone()) {
-            Some(RuntimeValue::Object(map)) => map,
-            _ => HashMap::new(),
-        };
+        Node::BlockStatement { body }
+        | Node::SwitchCase {
+            consequent: body, ..
+        } => {
+            for statement in body {
+                declare_var(statement, env)?;
+            }
+        }
+        Node::SwitchStatement { cases, .. } => {
+            for case in cases {
+                declare_var(case, env)?;
+            }
+        }
+        Node::IfStatement {
+            consequent,
+            alternate,
+            ..
+        } => {
+            declare_var(consequent, env)?;
+            if let Some(alternate) = alternate {
+                declare_var(alternate, env)?;
+            }
+        }
+        Node::ForStatement { init, body, .. } => {
+            if let Some(init) = init {
+                declare_var(init, env)?;
+            }
+            declare_var(body, env)?;
+        }
+        Node::ForOfStatement { left, body, .. } | Node::ForInStatement { left, body, .. } => {
+            declare_var(left, env)?;
+            declare_var(body, env)?;
+        }
+        Node::WhileStatement { body, .. }
+        | Node::DoWhileStatement { body, .. }
+        | Node::LabeledStatement { body, .. } => declare_var(body, env)?,
+        Node::TryStatement {
+            block,
+            handler,
+            finalizer,
+            ..
+        } => {
+            for node in [Some(block), handler.as_ref(), finalizer.as_ref()]
+                .into_iter()
+                .flatten()
+            {
+                declare_var(node, env)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
 
-        let mut mf2_parser = Mf2Parser::new(&method.instance.message);
-        let ast = mf2_parser.parse();
+fn declared_names(declarations: &[Node]) -> impl Iterator<Item = &str> {
+    declarations
+        .iter()
+        .filter_map(|declarator| match declarator {
+            Node::VariableDeclarator { id, .. } => match id.as_ref() {
+                Node::Identifier(name) => Some(name.as_str()),
+                _ => None,
+            },
+            _ => None,
+        })
+}
 
-        // ASTをvariablesを使ってフォーマット
-        match ast {
-            Ok(message) => MF2Printer::print(&message, &variables),
-            _ => method.instance.message.to_string(),
-        }
+/// ループ本体の完了から、次の繰り返しに進むかを決める。labels はそのループに付いたラベル
+/// Reference: https://262.ecma-international.org/#sec-loopcontinues
+fn loop_continues(result: EvalResult<Option<RuntimeValue>>, labels: &[String]) -> EvalResult<bool> {
+    match result {
+        Ok(_) | Err(Completion::Continue(None)) => Ok(true),
+        Err(Completion::Break(None)) => Ok(false),
+        Err(Completion::Continue(Some(label))) if labels.contains(&label) => Ok(true),
+        Err(Completion::Break(Some(label))) if labels.contains(&label) => Ok(false),
+        Err(completion) => Err(completion),
+    }
+}
+
+/// for...of で取り出す値。配列は要素で穴は undefined、文字列はコードポイントごと
+/// Reference: https://262.ecma-international.org/#sec-getiterator
+fn iterate(value: RuntimeValue) -> EvalResult<Vec<RuntimeValue>> {
+    match value {
+        RuntimeValue::Array(items) => Ok(items
+            .borrow()
+            .iter()
+            .map(|item| item.clone().unwrap_or_else(undefined))
+            .collect()),
+        RuntimeValue::StringLiteral(s) => Ok(s
+            .chars()
+            .map(|c| RuntimeValue::StringLiteral(c.to_string()))
+            .collect()),
+        other => Err(throw_error(
+            "TypeError",
+            format!("{} is not iterable", other),
+        )),
+    }
+}
+
+/// for...in で列挙するプロパティ名。配列の穴は飛ばす
+/// Reference: https://262.ecma-international.org/#sec-enumerate-object-properties
+fn enumerable_keys(value: &RuntimeValue) -> Vec<String> {
+    match value {
+        RuntimeValue::Array(items) => items
+            .borrow()
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| item.is_some())
+            .map(|(i, _)| i.to_string())
+            .collect(),
+        RuntimeValue::StringLiteral(s) => (0..s.encode_utf16().count())
+            .map(|i| i.to_string())
+            .collect(),
+        RuntimeValue::Object(object) => object.borrow().enumerable_keys(),
+        _ => Vec::new(),
+    }
+}
+
+thread_local! {
+    /// join や toString で連結している途中の配列
+    static JOINING: RefCell<Vec<Shared<Vec<Option<RuntimeValue>>>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// 配列の要素を文字列にして連結する。穴と undefined と null は空文字列になる
+/// Reference: https://262.ecma-international.org/#sec-array.prototype.join
+fn join_items(array: &Shared<Vec<Option<RuntimeValue>>>, separator: &str) -> String {
+    // 連結中の配列に戻ってきたら、V8 と同じく空文字列にして循環を断つ
+    if JOINING.with_borrow(|joining| joining.contains(array)) {
+        return String::new();
+    }
+    JOINING.with_borrow_mut(|joining| joining.push(array.clone()));
+    let joined = array
+        .borrow()
+        .iter()
+        .map(|item| match item {
+            None | Some(RuntimeValue::Undefined | RuntimeValue::Null) => String::new(),
+            Some(item) => item.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(separator);
+    JOINING.with_borrow_mut(|joining| joining.pop());
+    joined
+}
+
+/// map などのコールバック。関数でなければ TypeError
+fn callback_function(value: Option<RuntimeValue>) -> EvalResult<RuntimeValue> {
+    match value {
+        Some(value) if is_callable(&value) => Ok(value),
+        value => Err(throw_error(
+            "TypeError",
+            format!("{} is not a function", value.unwrap_or_else(undefined)),
+        )),
+    }
+}
+
+/// 配列自身のプロパティ。length と添字の要素で、それ以外はプロトタイプから読む
+fn array_property(items: &Shared<Vec<Option<RuntimeValue>>>, key: &str) -> Option<RuntimeValue> {
+    if key == "length" {
+        return Some(RuntimeValue::Number(items.borrow().len() as f64));
+    }
+    let index = array_index(key)?;
+    Some(
+        items
+            .borrow()
+            .get(index)
+            .cloned()
+            .flatten()
+            .unwrap_or_else(undefined),
+    )
+}
+
+/// 文字列の length と添字。どちらも UTF-16 のコード単位で数える
+/// Reference: https://262.ecma-international.org/#sec-string-exotic-objects
+fn string_property(value: &str, key: &str) -> Option<RuntimeValue> {
+    if key == "length" {
+        return Some(RuntimeValue::Number(value.encode_utf16().count() as f64));
+    }
+    let unit = value.encode_utf16().nth(array_index(key)?)?;
+    Some(RuntimeValue::StringLiteral(String::from_utf16_lossy(&[
+        unit,
+    ])))
+}
+
+/// slice などの引数の位置。負の数は末尾から数え、0 から len の範囲に収める
+/// Reference: https://262.ecma-international.org/#sec-array.prototype.slice
+fn relative_index(value: Option<RuntimeValue>, len: usize, default: usize) -> EvalResult<usize> {
+    let Some(value) = value.filter(|value| *value != undefined()) else {
+        return Ok(default);
+    };
+    let relative = to_number(value)?;
+    let relative = if relative.is_nan() {
+        0.0
+    } else {
+        relative.trunc()
+    };
+    Ok(if relative < 0.0 {
+        (len as f64 + relative).max(0.0) as usize
+    } else {
+        relative.min(len as f64) as usize
+    })
+}
+
+/// includes の比較。=== と違って NaN は NaN に等しい
+/// Reference: https://262.ecma-international.org/#sec-samevaluezero
+fn same_value_zero(left: &RuntimeValue, right: &RuntimeValue) -> bool {
+    match (left, right) {
+        (RuntimeValue::Number(left), RuntimeValue::Number(right)) => {
+            left == right || (left.is_nan() && right.is_nan())
+        }
+        _ => left == right,
+    }
+}
+
+/// 配列の長さを変える。伸ばした分は穴になる
+fn resize_array(items: &mut Vec<Option<RuntimeValue>>, length: usize) -> EvalResult<()> {
+    if length > MAX_ARRAY_LENGTH {
+        return Err(throw_error(
+            "RangeError",
+            format!("Array length {} exceeds the supported maximum", length),
+        ));
+    }
+    items.resize(length, None);
+    Ok(())
+}
+
+/// object[key] = value。オブジェクトと配列以外には書き込めない
+/// Reference: https://262.ecma-international.org/#sec-putvalue
+fn set_property(object: &RuntimeValue, key: String, value: RuntimeValue) -> EvalResult<()> {
+    match (object, array_index(&key)) {
+        (RuntimeValue::Object(object) | RuntimeValue::Error(object), _) => {
+            object.borrow_mut().set(key, value);
+            Ok(())
+        }
+        // 添字が length 以上なら間を穴で埋める
+        (RuntimeValue::Array(items), Some(index)) => {
+            let mut items = items.borrow_mut();
+            if index >= items.len() {
+                resize_array(&mut items, index + 1)?;
+            }
+            items[index] = Some(value);
+            Ok(())
+        }
+        (RuntimeValue::Array(items), None) if key == "length" => {
+            let length = to_number(value)?;
+            if length < 0.0 || length.fract() != 0.0 || length > u32::MAX as f64 {
+                return Err(throw_error("RangeError", "Invalid array length"));
+            }
+            resize_array(&mut items.borrow_mut(), length as usize)
+        }
+        (other, _) if is_nullish(other) => Err(throw_error(
+            "TypeError",
+            format!("Cannot set properties of {} (setting '{}')", other, key),
+        )),
+        (other, _) => Err(throw_error(
+            "TypeError",
+            format!(
+                "Cannot create property '{}' on {} '{}'",
+                key,
+                type_of(other),
+                other
+            ),
+        )),
+    }
+}
+
+pub struct Runtime {
+    functions: FunctionRegistry,
+    catalog: Catalog,
+    global: Shared<Object>,
+    array_prototype: Shared<Object>,
+    message_format_prototype: Shared<Object>,
+    /// これより深く (下位のアドレスへ) スタックを使うと RangeError を投げる
+    stack_limit: usize,
+}
+impl Runtime {
+    /// STACK_SIZE のスタックを持つスレッドの中で作り、そのスレッドで実行する
+    pub fn new(catalog: Catalog) -> Self {
+        Self {
+            functions: FunctionRegistry::default(),
+            catalog,
+            global: global_object(),
+            array_prototype: array_prototype(),
+            message_format_prototype: message_format_prototype(),
+            stack_limit: stack_position().saturating_sub(STACK_SIZE - STACK_RESERVE),
+        }
+    }
+
+    /// 変数を探し、宣言されていなければグローバルオブジェクトのプロパティを探す
+    /// Reference: https://262.ecma-international.org/#sec-global-environment-records
+    fn resolve_binding(
+        &self,
+        name: &str,
+        env: &Rc<RefCell<Environment>>,
+    ) -> Option<EvalResult<RuntimeValue>> {
+        env.borrow()
+            .get_var(name)
+            .or_else(|| self.global.borrow().get(name).map(Ok))
+    }
+
+    /// プログラムを実行し、最後の文の値を出力する。捕捉されなかった例外は Err で返す
+    pub fn execute(&mut self, program: Program) -> Result<(), Box<RuntimeValue>> {
+        if let Some(res) = self.run(program)? {
+            println!("> {}", res);
+        }
+        Ok(())
+    }
+
+    /// プログラムを実行し、最後の文の値を返す
+    fn run(&mut self, program: Program) -> Result<Option<RuntimeValue>, Box<RuntimeValue>> {
+        let env = Rc::new(RefCell::new(Environment::new(None)));
+        env.borrow_mut()
+            .initialize("undefined", BindingKind::Const, undefined());
+        env.borrow_mut()
+            .initialize("NaN", BindingKind::Const, RuntimeValue::Number(f64::NAN));
+        env.borrow_mut().initialize(
+            "Infinity",
+            BindingKind::Const,
+            RuntimeValue::Number(f64::INFINITY),
+        );
+        let hoisted = program
+            .body
+            .iter()
+            .try_for_each(|node| declare_var(node, &env))
+            .and_then(|_| declare_lexical(&program.body, &env));
+        if let Err(Completion::Throw(exception)) = hoisted {
+            return Err(exception);
+        }
+        let mut result = None;
+        for node in program.body {
+            match self.eval(Some(node), env.clone()) {
+                Ok(Some(value)) => result = Some(value),
+                // 宣言は値を持たないので、直前の文の値を残す
+                Ok(None) => {}
+                Err(Completion::Throw(exception)) => return Err(exception),
+                // 関数の外の return や、ループの外の break と continue は構文エラーになるので、ここには来ない
+                Err(Completion::Return(_) | Completion::Break(_) | Completion::Continue(_)) => {
+                    unreachable!("jump outside of a function or a loop")
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// ToPrimitive。オブジェクトに valueOf や toString があれば呼び、プリミティブを返した方を使う
+    /// hint が "string" なら toString を先に呼ぶ
+    /// Reference: https://262.ecma-international.org/#sec-ordinarytoprimitive
+    fn primitive_value(&mut self, value: RuntimeValue, hint: &str) -> EvalResult<RuntimeValue> {
+        let RuntimeValue::Object(object) = &value else {
+            return Ok(to_primitive(value));
+        };
+        let methods = if hint == "string" {
+            ["toString", "valueOf"]
+        } else {
+            ["valueOf", "toString"]
+        };
+        for name in methods {
+            let method = object.borrow().get(name);
+            let Some(method) = method.filter(is_callable) else {
+                continue;
+            };
+            let result = self.call(&method, value.clone(), Vec::new())?;
+            if is_primitive(&result) {
+                return Ok(result);
+            }
+            // 組み込みの valueOf はオブジェクト自身を返すので、toString もオブジェクトなら変換できない
+            if name == "toString" {
+                return Err(throw_error(
+                    "TypeError",
+                    "Cannot convert object to primitive value",
+                ));
+            }
+        }
+        // Object.prototype.toString の "[object Object]"
+        Ok(to_primitive(value))
+    }
+
+    /// 二項演算子の被演算子のオブジェクトをプリミティブにする
+    /// === と、オブジェクト同士の == は同一性で比べるので変換しない
+    fn binary_operands(
+        &mut self,
+        operator: &str,
+        left: RuntimeValue,
+        right: RuntimeValue,
+    ) -> EvalResult<(RuntimeValue, RuntimeValue)> {
+        match operator {
+            "===" | "!==" => Ok((left, right)),
+            "==" | "!=" => match (&left, &right) {
+                (RuntimeValue::Object(_), other) if is_primitive(other) && !is_nullish(other) => {
+                    Ok((self.primitive_value(left, "default")?, right))
+                }
+                (other, RuntimeValue::Object(_)) if is_primitive(other) && !is_nullish(other) => {
+                    Ok((left, self.primitive_value(right, "default")?))
+                }
+                _ => Ok((left, right)),
+            },
+            _ => {
+                let hint = if operator == "+" { "default" } else { "number" };
+                Ok((
+                    self.primitive_value(left, hint)?,
+                    self.primitive_value(right, hint)?,
+                ))
+            }
+        }
+    }
+
+    /// 値を持たない式は undefined として評価する
+    fn eval_value(
+        &mut self,
+        node: Node,
+        env: Rc<RefCell<Environment>>,
+    ) -> EvalResult<RuntimeValue> {
+        Ok(self.eval(Some(node), env)?.unwrap_or_else(undefined))
+    }
+
+    /// ノードを評価する。文は値を持たないことがある
+    ///
+    /// 評価は入れ子の分だけ再帰する。ここではノードの種類でメソッドを選ぶだけにして、
+    /// 種類ごとのローカル変数がこの関数のフレームに積まれないようにする
+    pub fn eval(
+        &mut self,
+        _node: Option<Node>,
+        env: Rc<RefCell<Environment>>,
+    ) -> EvalResult<Option<RuntimeValue>> {
+        let Some(node) = _node else {
+            return Ok(None);
+        };
+        // V8 と同じく、呼び出しの数ではなくスタックの残りで判定する
+        if stack_position() < self.stack_limit {
+            return Err(throw_error(
+                "RangeError",
+                "Maximum call stack size exceeded",
+            ));
+        }
+        let evaluate: Evaluate = match &node {
+            Node::VariableDeclaration { .. } => Self::eval_variable_declaration,
+            Node::AssignmentExpression { .. } => Self::eval_assignment,
+            Node::BlockStatement { .. } => Self::eval_block,
+            Node::IfStatement { .. } => Self::eval_if,
+            Node::WhileStatement { .. }
+            | Node::DoWhileStatement { .. }
+            | Node::ForStatement { .. }
+            | Node::ForOfStatement { .. }
+            | Node::ForInStatement { .. } => |runtime, node, env| runtime.eval_loop(node, &[], env),
+            Node::LabeledStatement { .. } => Self::eval_labeled,
+            Node::SwitchStatement { .. } => Self::eval_switch,
+            Node::BreakStatement { .. }
+            | Node::ContinueStatement { .. }
+            | Node::ReturnStatement { .. }
+            | Node::ThrowStatement { .. } => Self::eval_jump,
+            Node::TryStatement { .. } => Self::eval_try,
+            Node::NewExpression { .. } => Self::eval_new,
+            Node::MemberExpression { .. } => Self::eval_member,
+            Node::CallExpression { .. } => Self::eval_call,
+            Node::ObjectExpression { .. } => Self::eval_object,
+            Node::ArrayExpression { .. } => Self::eval_array,
+            Node::ArrowFunctionExpression { .. } | Node::FunctionExpression { .. } => {
+                Self::eval_function
+            }
+            Node::TemplateLiteral { .. } => Self::eval_template,
+            Node::BinaryExpression { .. } => Self::eval_binary,
+            Node::LogicalExpression { .. } => Self::eval_logical,
+            Node::UnaryExpression { .. } => Self::eval_unary,
+            Node::ConditionalExpression { .. } => Self::eval_conditional,
+            Node::UpdateExpression { .. } => Self::eval_update,
+            Node::SequenceExpression { .. } => Self::eval_sequence,
+            _ => Self::eval_simple,
+        };
+        evaluate(self, node, env)
+    }
+
+    /// 制御を移す文
+    fn eval_jump(
+        &mut self,
+        node: Node,
+        env: Rc<RefCell<Environment>>,
+    ) -> EvalResult<Option<RuntimeValue>> {
+        match node {
+            Node::BreakStatement { label } => Err(Completion::Break(label)),
+            Node::ContinueStatement { label } => Err(Completion::Continue(label)),
+            Node::ReturnStatement { argument } => Err(Completion::Return(Box::new(
+                self.eval_value_or_undefined(argument, env)?,
+            ))),
+            Node::ThrowStatement { argument } => Err(Completion::Throw(Box::new(
+                self.eval_value(*argument, env)?,
+            ))),
+            _ => unreachable!("eval_jump is called with a jump statement"),
+        }
+    }
+
+    /// 式文、識別子、リテラルなど、子を評価しないか 1 つだけ評価するノード
+    fn eval_simple(
+        &mut self,
+        node: Node,
+        env: Rc<RefCell<Environment>>,
+    ) -> EvalResult<Option<RuntimeValue>> {
+        match node {
+            Node::ExpressionStatement { expression } => self.eval(Some(*expression), env),
+            Node::Identifier(name) => self.eval_identifier(&name, &env),
+            Node::NumericLiteral(value) => Ok(Some(RuntimeValue::Number(value))),
+            Node::BigIntLiteral(value) => Ok(Some(RuntimeValue::BigInt(value))),
+            Node::StringLiteral(value) => Ok(Some(RuntimeValue::StringLiteral(value))),
+            Node::BooleanLiteral(value) => Ok(Some(RuntimeValue::Boolean(value))),
+            Node::NullLiteral => Ok(Some(RuntimeValue::Null)),
+            // 空文と関数宣言 (ブロックに入るときに巻き上げ済み) は何もしない
+            _ => Ok(None),
+        }
+    }
+
+    /// 省略できる式。省略されていれば undefined
+    fn eval_value_or_undefined(
+        &mut self,
+        node: Option<Box<Node>>,
+        env: Rc<RefCell<Environment>>,
+    ) -> EvalResult<RuntimeValue> {
+        match node {
+            Some(node) => self.eval_value(*node, env),
+            None => Ok(undefined()),
+        }
+    }
+
+    fn eval_variable_declaration(
+        &mut self,
+        node: Node,
+        env: Rc<RefCell<Environment>>,
+    ) -> EvalResult<Option<RuntimeValue>> {
+        let Node::VariableDeclaration { kind, declarations } = node else {
+            unreachable!("eval_variable_declaration is called with a VariableDeclaration");
+        };
+        for declarator in declarations {
+            let Node::VariableDeclarator { id, init } = declarator else {
+                unreachable!("VariableDeclaration holds VariableDeclarator nodes");
+            };
+            let Node::Identifier(name) = *id else {
+                return Err(throw_error(
+                    "SyntaxError",
+                    "Expected identifier in variable declaration",
+                ));
+            };
+            match (kind.as_str(), init) {
+                // 初期化子のない var は巻き上げた値のまま
+                ("var", None) => {}
+                ("var", Some(init)) => {
+                    let value = self.eval_named(*init, &name, env.clone())?;
+                    env.borrow_mut().set_var(&name, value)?;
+                }
+                (kind, init) => {
+                    let value = match init {
+                        Some(init) => self.eval_named(*init, &name, env.clone())?,
+                        None => undefined(),
+                    };
+                    let kind = if kind == "const" {
+                        BindingKind::Const
+                    } else {
+                        BindingKind::Let
+                    };
+                    env.borrow_mut().initialize(&name, kind, value);
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Reference: https://262.ecma-international.org/#sec-assignment-operators-runtime-semantics-evaluation
+    fn eval_assignment(
+        &mut self,
+        node: Node,
+        env: Rc<RefCell<Environment>>,
+    ) -> EvalResult<Option<RuntimeValue>> {
+        let Node::AssignmentExpression {
+            operator,
+            left,
+            right,
+        } = node
+        else {
+            unreachable!("eval_assignment is called with an AssignmentExpression");
+        };
+        let value = match operator.as_str() {
+            "=" => self.eval_value(*right, env.clone())?,
+            operator => {
+                let current = self.eval_value((*left).clone(), env.clone())?;
+                // 論理代入は左辺で結果が決まれば右辺を評価せず、代入もしない
+                let short_circuit = match operator {
+                    "&&=" => !to_boolean(&current),
+                    "||=" => to_boolean(&current),
+                    "??=" => !is_nullish(&current),
+                    _ => false,
+                };
+                if short_circuit {
+                    return Ok(Some(current));
+                }
+                let right = self.eval_value(*right, env.clone())?;
+                match operator {
+                    "&&=" | "||=" | "??=" => right,
+                    _ => apply_binary_operator(&operator[..operator.len() - 1], current, right)?,
+                }
+            }
+        };
+        self.assign(*left, value.clone(), env)?;
+        Ok(Some(value))
+    }
+
+    fn eval_block(
+        &mut self,
+        node: Node,
+        env: Rc<RefCell<Environment>>,
+    ) -> EvalResult<Option<RuntimeValue>> {
+        let Node::BlockStatement { body } = node else {
+            unreachable!("eval_block is called with a BlockStatement");
+        };
+        let block_env = Rc::new(RefCell::new(Environment::new(Some(env))));
+        declare_lexical(&body, &block_env)?;
+        for statement in body {
+            self.eval(Some(statement), block_env.clone())?;
+        }
+        Ok(None)
+    }
+
+    fn eval_if(
+        &mut self,
+        node: Node,
+        env: Rc<RefCell<Environment>>,
+    ) -> EvalResult<Option<RuntimeValue>> {
+        let Node::IfStatement {
+            test,
+            consequent,
+            alternate,
+        } = node
+        else {
+            unreachable!("eval_if is called with an IfStatement");
+        };
+        let test = self.eval_value(*test, env.clone())?;
+        let branch = if to_boolean(&test) {
+            Some(*consequent)
+        } else {
+            alternate.map(|alternate| *alternate)
+        };
+        self.eval(branch, env)
+    }
+
+    /// 条件に合う方だけを評価する
+    fn eval_conditional(
+        &mut self,
+        node: Node,
+        env: Rc<RefCell<Environment>>,
+    ) -> EvalResult<Option<RuntimeValue>> {
+        let Node::ConditionalExpression {
+            test,
+            consequent,
+            alternate,
+        } = node
+        else {
+            unreachable!("eval_conditional is called with a ConditionalExpression");
+        };
+        let test = self.eval_value(*test, env.clone())?;
+        let branch = if to_boolean(&test) {
+            consequent
+        } else {
+            alternate
+        };
+        Ok(Some(self.eval_value(*branch, env)?))
+    }
+
+    fn eval_labeled(
+        &mut self,
+        node: Node,
+        env: Rc<RefCell<Environment>>,
+    ) -> EvalResult<Option<RuntimeValue>> {
+        let Node::LabeledStatement { label, body } = node else {
+            unreachable!("eval_labeled is called with a LabeledStatement");
+        };
+        // 重なったラベルはすべて同じ文に付く。例: a: b: for (...) {}
+        let mut labels = vec![label];
+        let mut body = *body;
+        while let Node::LabeledStatement { label, body: inner } = body {
+            labels.push(label);
+            body = *inner;
+        }
+        let result = match body {
+            node @ (Node::WhileStatement { .. }
+            | Node::DoWhileStatement { .. }
+            | Node::ForStatement { .. }
+            | Node::ForOfStatement { .. }
+            | Node::ForInStatement { .. }) => self.eval_loop(node, &labels, env),
+            body => self.eval(Some(body), env),
+        };
+        match result {
+            Err(Completion::Break(Some(label))) if labels.contains(&label) => Ok(None),
+            result => result,
+        }
+    }
+
+    /// 一致する case か default から、break するまで後の節も続けて実行する
+    /// Reference: https://262.ecma-international.org/#sec-switch-statement
+    fn eval_switch(
+        &mut self,
+        node: Node,
+        env: Rc<RefCell<Environment>>,
+    ) -> EvalResult<Option<RuntimeValue>> {
+        let Node::SwitchStatement {
+            discriminant,
+            cases,
+        } = node
+        else {
+            unreachable!("eval_switch is called with a SwitchStatement");
+        };
+        let discriminant = self.eval_value(*discriminant, env.clone())?;
+        let block_env = Rc::new(RefCell::new(Environment::new(Some(env))));
+        let statements: Vec<Node> = cases
+            .iter()
+            .flat_map(|case| match case {
+                Node::SwitchCase { consequent, .. } => consequent.clone(),
+                _ => Vec::new(),
+            })
+            .collect();
+        declare_lexical(&statements, &block_env)?;
+
+        let mut start = None;
+        for (index, case) in cases.iter().enumerate() {
+            if let Node::SwitchCase {
+                test: Some(test), ..
+            } = case
+                && self.eval_value((**test).clone(), block_env.clone())? == discriminant
+            {
+                start = Some(index);
+                break;
+            }
+        }
+        let start = start.or_else(|| {
+            cases
+                .iter()
+                .position(|case| matches!(case, Node::SwitchCase { test: None, .. }))
+        });
+        let Some(start) = start else {
+            return Ok(None);
+        };
+        for case in cases.into_iter().skip(start) {
+            let Node::SwitchCase { consequent, .. } = case else {
+                unreachable!("SwitchStatement holds SwitchCase nodes");
+            };
+            for statement in consequent {
+                match self.eval(Some(statement), block_env.clone()) {
+                    Err(Completion::Break(None)) => return Ok(None),
+                    result => result?,
+                };
+            }
+        }
+        Ok(None)
+    }
+
+    /// Reference: https://262.ecma-international.org/#sec-try-statement
+    fn eval_try(
+        &mut self,
+        node: Node,
+        env: Rc<RefCell<Environment>>,
+    ) -> EvalResult<Option<RuntimeValue>> {
+        let Node::TryStatement {
+            block,
+            param,
+            handler,
+            finalizer,
+        } = node
+        else {
+            unreachable!("eval_try is called with a TryStatement");
+        };
+        let mut result = self.eval(Some(*block), env.clone());
+        if let Some(handler) = handler
+            && let Err(Completion::Throw(exception)) = &result
+        {
+            let catch_env = Rc::new(RefCell::new(Environment::new(Some(env.clone()))));
+            if let Some(param) = param {
+                catch_env
+                    .borrow_mut()
+                    .define_var(param, exception.as_ref().clone());
+            }
+            result = self.eval(Some(*handler), catch_env);
+        }
+        // finally の中で例外や return が起きた場合はそちらが優先される
+        if let Some(finalizer) = finalizer {
+            self.eval(Some(*finalizer), env)?;
+        }
+        result.map(|_| None)
+    }
+
+    fn eval_new(
+        &mut self,
+        node: Node,
+        env: Rc<RefCell<Environment>>,
+    ) -> EvalResult<Option<RuntimeValue>> {
+        let Node::NewExpression { callee, arguments } = node else {
+            unreachable!("eval_new is called with a NewExpression");
+        };
+        let text = callee_text(&callee);
+        let callee = self.eval_value(*callee, env.clone())?;
+        let args = self.eval_arguments(arguments, env)?;
+        if let RuntimeValue::NativeFunction(function) = &callee
+            && let Some(construct) = function.construct
+        {
+            return construct(self, undefined(), args).map(Some);
+        }
+        Err(throw_error(
+            "TypeError",
+            format!(
+                "{} is not a constructor",
+                text.unwrap_or_else(|| callee.to_string())
+            ),
+        ))
+    }
+
+    fn eval_member(
+        &mut self,
+        node: Node,
+        env: Rc<RefCell<Environment>>,
+    ) -> EvalResult<Option<RuntimeValue>> {
+        let Node::MemberExpression {
+            object,
+            property,
+            computed,
+        } = node
+        else {
+            unreachable!("eval_member is called with a MemberExpression");
+        };
+        let object = self.eval_value(*object, env.clone())?;
+        let key = self.property_key(*property, computed, env)?;
+        self.get_property(&object, &key).map(Some)
+    }
+
+    fn eval_call(
+        &mut self,
+        node: Node,
+        env: Rc<RefCell<Environment>>,
+    ) -> EvalResult<Option<RuntimeValue>> {
+        let Node::CallExpression { callee, arguments } = node else {
+            unreachable!("eval_call is called with a CallExpression");
+        };
+        // localな環境を作成
+        let local_env = Rc::new(RefCell::new(Environment::new(Some(env))));
+        let text = callee_text(&callee);
+        // obj.method() は obj を this にして、obj かそのプロトタイプのメソッドを呼び出す
+        let (this, function) = match *callee {
+            Node::MemberExpression {
+                object,
+                property,
+                computed,
+            } => {
+                let this = self.eval_value(*object, local_env.clone())?;
+                let key = self.property_key(*property, computed, local_env.clone())?;
+                let function = self.get_property(&this, &key)?;
+                (this, function)
+            }
+            callee => (undefined(), self.eval_value(callee, local_env.clone())?),
+        };
+        if !is_callable(&function) {
+            return Err(throw_error(
+                "TypeError",
+                format!(
+                    "{} is not a function",
+                    text.unwrap_or_else(|| function.to_string())
+                ),
+            ));
+        }
+        let args = self.eval_arguments(arguments, local_env)?;
+        self.call(&function, this, args).map(Some)
+    }
+
+    /// Reference: https://262.ecma-international.org/#sec-object-initializer-runtime-semantics-evaluation
+    fn eval_object(
+        &mut self,
+        node: Node,
+        env: Rc<RefCell<Environment>>,
+    ) -> EvalResult<Option<RuntimeValue>> {
+        let Node::ObjectExpression { properties } = node else {
+            unreachable!("eval_object is called with an ObjectExpression");
+        };
+        let mut object = Object::default();
+        for property in properties {
+            let Node::Property { key, value } = property else {
+                unreachable!("ObjectExpression holds Property nodes");
+            };
+            let key = self.eval_value(*key, env.clone())?.to_string();
+            let value = self.eval_value(*value, env.clone())?;
+            // __proto__: value はプロパティを作らずにプロトタイプを決める
+            if key == "__proto__" {
+                if let RuntimeValue::Object(prototype) = value {
+                    object.set_prototype(Some(prototype));
+                }
+                continue;
+            }
+            object.set(key, value);
+        }
+        Ok(Some(RuntimeValue::Object(Shared::new(object))))
+    }
+
+    fn eval_identifier(
+        &self,
+        name: &str,
+        env: &Rc<RefCell<Environment>>,
+    ) -> EvalResult<Option<RuntimeValue>> {
+        match self.resolve_binding(name, env) {
+            Some(value) => Ok(Some(value?)),
+            None => Err(throw_error(
+                "ReferenceError",
+                format!("{} is not defined", name),
+            )),
+        }
+    }
+
+    /// 省略された要素は穴になる
+    fn eval_array(
+        &mut self,
+        node: Node,
+        env: Rc<RefCell<Environment>>,
+    ) -> EvalResult<Option<RuntimeValue>> {
+        let Node::ArrayExpression { elements } = node else {
+            unreachable!("eval_array is called with an ArrayExpression");
+        };
+        let mut items = Vec::new();
+        for element in elements {
+            items.push(match element {
+                Some(element) => Some(self.eval_value(element, env.clone())?),
+                None => None,
+            });
+        }
+        Ok(Some(RuntimeValue::Array(Shared::new(items))))
+    }
+
+    /// 関数式とアロー関数。その時点の環境を閉じ込める
+    fn eval_function(
+        &mut self,
+        node: Node,
+        env: Rc<RefCell<Environment>>,
+    ) -> EvalResult<Option<RuntimeValue>> {
+        let (id, params, body) = match node {
+            Node::FunctionExpression { id, params, body } => (id, params, body),
+            Node::ArrowFunctionExpression { params, body } => (None, params, body),
+            _ => unreachable!("eval_function is called with a function expression"),
+        };
+        let Some(name) = id else {
+            return Ok(Some(RuntimeValue::Function(Function {
+                name: None,
+                params,
+                body: Rc::new(*body),
+                env,
+            })));
+        };
+        // 名前付き関数式の名前は関数の中からだけ見える
+        let function_env = Rc::new(RefCell::new(Environment::new(Some(env))));
+        let function = RuntimeValue::Function(Function {
+            name: Some(name.clone()),
+            params,
+            body: Rc::new(*body),
+            env: function_env.clone(),
+        });
+        function_env
+            .borrow_mut()
+            .initialize(&name, BindingKind::Const, function.clone());
+        Ok(Some(function))
+    }
+
+    fn eval_template(
+        &mut self,
+        node: Node,
+        env: Rc<RefCell<Environment>>,
+    ) -> EvalResult<Option<RuntimeValue>> {
+        let Node::TemplateLiteral {
+            quasis,
+            expressions,
+        } = node
+        else {
+            unreachable!("eval_template is called with a TemplateLiteral");
+        };
+        let mut quasis = quasis.into_iter();
+        let mut result = quasis.next().unwrap_or_default();
+        for (expression, quasi) in expressions.into_iter().zip(quasis) {
+            let value = self.eval_value(expression, env.clone())?;
+            result.push_str(&self.primitive_value(value, "string")?.to_string());
+            result.push_str(&quasi);
+        }
+        Ok(Some(RuntimeValue::StringLiteral(result)))
+    }
+
+    fn eval_binary(
+        &mut self,
+        node: Node,
+        env: Rc<RefCell<Environment>>,
+    ) -> EvalResult<Option<RuntimeValue>> {
+        let Node::BinaryExpression {
+            operator,
+            left,
+            right,
+        } = node
+        else {
+            unreachable!("eval_binary is called with a BinaryExpression");
+        };
+        let left = self.eval_value(*left, env.clone())?;
+        let right = self.eval_value(*right, env)?;
+        let (left, right) = self.binary_operands(&operator, left, right)?;
+        Ok(Some(evaluate_binary_expression(&operator, left, right)?))
+    }
+
+    /// 右辺は必要なときだけ評価する
+    fn eval_logical(
+        &mut self,
+        node: Node,
+        env: Rc<RefCell<Environment>>,
+    ) -> EvalResult<Option<RuntimeValue>> {
+        let Node::LogicalExpression {
+            operator,
+            left,
+            right,
+        } = node
+        else {
+            unreachable!("eval_logical is called with a LogicalExpression");
+        };
+        let left = self.eval_value(*left, env.clone())?;
+        let short_circuit = match operator.as_str() {
+            "&&" => !to_boolean(&left),
+            "||" => to_boolean(&left),
+            _ => !is_nullish(&left),
+        };
+        if short_circuit {
+            Ok(Some(left))
+        } else {
+            Ok(Some(self.eval_value(*right, env)?))
+        }
+    }
+
+    fn eval_unary(
+        &mut self,
+        node: Node,
+        env: Rc<RefCell<Environment>>,
+    ) -> EvalResult<Option<RuntimeValue>> {
+        let Node::UnaryExpression { operator, argument } = node else {
+            unreachable!("eval_unary is called with a UnaryExpression");
+        };
+        // 宣言されていない変数の typeof は ReferenceError ではなく "undefined"
+        if let Node::Identifier(name) = argument.as_ref()
+            && operator == "typeof"
+            && self.resolve_binding(name, &env).is_none()
+        {
+            return Ok(Some(RuntimeValue::StringLiteral("undefined".to_string())));
+        }
+        let mut argument = self.eval_value(*argument, env)?;
+        if operator == "+" || operator == "-" {
+            argument = self.primitive_value(argument, "number")?;
+        }
+        Ok(Some(evaluate_unary_expression(&operator, argument)?))
+    }
+
+    /// Reference: https://262.ecma-international.org/#sec-update-expressions
+    fn eval_update(
+        &mut self,
+        node: Node,
+        env: Rc<RefCell<Environment>>,
+    ) -> EvalResult<Option<RuntimeValue>> {
+        let Node::UpdateExpression {
+            operator,
+            prefix,
+            argument,
+        } = node
+        else {
+            unreachable!("eval_update is called with an UpdateExpression");
+        };
+        let old = self.eval_value((*argument).clone(), env.clone())?;
+        let old = to_numeric(self.primitive_value(old, "number")?);
+        let one = match old {
+            RuntimeValue::BigInt(_) => RuntimeValue::BigInt(BigInt::from_digits("1", 10)),
+            _ => RuntimeValue::Number(1.0),
+        };
+        let new = apply_binary_operator(&operator[..1], old.clone(), one)?;
+        self.assign(*argument, new.clone(), env)?;
+        Ok(Some(if prefix { new } else { old }))
+    }
+
+    fn eval_sequence(
+        &mut self,
+        node: Node,
+        env: Rc<RefCell<Environment>>,
+    ) -> EvalResult<Option<RuntimeValue>> {
+        let Node::SequenceExpression { expressions } = node else {
+            unreachable!("eval_sequence is called with a SequenceExpression");
+        };
+        let mut value = undefined();
+        for expression in expressions {
+            value = self.eval_value(expression, env.clone())?;
+        }
+        Ok(Some(value))
+    }
+
+    /// ループを実行する。labels はこのループに付いたラベルで、continue の飛び先になる
+    /// Reference: https://262.ecma-international.org/#sec-iteration-statements
+    fn eval_loop(
+        &mut self,
+        node: Node,
+        labels: &[String],
+        env: Rc<RefCell<Environment>>,
+    ) -> EvalResult<Option<RuntimeValue>> {
+        match node {
+            Node::WhileStatement { test, body } => {
+                while to_boolean(&self.eval_value((*test).clone(), env.clone())?) {
+                    let result = self.eval(Some((*body).clone()), env.clone());
+                    if !loop_continues(result, labels)? {
+                        break;
+                    }
+                }
+            }
+            Node::DoWhileStatement { body, test } => loop {
+                let result = self.eval(Some((*body).clone()), env.clone());
+                if !loop_continues(result, labels)?
+                    || !to_boolean(&self.eval_value((*test).clone(), env.clone())?)
+                {
+                    break;
+                }
+            },
+            Node::ForStatement {
+                init,
+                test,
+                update,
+                body,
+            } => {
+                let loop_env = Rc::new(RefCell::new(Environment::new(Some(env))));
+                if let Some(init) = init {
+                    declare_lexical(std::slice::from_ref(init.as_ref()), &loop_env)?;
+                    self.eval(Some(*init), loop_env.clone())?;
+                }
+                let mut iteration_env =
+                    Rc::new(RefCell::new(loop_env.borrow().copy_for_iteration()));
+                loop {
+                    if let Some(test) = &test {
+                        let test = self.eval_value((**test).clone(), iteration_env.clone())?;
+                        if !to_boolean(&test) {
+                            break;
+                        }
+                    }
+                    let result = self.eval(Some((*body).clone()), iteration_env.clone());
+                    if !loop_continues(result, labels)? {
+                        break;
+                    }
+                    let next_env = iteration_env.borrow().copy_for_iteration();
+                    iteration_env = Rc::new(RefCell::new(next_env));
+                    if let Some(update) = &update {
+                        self.eval(Some((**update).clone()), iteration_env.clone())?;
+                    }
+                }
+            }
+            Node::ForOfStatement { left, right, body } => {
+                let right = self.eval_value(*right, env.clone())?;
+                for item in iterate(right)? {
+                    if !self.eval_for_iteration(&left, item, &body, labels, env.clone())? {
+                        break;
+                    }
+                }
+            }
+            Node::ForInStatement { left, right, body } => {
+                let right = self.eval_value(*right, env.clone())?;
+                for key in enumerable_keys(&right) {
+                    let key = RuntimeValue::StringLiteral(key);
+                    if !self.eval_for_iteration(&left, key, &body, labels, env.clone())? {
+                        break;
+                    }
+                }
+            }
+            _ => unreachable!("eval_loop is called with an iteration statement"),
+        }
+        Ok(None)
+    }
+
+    /// for...of と for...in の1回分。繰り返しごとに新しい環境で left に値を束縛する
+    fn eval_for_iteration(
+        &mut self,
+        left: &Node,
+        value: RuntimeValue,
+        body: &Node,
+        labels: &[String],
+        env: Rc<RefCell<Environment>>,
+    ) -> EvalResult<bool> {
+        let iteration_env = Rc::new(RefCell::new(Environment::new(Some(env))));
+        match left {
+            Node::VariableDeclaration { kind, declarations } => {
+                let name = declared_names(declarations)
+                    .next()
+                    .expect("for...of declares a single binding");
+                match kind.as_str() {
+                    "var" => iteration_env.borrow_mut().set_var(name, value)?,
+                    "const" => {
+                        iteration_env
+                            .borrow_mut()
+                            .initialize(name, BindingKind::Const, value)
+                    }
+                    _ => iteration_env
+                        .borrow_mut()
+                        .initialize(name, BindingKind::Let, value),
+                }
+            }
+            target => self.assign(target.clone(), value, iteration_env.clone())?,
+        }
+        let result = self.eval(Some(body.clone()), iteration_env);
+        loop_continues(result, labels)
+    }
+
+    /// 代入先の変数やプロパティに値を書き込む
+    fn assign(
+        &mut self,
+        target: Node,
+        value: RuntimeValue,
+        env: Rc<RefCell<Environment>>,
+    ) -> EvalResult<()> {
+        match target {
+            // 宣言されていなければグローバルオブジェクトのプロパティに書き込む
+            Node::Identifier(name)
+                if env.borrow().get_var(&name).is_none()
+                    && self.global.borrow().get_own(&name).is_some() =>
+            {
+                self.global.borrow_mut().set(name, value);
+                Ok(())
+            }
+            Node::Identifier(name) => env.borrow_mut().set_var(&name, value),
+            // オブジェクトは参照なので、const の変数が指すオブジェクトも書き換えられる
+            Node::MemberExpression {
+                object,
+                property,
+                computed,
+            } => {
+                let object = self.eval_value(*object, env.clone())?;
+                let key = self.property_key(*property, computed, env)?;
+                set_property(&object, key, value)
+            }
+            _ => Err(throw_error(
+                "SyntaxError",
+                "Invalid left-hand side in assignment",
+            )),
+        }
+    }
+
+    fn eval_arguments(
+        &mut self,
+        arguments: Vec<Node>,
+        env: Rc<RefCell<Environment>>,
+    ) -> EvalResult<Vec<RuntimeValue>> {
+        arguments
+            .into_iter()
+            .map(|arg| self.eval_value(arg, env.clone()))
+            .collect()
+    }
+
+    /// 初期化子が無名関数なら、変数の名前を関数の名前にする
+    /// Reference: https://262.ecma-international.org/#sec-runtime-semantics-namedevaluation
+    fn eval_named(
+        &mut self,
+        init: Node,
+        name: &str,
+        env: Rc<RefCell<Environment>>,
+    ) -> EvalResult<RuntimeValue> {
+        let anonymous = matches!(
+            init,
+            Node::ArrowFunctionExpression { .. } | Node::FunctionExpression { id: None, .. }
+        );
+        let mut value = self.eval_value(init, env)?;
+        if anonymous && let RuntimeValue::Function(function) = &mut value {
+            function.name = Some(name.to_string());
+        }
+        Ok(value)
+    }
+
+    /// a.b の b は変数として解決しない。a[b] の b は評価して文字列にする
+    fn property_key(
+        &mut self,
+        property: Node,
+        computed: bool,
+        env: Rc<RefCell<Environment>>,
+    ) -> EvalResult<String> {
+        match property {
+            Node::Identifier(name) if !computed => Ok(name),
+            property => Ok(self.eval_value(property, env)?.to_string()),
+        }
+    }
+
+    /// プロパティを読む。自身になければプロトタイプをたどり、最後まで無ければ undefined
+    /// Reference: https://262.ecma-international.org/#sec-getv
+    fn get_property(&self, object: &RuntimeValue, key: &str) -> EvalResult<RuntimeValue> {
+        let value = match object {
+            object if is_nullish(object) => {
+                return Err(throw_error(
+                    "TypeError",
+                    format!("Cannot read properties of {} (reading '{}')", object, key),
+                ));
+            }
+            RuntimeValue::Locale(locale) => Some(locale_property(locale, key)),
+            RuntimeValue::StringLiteral(value) => string_property(value, key),
+            RuntimeValue::Array(items) => {
+                array_property(items, key).or_else(|| self.array_prototype.borrow().get(key))
+            }
+            RuntimeValue::Object(object) | RuntimeValue::Error(object) => object.borrow().get(key),
+            RuntimeValue::MessageFormatInstance(_) => {
+                self.message_format_prototype.borrow().get(key)
+            }
+            _ => None,
+        };
+        Ok(value.unwrap_or_else(undefined))
+    }
+
+    /// 関数を呼び出す。this は組み込み関数にだけ渡す
+    /// Reference: https://262.ecma-international.org/#sec-call
+    fn call(
+        &mut self,
+        function: &RuntimeValue,
+        this: RuntimeValue,
+        args: Vec<RuntimeValue>,
+    ) -> EvalResult<RuntimeValue> {
+        match function {
+            RuntimeValue::Function(function) => self.call_function(function, args),
+            RuntimeValue::NativeFunction(function) => (function.call)(self, this, args),
+            function => Err(throw_error(
+                "TypeError",
+                format!("{} is not a function", function),
+            )),
+        }
+    }
+
+    /// new Intl.MessageFormat(locales, source, options)
+    /// Reference: https://github.com/tc39/proposal-intl-messageformat#constructor
+    fn construct_message_format(
+        &mut self,
+        _: RuntimeValue,
+        args: Vec<RuntimeValue>,
+    ) -> EvalResult<RuntimeValue> {
+        let Some(RuntimeValue::StringLiteral(message)) = args.get(1) else {
+            return Err(throw_error(
+                "TypeError",
+                "Intl.MessageFormat source must be a string",
+            ));
+        };
+        // locales は文字列か文字列の配列
+        let requested = match args.first() {
+            // 穴は飛ばす
+            Some(RuntimeValue::Array(items)) => items
+                .borrow()
+                .iter()
+                .flatten()
+                .map(|item| item.to_string())
+                .collect(),
+            None | Some(RuntimeValue::Undefined) => Vec::new(),
+            Some(locale) => vec![locale.to_string()],
+        };
+        let options = match args.get(2) {
+            Some(RuntimeValue::Object(options)) => options.borrow().to_map(),
+            _ => HashMap::new(),
+        };
+        let locale = resolve_locale(&requested, locale_matcher_option(&options)?)
+            .map_err(|error| throw_error("RangeError", error))?;
+        // カスタム関数は同名の組み込み関数より優先する
+        let functions = functions_option(&options)?;
+        let mut registry = self.functions.clone();
+        for name in functions.keys() {
+            registry.register_custom(name);
+        }
+        // 構文エラーは format() ではなく構築時に報告する
+        let compiled = CompiledMessage::compile(message, &registry)
+            .map_err(|error| throw_error("SyntaxError", error.message(message)))?;
+        Ok(RuntimeValue::MessageFormatInstance(Rc::new(
+            MessageFormatInstance::new(
+                format_options(&options, locale)?,
+                message,
+                compiled,
+                functions,
+            ),
+        )))
+    }
+
+    /// Array.prototype のメソッドを呼び出す。push と pop は array を書き換える
+    /// Reference: https://262.ecma-international.org/#sec-properties-of-the-array-prototype-object
+    fn call_array_method(
+        &mut self,
+        method: &str,
+        this: RuntimeValue,
+        args: Vec<RuntimeValue>,
+    ) -> EvalResult<RuntimeValue> {
+        let RuntimeValue::Array(array) = &this else {
+            return Err(throw_error(
+                "TypeError",
+                format!("Array.prototype.{} called on non-array {}", method, this),
+            ));
+        };
+        let mut args = args.into_iter();
+        match method {
+            "push" => {
+                let mut items = array.borrow_mut();
+                items.extend(args.map(Some));
+                Ok(RuntimeValue::Number(items.len() as f64))
+            }
+            "pop" => Ok(array.borrow_mut().pop().flatten().unwrap_or_else(undefined)),
+            "join" => {
+                let separator = match args.next() {
+                    Some(separator) if separator != undefined() => separator.to_string(),
+                    _ => ",".to_string(),
+                };
+                Ok(RuntimeValue::StringLiteral(join_items(array, &separator)))
+            }
+            "slice" => {
+                let items = array.borrow();
+                let start = relative_index(args.next(), items.len(), 0)?;
+                let end = relative_index(args.next(), items.len(), items.len())?;
+                Ok(RuntimeValue::Array(Shared::new(
+                    items[start..end.max(start)].to_vec(),
+                )))
+            }
+            "includes" => {
+                let items = array.borrow();
+                let target = args.next().unwrap_or_else(undefined);
+                let start = relative_index(args.next(), items.len(), 0)?;
+                Ok(RuntimeValue::Boolean(items[start..].iter().any(|item| {
+                    same_value_zero(item.as_ref().unwrap_or(&undefined()), &target)
+                })))
+            }
+            // コールバックは (要素, 添字, 配列) で呼び出す。コールバックが配列を書き換えても、
+            // 呼び出す前の要素を順にたどる。穴では呼ばず、map の結果でも穴のままにする
+            "map" | "filter" | "forEach" => {
+                let callback = callback_function(args.next())?;
+                let items = array.borrow().clone();
+                let mut result = Vec::new();
+                for (index, item) in items.into_iter().enumerate() {
+                    let Some(item) = item else {
+                        if method == "map" {
+                            result.push(None);
+                        }
+                        continue;
+                    };
+                    let args = vec![
+                        item.clone(),
+                        RuntimeValue::Number(index as f64),
+                        RuntimeValue::Array(array.clone()),
+                    ];
+                    let value = self.call(&callback, undefined(), args)?;
+                    match method {
+                        "map" => result.push(Some(value)),
+                        "filter" if to_boolean(&value) => result.push(Some(item)),
+                        _ => {}
+                    }
+                }
+                Ok(match method {
+                    "forEach" => undefined(),
+                    _ => RuntimeValue::Array(Shared::new(result)),
+                })
+            }
+            // 初期値がなければ最初の要素から始める。穴は飛ばす
+            "reduce" => {
+                let callback = callback_function(args.next())?;
+                let items = array.borrow().clone();
+                let mut items = items
+                    .into_iter()
+                    .enumerate()
+                    .filter_map(|(index, item)| Some((index, item?)));
+                let mut accumulator =
+                    match args.next().or_else(|| items.next().map(|(_, first)| first)) {
+                        Some(initial) => initial,
+                        None => {
+                            return Err(throw_error(
+                                "TypeError",
+                                "Reduce of empty array with no initial value",
+                            ));
+                        }
+                    };
+                for (index, item) in items {
+                    let args = vec![
+                        accumulator,
+                        item,
+                        RuntimeValue::Number(index as f64),
+                        RuntimeValue::Array(array.clone()),
+                    ];
+                    accumulator = self.call(&callback, undefined(), args)?;
+                }
+                Ok(accumulator)
+            }
+            _ => unreachable!("unknown array method {}", method),
+        }
+    }
+
+    /// 仮引数を束縛した環境で関数本体を評価する。足りない引数は undefined
+    fn call_function(
+        &mut self,
+        function: &Function,
+        args: Vec<RuntimeValue>,
+    ) -> EvalResult<RuntimeValue> {
+        let env = Rc::new(RefCell::new(Environment::new(Some(function.env.clone()))));
+        let mut args = args.into_iter();
+        for param in &function.params {
+            match param {
+                Node::Identifier(name) => {
+                    let value = args.next().unwrap_or_else(undefined);
+                    env.borrow_mut().define_var(name.clone(), value);
+                }
+                // 引数が undefined のときだけ既定値を使う。既定値は前の仮引数を参照できる
+                Node::AssignmentPattern { left, right } => {
+                    let Node::Identifier(name) = left.as_ref() else {
+                        continue;
+                    };
+                    let value = match args.next() {
+                        Some(value) if value != undefined() => value,
+                        _ => self.eval_value(right.as_ref().clone(), env.clone())?,
+                    };
+                    env.borrow_mut().define_var(name.clone(), value);
+                }
+                // 残りの引数をすべて配列にまとめる
+                Node::RestElement { argument } => {
+                    let Node::Identifier(name) = argument.as_ref() else {
+                        continue;
+                    };
+                    let rest = new_array(args.by_ref().collect());
+                    env.borrow_mut().define_var(name.clone(), rest);
+                }
+                _ => {}
+            }
+        }
+
+        let body = function.body.as_ref();
+        declare_var(body, &env)?;
+        match self.eval(Some(body.clone()), env) {
+            Err(Completion::Return(value)) => Ok(*value),
+            Err(exception) => Err(exception),
+            // return せずに終わったブロックは undefined を返す
+            Ok(_) if matches!(body, Node::BlockStatement { .. }) => Ok(undefined()),
+            // 式が本体の場合はその値を返す
+            Ok(value) => Ok(value.unwrap_or_else(undefined)),
+        }
+    }
+
+    /// MF2 のカスタム関数を (context, options, input) で呼び出す
+    /// Reference: https://github.com/tc39/proposal-intl-messageformat#messagefunction
+    fn call_custom_function(
+        &mut self,
+        instance: &MessageFormatInstance,
+        name: &str,
+        operand: Option<&MessageValue>,
+        options: &HashMap<String, MessageValue>,
+    ) -> EvalResult<RuntimeValue> {
+        let function = &instance.functions[name];
+        let context = new_object([
+            (
+                "locales".to_string(),
+                new_array(vec![RuntimeValue::StringLiteral(
+                    instance.options.locale.to_string(),
+                )]),
+            ),
+            (
+                "dir".to_string(),
+                RuntimeValue::StringLiteral(instance.options.dir.as_str().to_string()),
+            ),
+        ]);
+        // オプションは名前順に並べる
+        let mut options: Vec<_> = options
+            .iter()
+            .map(|(name, value)| (name.clone(), RuntimeValue::from(value)))
+            .collect();
+        options.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let input = match operand {
+            Some(value) => RuntimeValue::from(value),
+            None => undefined(),
+        };
+        self.call_function(function, vec![context, new_object(options), input])
+    }
+
+    /// Intl.MessageFormat.prototype.resolvedOptions()
+    fn message_format_resolved_options(
+        &mut self,
+        this: RuntimeValue,
+        _: Vec<RuntimeValue>,
+    ) -> EvalResult<RuntimeValue> {
+        let instance = this_message_format(this, "resolvedOptions")?;
+        let resolved = &instance.options;
+        // 組み込み関数とカスタム関数の名前の和集合
+        let mut names: Vec<&str> = self.functions.names();
+        names.extend(instance.functions.keys().map(String::as_str));
+        names.sort();
+        names.dedup();
+        let functions = names
+            .into_iter()
+            .map(|name| RuntimeValue::StringLiteral(name.to_string()))
+            .collect();
+        Ok(new_object([
+            (
+                "locale".to_string(),
+                RuntimeValue::StringLiteral(resolved.locale.to_string()),
+            ),
+            (
+                "bidiIsolation".to_string(),
+                RuntimeValue::StringLiteral(resolved.bidi_isolation.as_str().to_string()),
+            ),
+            (
+                "dir".to_string(),
+                RuntimeValue::StringLiteral(resolved.dir.as_str().to_string()),
+            ),
+            ("functions".to_string(), new_array(functions)),
+        ]))
+    }
+
+    /// Intl.MessageFormat.prototype.format(values, onError)
+    fn message_format_format(
+        &mut self,
+        this: RuntimeValue,
+        args: Vec<RuntimeValue>,
+    ) -> EvalResult<RuntimeValue> {
+        let instance = this_message_format(this, "format")?;
+        let mut args = args.into_iter();
+        let variables = match args.next() {
+            Some(RuntimeValue::Object(object)) => message_arguments(&object.borrow()),
+            _ => HashMap::new(),
+        };
+        let on_error = match args.next() {
+            None => None,
+            Some(value) if value == undefined() => None,
+            Some(value) if is_callable(&value) => Some(value),
+            Some(value) => {
+                return Err(throw_error(
+                    "TypeError",
+                    format!("{} is not a function", value),
+                ));
+            }
+        };
+
+        // コンパイル済みのメッセージをvariablesを使ってフォーマット
+        // カスタム関数が投げた例外は function-error としてフォールバックし、エラーの順に取っておく
+        let mut exceptions = Vec::new();
+        let (output, errors) = instance.message.format_with(
+            &instance.options,
+            &variables,
+            &mut |name, operand, options| match self
+                .call_custom_function(&instance, name, operand, options)
+            {
+                Ok(RuntimeValue::Undefined) => Err(FunctionError::BadResult),
+                Ok(value) => Ok(value.to_message_value()),
+                Err(completion) => {
+                    exceptions.push(completion);
+                    Err(FunctionError::Failed)
+                }
+            },
+        );
+        let mut exceptions = exceptions.into_iter();
+        // 解決・フォーマットのエラーは onError に渡す。onError がなければ最初のエラーを投げる
+        for error in errors {
+            let exception = match error.kind {
+                FormatErrorKind::FunctionError(_) => exceptions.next(),
+                _ => None,
+            };
+            match &on_error {
+                Some(on_error) => {
+                    let cause = match exception {
+                        Some(Completion::Throw(value)) => Some(*value),
+                        _ => None,
+                    };
+                    let error = message_error(&error, &instance.source, cause);
+                    self.call(on_error, undefined(), vec![error])?;
+                }
+                // 関数が投げた例外はそのまま投げ直す
+                None => {
+                    return Err(exception
+                        .unwrap_or_else(|| throw_error("Error", error.message(&instance.source))));
+                }
+            }
+        }
+        Ok(RuntimeValue::StringLiteral(output))
+    }
+
+    fn call_catalog_format(&mut self, args: Vec<RuntimeValue>) -> EvalResult<String> {
+        let mut args = args.into_iter();
+        let id = args.next().map(|id| id.to_string()).unwrap_or_default();
+        let locale = args
+            .next()
+            .map(|locale| locale.to_string())
+            .unwrap_or_default();
+        let variables = match args.next() {
+            Some(RuntimeValue::Object(object)) => message_arguments(&object.borrow()),
+            _ => HashMap::new(),
+        };
+
+        match self.catalog.format(&id, &locale, &variables) {
+            Some((output, errors)) => {
+                for error in errors {
+                    eprintln!("Warning: {}", error);
+                }
+                Ok(output)
+            }
+            None => {
+                // メッセージが見つからない場合はIDをそのまま返す
+                eprintln!("Warning: missing message: {}", id);
+                Ok(id)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    /// スクリプトを実行し、最後の文の値か捕捉されなかった例外を文字列で返す
+    /// main と同じく、大きなスタックを持つスレッドで実行する
+    fn run(source: &str) -> String {
+        let source = source.to_string();
+        std::thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn(move || {
+                let program = Parser::new(&source).parse().unwrap();
+                match Runtime::new(Catalog::new("en")).run(program) {
+                    Ok(value) => value.unwrap_or_else(undefined).to_string(),
+                    Err(exception) => format!("Uncaught {}", exception),
+                }
+            })
+            .unwrap()
+            .join()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_message_format_options() {
+        let source = r#"
+            const mf = new Intl.MessageFormat("en", "{$x :shout}!", {
+                bidiIsolation: "none",
+                functions: { shout: (ctx, options, input) => input + "!!" },
+            });
+            const options = mf.resolvedOptions();
+        "#;
+        assert_eq!(
+            run(&format!("{} mf.format({{ x: \"hi\" }});", source)),
+            "hi!!!"
+        );
+        assert_eq!(run(&format!("{} options.bidiIsolation;", source)), "none");
+        assert_eq!(
+            run(&format!(
+                "{} options.functions.includes(\"shout\");",
+                source
+            )),
+            "true"
+        );
+        assert_eq!(
+            run(r#"new Intl.MessageFormat("en", "{$x}", { functions: 1 });"#),
+            "Uncaught TypeError: Intl.MessageFormat options property functions must be an object, got 1"
+        );
+        assert_eq!(
+            run(r#"new Intl.MessageFormat("en", "{$x}", { functions: { f: 1 } });"#),
+            "Uncaught TypeError: MessageFormat function :f is not a function"
+        );
+    }
+
+    #[test]
+    fn test_custom_function_errors() {
+        let source = r#"
+            const mf = new Intl.MessageFormat("en", "a {$x :boom} b {$y :none}", {
+                bidiIsolation: "none",
+                functions: {
+                    boom: () => { throw new TypeError("kaboom"); },
+                    none: () => undefined,
+                },
+            });
+            const errors = [];
+            const output = mf.format({ x: 1, y: 2 }, (error) => errors.push(error));
+        "#;
+        // 例外を投げた関数と undefined を返した関数はフォールバックして onError に渡す
+        assert_eq!(run(&format!("{} output;", source)), "a {$x} b {$y}");
+        assert_eq!(
+            run(&format!(
+                "{} errors.map((e) => e.type + \" \" + e.start).join();",
+                source
+            )),
+            "function-error 2,bad-function-result 15"
+        );
+        assert_eq!(
+            run(&format!("{} errors[0].cause.message;", source)),
+            "kaboom"
+        );
+        // onError がなければ関数が投げた例外をそのまま投げる
+        assert_eq!(
+            run(&format!("{} mf.format({{ x: 1 }});", source)),
+            "Uncaught TypeError: kaboom"
+        );
+    }
+
+    #[test]
+    fn test_throw_catch_finally() {
+        // catch で投げ直した値で、捕捉したエラーを確かめる
+        assert_eq!(
+            run(r#"try { throw new TypeError("bad"); } catch (e) { throw e.message; }"#),
+            "Uncaught bad"
+        );
+        // ランタイムが投げるエラーも捕捉できる
+        assert_eq!(
+            run(r#"try { new Intl.Locale("en-US!!"); } catch (e) { throw e.name; }"#),
+            "Uncaught RangeError"
+        );
+        // catch の引数は省略でき、例外が無ければ finally だけが実行される
+        assert_eq!(
+            run(r#"try { throw 1; } catch { throw "caught"; }"#),
+            "Uncaught caught"
+        );
+        assert_eq!(
+            run(r#"try { const a = 1; } catch (e) { throw "no"; } finally { throw "finally"; }"#),
+            "Uncaught finally"
+        );
+        assert_eq!(run(r#"try { throw 1; } catch (e) {} "after";"#), "after");
+        // 値はそのまま投げられる
+        assert_eq!(run(r#"throw "oops";"#), "Uncaught oops");
+        assert_eq!(
+            run(r#"try { throw 1; } finally { throw new Error("later"); }"#),
+            "Uncaught Error: later"
+        );
+        assert_eq!(
+            run(r#"const e = new RangeError("x"); e.name;"#),
+            "RangeError"
+        );
+        assert_eq!(run(r#"new RangeError("x");"#), "RangeError: x");
+        // finally の return は try の return を上書きする
+        assert_eq!(
+            run("function f() { try { return 1; } finally { return 2; } } f();"),
+            "2"
+        );
+        assert_eq!(
+            run(r#"let e = RangeError("x"); e.name + " " + e;"#),
+            "RangeError RangeError: x"
+        );
+    }
+
+    #[test]
+    fn test_error_objects_are_shared() {
+        // エラーはプロパティを追加でき、参照で共有される
+        assert_eq!(
+            run(r#"
+                let caught;
+                try { throw new TypeError("bad"); } catch (e) { e.code = 5; caught = e; }
+                `${caught.code} ${caught === caught} ${new Error("x") === new Error("x")}`;
+            "#),
+            "5 true false"
+        );
+        assert_eq!(
+            run(r#"const e = new Error("m"); e.name = "Custom"; `${e}`;"#),
+            "Custom: m"
+        );
+        assert_eq!(run(r#"`${new Error()}`;"#), "Error");
+    }
+
+    #[test]
+    fn test_string_literals() {
+        assert_eq!(run(r#"'single';"#), "single");
+        assert_eq!(run(r#""\x41B\u{1F600}\t|\'\"\\";"#), "AB😀\t|'\"\\");
+        assert_eq!(run(r#"'\0';"#), "\0");
+        // 行継続は文字列に含まれない
+        assert_eq!(run("'a\\\nb';"), "ab");
+        assert_eq!(
+            run(r#"const n = 2; `${n} items\n${`nested ${n}`}`;"#),
+            "2 items\nnested 2"
+        );
+        assert_eq!(run("`\\u{48}i ${'`'}`;"), "Hi `");
+    }
+
+    #[test]
+    fn test_declarations_and_scopes() {
+        // var は巻き上げられ、宣言前は undefined
+        assert_eq!(
+            run(r#"const before = v; var v = 1; before + " " + v;"#),
+            "undefined 1"
+        );
+        // let と const は宣言前に参照すると ReferenceError (TDZ)
+        assert_eq!(
+            run("x; let x = 1;"),
+            "Uncaught ReferenceError: Cannot access 'x' before initialization"
+        );
+        assert_eq!(
+            run("{ y; const y = 1; }"),
+            "Uncaught ReferenceError: Cannot access 'y' before initialization"
+        );
+        // ブロックの let は外側の変数を隠す
+        assert_eq!(run("let a = 1; { let a = 2; a += 3; } a;"), "1");
+        assert_eq!(
+            run("let n = 10; n -= 4; n *= 2; n **= 2; n %= 100; n;"),
+            "44"
+        );
+        assert_eq!(
+            run("const c = 1; c = 2;"),
+            "Uncaught TypeError: Assignment to constant variable."
+        );
+    }
+
+    #[test]
+    fn test_control_flow() {
+        assert_eq!(
+            run(
+                r#"let s = ""; for (let i = 0; i < 5; i++) { if (i % 2) continue; else s += i; } s;"#
+            ),
+            "024"
+        );
+        assert_eq!(
+            run(
+                "let n = 0; while (true) { if (++n >= 3) break; } do { n += 10; } while (n < 20); n;"
+            ),
+            "23"
+        );
+        assert_eq!(
+            run(
+                r#"let s = ""; for (const k in { a: 1, b: 2 }) s += k; for (const c of "34") s += c; s;"#
+            ),
+            "ab34"
+        );
+        // case はフォールスルーし、該当がなければ default に進む
+        assert_eq!(
+            run(r#"
+                function f(x) {
+                    let s = "";
+                    switch (x) { case 1: s += "one "; case 2: s += "two"; break; default: s = "other"; }
+                    return s;
+                }
+                `${f(1)}|${f(2)}|${f(3)}`;
+            "#),
+            "one two|two|other"
+        );
+        // ラベル付きの break と continue は外側のループに作用する
+        assert_eq!(
+            run(r#"
+                let s = "";
+                outer: for (let i = 0; i < 3; i++) {
+                    for (let j = 0; j < 3; j++) {
+                        if (j === 1) continue outer;
+                        if (i === 2) break outer;
+                        s += `${i}${j} `;
+                    }
+                }
+                s;
+            "#),
+            "00 10 "
+        );
+        assert_eq!(
+            run(r#"block: { break block; "unreachable"; } "done";"#),
+            "done"
+        );
+    }
+
+    #[test]
+    fn test_literals_and_unbound_identifiers() {
+        assert_eq!(
+            run(
+                "[typeof undefined, typeof null, typeof true, null ?? \"default\", !false].join();"
+            ),
+            "undefined,object,boolean,default,true"
+        );
+        assert_eq!(
+            run("missing + 1;"),
+            "Uncaught ReferenceError: missing is not defined"
+        );
+        // typeof は宣言されていない名前でも ReferenceError にならない
+        assert_eq!(run("typeof missing;"), "undefined");
+        assert_eq!(
+            run("let e; try { missing; } catch (error) { e = error.name; } e;"),
+            "ReferenceError"
+        );
+        // リテラルで始まる文
+        assert_eq!(
+            run("null.y;"),
+            "Uncaught TypeError: Cannot read properties of null (reading 'y')"
+        );
+        assert_eq!(run("true ? 1 : 2;"), "1");
+        assert_eq!(run(r#"false || "x";"#), "x");
+        assert_eq!(run(r#"false || console.log("x")"#), "undefined");
+        assert_eq!(run("null;"), "null");
+    }
+
+    #[test]
+    fn test_global_object() {
+        assert_eq!(
+            run(
+                "[typeof Intl.MessageFormat, typeof globalThis.Intl, globalThis.Intl === Intl].join();"
+            ),
+            "function,object,true"
+        );
+        // 組み込みのコンストラクタも値として渡せる
+        assert_eq!(
+            run(r#"const C = Intl.Locale; "" + new C("en-us");"#),
+            "en-US"
+        );
+        assert_eq!(
+            run("Intl.MessageFormat();"),
+            "Uncaught TypeError: Constructor Intl.MessageFormat requires 'new'"
+        );
+    }
+
+    #[test]
+    fn test_format_errors() {
+        // 位置はバイトではなく UTF-16 のオフセット
+        let source = r#"
+            const mf = new Intl.MessageFormat("en", "日本 {$x}", { bidiIsolation: "none" });
+            const errors = [];
+            const output = mf.format({}, (error) => errors.push(error));
+            const error = errors[0];
+        "#;
+        assert_eq!(run(&format!("{} output;", source)), "日本 {$x}");
+        assert_eq!(run(&format!("{} errors.length;", source)), "1");
+        assert_eq!(
+            run(&format!(
+                "{} [error.type, error.message, error.start, error.end, error.source].join();",
+                source
+            )),
+            "unresolved-variable,unresolved-variable: $x at 3,3,7,{$x}"
+        );
+        // onError がなければエラーを投げる
+        assert_eq!(
+            run(r#"new Intl.MessageFormat("en", "日本 {$x}").format({});"#),
+            "Uncaught Error: unresolved-variable: $x at 3"
+        );
+        assert_eq!(
+            run(
+                r#"let m; try { new Intl.MessageFormat("en", "{$x}").format({}); } catch (e) { m = e.message; } m;"#
+            ),
+            "unresolved-variable: $x at 0"
+        );
+        // 構築時の構文エラーの位置も UTF-16 のオフセット
+        assert_eq!(
+            run(r#"new Intl.MessageFormat("en", "日本語 {$x");"#),
+            "Uncaught SyntaxError: syntax-error at 7"
+        );
+    }
+
+    #[test]
+    fn test_number_formatting() {
+        let format = |source: &str| {
+            run(&format!(
+                r#"new Intl.MessageFormat("en", "{}", {{ bidiIsolation: "none" }}).format({{ x: -0, y: 4.5, z: -2.5 }});"#,
+                source
+            ))
+        };
+        assert_eq!(format("{$x :number}"), "-0");
+        assert_eq!(format("{$y :integer} {$z :integer}"), "4 -2");
+    }
+
+    #[test]
+    fn test_user_defined_to_primitive() {
+        assert_eq!(
+            run(r#"const s = { toString: () => "custom" } + ""; s;"#),
+            "custom"
+        );
+        let object = r#"const o = { valueOf: () => 42, toString: () => "str" };"#;
+        // + は valueOf を先に、テンプレートは toString を先に呼ぶ
+        assert_eq!(run(&format!("{} o + 1;", object)), "43");
+        assert_eq!(run(&format!("{} `${{o}}`;", object)), "str");
+        assert_eq!(
+            run(&format!("{} [o == 42, o === 42, -o, o > 41];", object)),
+            "true,false,-42,true"
+        );
+        assert_eq!(run("({}) + 1;"), "[object Object]1");
+        assert_eq!(
+            run(r#"({ toString: () => { throw new Error("no"); } }) + "";"#),
+            "Uncaught Error: no"
+        );
+        // valueOf も toString もオブジェクトを返すと変換できない
+        assert_eq!(
+            run("({ valueOf: () => ({}), toString: () => ({}) }) + 1;"),
+            "Uncaught TypeError: Cannot convert object to primitive value"
+        );
+        assert_eq!(
+            run(r#"`${{ toString: () => [] }}`;"#),
+            "Uncaught TypeError: Cannot convert object to primitive value"
+        );
+        assert_eq!(run("({ valueOf: () => ({}) }) + 1;"), "[object Object]1");
+    }
+
+    #[test]
+    fn test_immediately_invoked_arrow_functions() {
+        assert_eq!(run("const r = (x => x + 1)(7); r;"), "8");
+        assert_eq!(run("((a, b) => a + b)(1, 2);"), "3");
+        assert_eq!(run("(() => 7)();"), "7");
+        assert_eq!(run("(() => ({ a: 1 }))().a;"), "1");
+        // 括弧のないアロー関数の本体は呼び出しを含む式
+        assert_eq!(run("const f = () => (x) => x * 2; f()(4);"), "8");
+    }
+
+    #[test]
+    fn test_call_depth_limit() {
+        assert_eq!(
+            run("function f() { return f(); } f();"),
+            "Uncaught RangeError: Maximum call stack size exceeded"
+        );
+        // 捕捉した後は再び呼び出せる
+        assert_eq!(
+            run(r#"
+                function f(n) { return n === 0 ? 0 : 1 + f(n - 1); }
+                let name;
+                try { f(1e6); } catch (e) { name = e.name; }
+                name + " " + f(1000);
+            "#),
+            "RangeError 1000"
+        );
+        // 入れ子の深い式を再帰で評価しても、呼び出しの数によらずスタックの残りで止める
+        let nested = format!(
+            "function g(n) {{ return n === 0 ? 0 : {}g(n - 1){}; }} g(1e6);",
+            "[".repeat(50),
+            "]".repeat(50)
+        );
+        assert_eq!(
+            run(&nested),
+            "Uncaught RangeError: Maximum call stack size exceeded"
+        );
+    }
+
+    #[test]
+    fn test_array_literals() {
+        assert_eq!(run("const a = [1, , 3]; a.length + \" \" + a;"), "3 1,,3");
+        assert_eq!(run("[, ].length + [1, 2, ].length;"), "3");
+        assert_eq!(run("[1, [2, 3]].length;"), "2");
+    }
+
+    #[test]
+    fn test_array_holes() {
+        // 穴は読むと undefined だが、forEach や for...in では飛ばす
+        assert_eq!(
+            run(r#"
+                const a = [1, , 3];
+                let seen = "";
+                a.forEach((x, i) => { seen += i; });
+                for (const key in a) seen += key;
+                `${a[1]} ${seen} ${a.map(x => x * 2).join()} ${a.filter(() => true).length}`;
+            "#),
+            "undefined 0202 2,,6 2"
+        );
+        assert_eq!(
+            run("const a = []; a[2] = 1; a.length = 4; a.reduce((s, x) => s + x, 0) + a.join();"),
+            "1,,1,"
+        );
+        assert_eq!(
+            run("let s = \"\"; for (const x of [, 1]) s += x; [, undefined].includes() + s;"),
+            "trueundefined1"
+        );
+    }
+
+    #[test]
+    fn test_huge_array_indices() {
+        assert_eq!(
+            run("const a = []; a[4294967294] = 1;"),
+            "Uncaught RangeError: Array length 4294967295 exceeds the supported maximum"
+        );
+        assert_eq!(
+            run("const a = []; a.length = 4e9;"),
+            "Uncaught RangeError: Array length 4000000000 exceeds the supported maximum"
+        );
+        assert_eq!(
+            run("const a = []; a.length = 2 ** 32;"),
+            "Uncaught RangeError: Invalid array length"
+        );
+        // 2^32 - 1 は添字ではない
+        assert_eq!(run("const a = [1]; a[\"4294967295\"];"), "undefined");
+    }
+
+    #[test]
+    fn test_strict_equality_of_objects() {
+        // Intl のオブジェクトも中身ではなく同一性で比較する
+        assert_eq!(
+            run(r#"
+                const a = new Intl.Locale("en");
+                `${a === a} ${a === new Intl.Locale("en")} ${new Intl.Locale(a) === a}`;
+            "#),
+            "true false false"
+        );
+        assert_eq!(
+            run(r#"
+                const mf = new Intl.MessageFormat("en", "x");
+                const same = mf;
+                `${mf === same} ${mf === new Intl.MessageFormat("en", "x")}`;
+            "#),
+            "true false"
+        );
+        assert_eq!(
+            run("const f = () => 1; `${f === f} ${f === (() => 1)}`;"),
+            "true false"
+        );
+    }
+
+    #[test]
+    fn test_cyclic_arrays_join_as_empty() {
+        assert_eq!(run("const a = [1]; a.push(a); a.join();"), "1,");
+        assert_eq!(run("const a = [1, 2]; a.push([3, a]); `${a}`;"), "1,2,3,");
+        // 循環でなければ同じ配列を何度でも連結できる
+        assert_eq!(run("const b = [1]; [b, b].join(\"|\");"), "1|1");
+    }
+
+    #[test]
+    fn test_member_access_on_literals() {
+        assert_eq!(run("const x = { a: 1 }.a; x;"), "1");
+        assert_eq!(run("`${ { a: 2 }.a }`;"), "2");
+        assert_eq!(run(r#"const x = { a: 1 }["a"]; x;"#), "1");
+        assert_eq!(run(r#"const x = "abc"[1]; x;"#), "b");
+        assert_eq!(run(r#"[1, 2].length + "😀".length;"#), "4");
     }
 }
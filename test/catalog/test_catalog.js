@@ -0,0 +1,2 @@
+catalog.format("greeting", "ja-JP", { name: "Saji" });
+catalog.format("items", "ja-JP", { count: 3 });
@@ -0,0 +1,20 @@
+const locales = Intl.getCanonicalLocales(["EN-us", "ja-jp", "fr"]);
+const messages = locales.map(
+  (locale) => new Intl.MessageFormat(locale, "{$count :number} {$locale}", { bidiIsolation: "none" }),
+);
+
+const results = [];
+messages.forEach((mf, i) => {
+  results.push(mf.format({ count: 1234.5, locale: locales[i] }));
+});
+
+const functions = messages[0].resolvedOptions().functions;
+const summary = [
+  results.join(" / "),
+  `${results.length} results`,
+  `has :number ${functions.includes("number")}`,
+  `first two ${locales.slice(0, 2)}`,
+  `total ${[1, 2, 3].filter((n) => n % 2 === 1).reduce((sum, n) => sum + n, 0)}`,
+];
+summary.pop();
+summary.join("; ");
@@ -0,0 +1,7 @@
+// Windows (CRLF) line endings, tabs and comments
+/* Turn off bidi isolation so the
+   output has no FSI/PDI characters. */
+const mf = new Intl.MessageFormat("en", "Hello {$place}!", {
+	bidiIsolation: "none", // "default" wraps {$place}
+});
+mf.format({ place: "World" }); /* done */
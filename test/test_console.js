@@ -0,0 +1,17 @@
+// console.log は引数をいくつでも受け取り、一度に1行ずつ出力する
+const locales = ["en", "fr", "ja"];
+const messages = locales.map((locale) => new Intl.MessageFormat(locale, "{$count :number} messages", { bidiIsolation: "none" }));
+
+console.log("formatters:", messages[0]);
+messages.forEach((mf, index) => {
+  console.log("%s: %s", locales[index], mf.format({ count: 1234.5 }));
+});
+
+// オブジェクトや配列は Node.js と同じように中身を表示する
+console.log({ locales, options: messages[2].resolvedOptions() });
+
+// 表にしてまとめて比べる
+console.table(messages.map((mf) => ({ locale: mf.resolvedOptions().locale, text: mf.format({ count: 1 }) })));
+
+// エラーは標準エラー出力に書き出す
+console.warn("unresolved:", messages[0].format({}, (error) => console.error(error.message)));
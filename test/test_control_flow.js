@@ -0,0 +1,56 @@
+const mf = new Intl.MessageFormat(
+  "en",
+  ".input {$n :number} .match $n 0 {{none}} one {{{$n} item}} * {{{$n} items}}",
+  { bidiIsolation: "none" },
+);
+
+// 0..100 の複数形をすべて確かめる
+let failures = "";
+for (let n = 0; n <= 100; n++) {
+  let expected;
+  if (n === 0) {
+    expected = "none";
+  } else if (n === 1) {
+    expected = "1 item";
+  } else {
+    expected = `${n} items`;
+  }
+  if (mf.format({ n: n }) !== expected) failures += ` ${n}`;
+}
+
+let seen = "";
+for (const word of ["a", "b", "c"]) seen += word;
+for (const key in { x: 1, y: 2 }) seen += key;
+
+let i = 0;
+while (true) {
+  i += 1;
+  if (i < 3) continue;
+  break;
+}
+do {
+  i--;
+} while (i > 0);
+
+function kind(count) {
+  switch (count) {
+    case 0:
+      return "zero";
+    case 1:
+    case 2:
+      return "few";
+    default:
+      return "many";
+  }
+}
+
+let pairs = 0;
+outer: for (let a = 0; a < 5; a++) {
+  for (let b = 0; b < 5; b++) {
+    if (b > a) continue outer;
+    if (a === 4) break outer;
+    pairs++;
+  }
+}
+
+`failures:${failures || " none"} seen: ${seen} i: ${i} kinds: ${kind(0)} ${kind(2)} ${kind(7)} pairs: ${pairs}`;
@@ -0,0 +1,9 @@
+try {
+  new Intl.Locale("en-US!!");
+} catch (e) {
+  const name = e.name;
+} finally {
+  const x = 1;
+}
+const locale = new Intl.Locale("en-US");
+locale.baseName;
@@ -0,0 +1,35 @@
+const greeting = format("Hello, {$name}!", { name: "World" });
+
+function format(source, args, locale = "en") {
+  const mf = new Intl.MessageFormat(locale, source, { bidiIsolation: "none" });
+  return mf.format(args);
+}
+
+function counter() {
+  let count = 0;
+  return () => {
+    count += 1;
+    return count;
+  };
+}
+
+const next = counter();
+next();
+next();
+
+const locales = function (first, ...rest) {
+  return Intl.getCanonicalLocales(rest);
+};
+
+const twice = (f) => (x) => f(f(x));
+const addOne = x => x + 1;
+const fact = function fact(n) {
+  return n <= 1 ? 1 : n * fact(n - 1);
+};
+
+format("{$greeting} {$locales} {$count :number} {$total :number}", {
+  greeting: greeting,
+  locales: locales("ignored", "EN-us"),
+  count: next(),
+  total: twice(addOne)(fact(5)),
+});
@@ -0,0 +1,7 @@
+// 組み込みのコンストラクタも普通の値として変数に入れたり渡したりできる
+const MF = Intl.MessageFormat;
+const create = (Constructor, source) => new Constructor("en", source, { bidiIsolation: "none" });
+const mf = create(MF, "{$count :number} {$kind}");
+
+const kinds = [typeof Intl, typeof MF, typeof globalThis.Intl.Locale, globalThis.Intl === Intl];
+mf.format({ count: 1234, kind: "messages" }) + " / " + kinds.join(" ");
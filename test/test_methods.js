@@ -0,0 +1,17 @@
+// メソッドはプロトタイプから探すので、すべての配列や MessageFormat で同じ関数を共有する
+const mf = new Intl.MessageFormat("en", "{$n :number} {$unit}", { bidiIsolation: "none" });
+const sameFormat = mf.format === new Intl.MessageFormat("fr", "{$n}").format;
+const samePush = [].push === [1, 2].push;
+
+// 組み込み関数もコールバックとして渡せる
+const locales = ["EN-us", "ja-jp"].map(Intl.getCanonicalLocales).join(" ");
+
+// this の無い呼び出しは TypeError になる
+const format = mf.format;
+let error;
+try {
+  format({ n: 1 });
+} catch (e) {
+  error = e.name;
+}
+mf.format({ n: 1234, unit: "km" }, undefined) + " " + [sameFormat, samePush, locales, error].join(" ");
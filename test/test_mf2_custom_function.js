@@ -0,0 +1,10 @@
+const mf = new Intl.MessageFormat("en", "{$name :tag kind=greeting} from {|ja| :locale}", {
+  bidiIsolation: "none",
+  functions: {
+    tag: (ctx, options, input) => options.kind,
+    locale: (ctx, options, input) => {
+      return ctx.locales;
+    }
+  }
+});
+mf.format({ name: "World" });
@@ -0,0 +1,2 @@
+const mf = new Intl.MessageFormat(["xx-YY", "fr-CA", "en"], "{$count :number} messages");
+mf.format({ count: 1234 });
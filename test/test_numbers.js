@@ -0,0 +1,2 @@
+const mf = new Intl.MessageFormat("en", ".input {$n :number} .match $n one {{{$n} item}} * {{{$n} items}}", { bidiIsolation: "none" });
+mf.format({ n: 1.5 });
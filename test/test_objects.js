@@ -0,0 +1,12 @@
+const defaults = { bidiIsolation: "none", locale: "en" };
+const options = { __proto__: defaults, locale: "fr" };
+const mf = new Intl.MessageFormat(options.locale, "{$user} a {$count :number} messages", options);
+
+// 同じオブジェクトを別の名前から書き換える
+const user = { name: "Ada", stats: { unread: 0 } };
+const alias = user;
+alias.stats.unread += 1234;
+
+const field = "count";
+const args = { user: user.name, [field]: user.stats["unread"] };
+mf.format(args);
@@ -0,0 +1,3 @@
+const count = 3;
+const mf = new Intl.MessageFormat("en", "{$summary}", { bidiIsolation: "none" });
+mf.format({ summary: `${count} × 1.5 = ${count * 1.5}, ${count > 1 ? "many" : "one"}, ${10n ** 20n}` });
@@ -0,0 +1,3 @@
+const name = 'W\x6frld';
+const mf = new Intl.MessageFormat('en', `Hello {$name}!\u{1F600}`, { bidiIsolation: 'none' });
+mf.format({ name: `${name} "1"` });
@@ -0,0 +1,12 @@
+const mf = new Intl.MessageFormat("en", "{$name} has {$count :number} messages", { bidiIsolation: "none" });
+
+// 値が無い引数は undefined になり、?? で既定値に置き換えられる
+function describe(name, count) {
+  return mf.format({ name: name ?? "Someone", count }, () => {});
+}
+
+const known = describe("Ada", 3);
+// count が undefined なので {$count} は未解決の変数として出力される
+const unknown = describe(null);
+const kinds = [typeof undefined, typeof null, typeof missing, null == undefined, null === undefined];
+known + " / " + unknown + " / " + kinds.join(" ");
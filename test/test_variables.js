@@ -0,0 +1,9 @@
+let total = 0;
+var label = "items";
+{
+  let total = 100;
+  label += ` (block saw ${total})`;
+}
+total += 3;
+const mf = new Intl.MessageFormat("en", "{$total :number} {$label}", { bidiIsolation: "none" });
+mf.format({ total: total, label: label });